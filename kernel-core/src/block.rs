@@ -0,0 +1,146 @@
+//! Devices that store data in fixed size blocks, like disks
+
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use spin::Mutex;
+
+/// The size of a block, the sector size of most disks
+pub const BLOCK_SIZE: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Past the last block
+    OutOfRange,
+    /// The device reported an error
+    Io,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::OutOfRange => "block out of range",
+            Error::Io => "device error",
+        })
+    }
+}
+
+pub trait BlockDevice: Send + Sync {
+    fn block_count(&self) -> u64;
+
+    /// Reads the blocks starting at `block` into `buf`, whose length is a
+    /// multiple of `BLOCK_SIZE`
+    fn read_blocks(&self, block: u64, buf: &mut [u8]) -> Result<(), Error>;
+
+    /// Writes `buf`, whose length is a multiple of `BLOCK_SIZE`, to the blocks
+    /// starting at `block`
+    fn write_blocks(&self, block: u64, buf: &[u8]) -> Result<(), Error>;
+}
+
+/// Reads `buf.len()` bytes from `offset`, which needn't be block aligned
+pub fn read_bytes(device: &dyn BlockDevice, offset: u64, buf: &mut [u8]) -> Result<(), Error> {
+    let mut block = [0; BLOCK_SIZE];
+    let mut done = 0;
+    while done < buf.len() {
+        let position = offset + done as u64;
+        let start = (position % BLOCK_SIZE as u64) as usize;
+        let len = (BLOCK_SIZE - start).min(buf.len() - done);
+        device.read_blocks(position / BLOCK_SIZE as u64, &mut block)?;
+        buf[done..done + len].copy_from_slice(&block[start..start + len]);
+        done += len;
+    }
+    Ok(())
+}
+
+/// Writes `buf` at `offset`, reading back the blocks it only covers partly
+pub fn write_bytes(device: &dyn BlockDevice, offset: u64, buf: &[u8]) -> Result<(), Error> {
+    let mut block = [0; BLOCK_SIZE];
+    let mut done = 0;
+    while done < buf.len() {
+        let position = offset + done as u64;
+        let start = (position % BLOCK_SIZE as u64) as usize;
+        let len = (BLOCK_SIZE - start).min(buf.len() - done);
+        let number = position / BLOCK_SIZE as u64;
+        if len < BLOCK_SIZE {
+            device.read_blocks(number, &mut block)?;
+        }
+        block[start..start + len].copy_from_slice(&buf[done..done + len]);
+        device.write_blocks(number, &block)?;
+        done += len;
+    }
+    Ok(())
+}
+
+/// A disk in memory
+pub struct RamDisk {
+    data: Mutex<Vec<u8>>,
+}
+
+impl RamDisk {
+    /// `blocks` blocks of zeros
+    pub fn new(blocks: u64) -> Self {
+        Self::from_vec(vec![0; blocks as usize * BLOCK_SIZE])
+    }
+
+    /// A disk holding `data`, padded with zeros to a whole block
+    pub fn from_vec(mut data: Vec<u8>) -> Self {
+        data.resize(data.len().next_multiple_of(BLOCK_SIZE), 0);
+        Self {
+            data: Mutex::new(data),
+        }
+    }
+
+    /// A copy of the contents
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.data.lock().clone()
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn block_count(&self) -> u64 {
+        (self.data.lock().len() / BLOCK_SIZE) as u64
+    }
+
+    fn read_blocks(&self, block: u64, buf: &mut [u8]) -> Result<(), Error> {
+        let data = self.data.lock();
+        let start = block as usize * BLOCK_SIZE;
+        let blocks = data
+            .get(start..start + buf.len())
+            .ok_or(Error::OutOfRange)?;
+        buf.copy_from_slice(blocks);
+        Ok(())
+    }
+
+    fn write_blocks(&self, block: u64, buf: &[u8]) -> Result<(), Error> {
+        let mut data = self.data.lock();
+        let start = block as usize * BLOCK_SIZE;
+        let blocks = data
+            .get_mut(start..start + buf.len())
+            .ok_or(Error::OutOfRange)?;
+        blocks.copy_from_slice(buf);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unaligned_bytes() {
+        let disk = RamDisk::new(3);
+        let data: Vec<u8> = (0..600).map(|byte| byte as u8).collect();
+        write_bytes(&disk, 300, &data).unwrap();
+
+        let contents = disk.to_vec();
+        assert!(contents[..300].iter().all(|&byte| byte == 0));
+        assert_eq!(&contents[300..900], &data[..]);
+        assert!(contents[900..].iter().all(|&byte| byte == 0));
+
+        let mut buf = [0; 10];
+        read_bytes(&disk, 505, &mut buf).unwrap();
+        assert_eq!(&buf[..], &data[205..215]);
+        assert_eq!(write_bytes(&disk, 1530, &[1; 8]), Err(Error::OutOfRange));
+        assert_eq!(disk.block_count(), 3);
+    }
+}
@@ -20,3 +20,16 @@ impl Color {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_is_stored_as_bgr() {
+        // The framebuffer expects blue in the lowest byte
+        assert_eq!(Color::hex(0x123456).value(), [0x56, 0x34, 0x12, 0]);
+        // Bits above the 24 bit color are ignored
+        assert_eq!(Color::hex(0xaa00_00ff).value(), [0xff, 0, 0, 0]);
+    }
+}
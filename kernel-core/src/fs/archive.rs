@@ -0,0 +1,192 @@
+//! A tar or cpio archive as a read only file system, for the initrd
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+
+use super::{DirEntry, Error, FileSystem, FileType, InodeId, Metadata, Result};
+use crate::initrd::{self, Archive, Entry, Kind};
+
+const ROOT: InodeId = 0;
+
+struct Node {
+    // `None` for directories that are only implied by the paths of their files
+    entry: Option<Entry<'static>>,
+    children: BTreeMap<String, InodeId>,
+}
+
+/// The files of an archive, read in place. The inode numbers are indices into
+/// the directory tree built when it is created.
+pub struct ArchiveFs {
+    nodes: Vec<Node>,
+}
+
+impl ArchiveFs {
+    /// Indexes the entries of `archive`. Devices, hard links and such are left
+    /// out, and a later entry replaces an earlier one with the same path.
+    pub fn new(archive: Archive<'static>) -> core::result::Result<Self, initrd::Error> {
+        let mut fs = Self {
+            nodes: alloc::vec![Node {
+                entry: None,
+                children: BTreeMap::new(),
+            }],
+        };
+        for entry in archive.entries() {
+            let entry = entry?;
+            if entry.kind == Kind::Other {
+                continue;
+            }
+            let path = format!("{}", entry.path());
+            let mut inode = ROOT;
+            for name in path.split('/').filter(|name| !name.is_empty()) {
+                inode = fs.child(inode, name);
+            }
+            fs.nodes[inode as usize].entry = Some(entry);
+        }
+        Ok(fs)
+    }
+
+    // The child called `name`, added as a directory if it is missing
+    fn child(&mut self, dir: InodeId, name: &str) -> InodeId {
+        if let Some(&inode) = self.nodes[dir as usize].children.get(name) {
+            return inode;
+        }
+        let inode = self.nodes.len() as InodeId;
+        self.nodes.push(Node {
+            entry: None,
+            children: BTreeMap::new(),
+        });
+        self.nodes[dir as usize]
+            .children
+            .insert(String::from(name), inode);
+        inode
+    }
+
+    fn node(&self, inode: InodeId) -> Result<&Node> {
+        self.nodes.get(inode as usize).ok_or(Error::NotFound)
+    }
+
+    fn file_type(node: &Node) -> FileType {
+        match node.entry.map(|entry| entry.kind) {
+            Some(Kind::File) => FileType::File,
+            Some(Kind::Symlink) => FileType::Symlink,
+            _ => FileType::Directory,
+        }
+    }
+}
+
+impl FileSystem for ArchiveFs {
+    fn name(&self) -> &'static str {
+        "archive"
+    }
+
+    fn root(&self) -> InodeId {
+        ROOT
+    }
+
+    fn lookup(&self, dir: InodeId, name: &str) -> Result<InodeId> {
+        let node = self.node(dir)?;
+        if Self::file_type(node) != FileType::Directory {
+            return Err(Error::NotADirectory);
+        }
+        node.children.get(name).copied().ok_or(Error::NotFound)
+    }
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata> {
+        let node = self.node(inode)?;
+        let file_type = Self::file_type(node);
+        let size = match (file_type, node.entry) {
+            (FileType::File, Some(entry)) => entry.data.len(),
+            (FileType::Symlink, Some(entry)) => entry.link_target().unwrap_or("").len(),
+            _ => node.children.len(),
+        };
+        Ok(Metadata {
+            inode,
+            file_type,
+            size: size as u64,
+            mode: node.entry.map_or(0o755, |entry| entry.mode),
+            mtime: node.entry.map_or(0, |entry| entry.mtime),
+        })
+    }
+
+    fn read(&self, inode: InodeId, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let node = self.node(inode)?;
+        let data = match (Self::file_type(node), node.entry) {
+            (FileType::File, Some(entry)) => entry.data,
+            (FileType::Directory, _) => return Err(Error::IsADirectory),
+            _ => return Err(Error::InvalidArgument),
+        };
+        let start = (offset as usize).min(data.len());
+        let read = buf.len().min(data.len() - start);
+        buf[..read].copy_from_slice(&data[start..start + read]);
+        Ok(read)
+    }
+
+    fn read_dir(&self, dir: InodeId) -> Result<Vec<DirEntry>> {
+        let node = self.node(dir)?;
+        if Self::file_type(node) != FileType::Directory {
+            return Err(Error::NotADirectory);
+        }
+        Ok(node
+            .children
+            .iter()
+            .map(|(name, &inode)| DirEntry {
+                name: name.clone(),
+                inode,
+                file_type: Self::file_type(&self.nodes[inode as usize]),
+            })
+            .collect())
+    }
+
+    fn read_link(&self, inode: InodeId) -> Result<String> {
+        let node = self.node(inode)?;
+        node.entry
+            .and_then(|entry| entry.link_target())
+            .map(String::from)
+            .ok_or(Error::InvalidArgument)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use super::super::{OpenFlags, TmpFs, Vfs};
+    use super::*;
+    use crate::initrd::testing::{tar, Member};
+
+    #[test]
+    fn archives_are_mounted_read_only() {
+        let data = tar(&[
+            Member::file("./etc/motd", b"hi"),
+            Member::dir("./etc/"),
+            Member::symlink("./motd", "etc/motd"),
+            Member::file("usr/share/implied", b""),
+        ]);
+        let archive = Archive::new(Vec::leak(data)).unwrap();
+
+        let vfs = Vfs::new();
+        vfs.mount("/", Arc::new(TmpFs::new())).unwrap();
+        vfs.create_dir("/initrd").unwrap();
+        vfs.mount("/initrd", Arc::new(ArchiveFs::new(archive).unwrap()))
+            .unwrap();
+
+        assert_eq!(vfs.read("/initrd/etc/motd").unwrap(), b"hi");
+        assert_eq!(vfs.read("/initrd/motd").unwrap(), b"hi");
+        assert_eq!(vfs.metadata("/initrd/etc").unwrap().mode, 0o755);
+        assert_eq!(vfs.metadata("/initrd/etc/motd").unwrap().size, 2);
+        let usr = vfs.metadata("/initrd/usr/share").unwrap();
+        assert_eq!(usr.file_type, FileType::Directory);
+        let names: Vec<String> = vfs
+            .read_dir("/initrd")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["etc", "motd", "usr"]);
+
+        assert_eq!(vfs.write("/initrd/new", b"").err(), Some(Error::ReadOnly));
+        let flags = OpenFlags::WRITE;
+        let mut file = vfs.open("/initrd/etc/motd", flags).unwrap();
+        assert_eq!(file.write(b"x"), Err(Error::ReadOnly));
+        assert_eq!(vfs.remove("/initrd/etc/motd"), Err(Error::ReadOnly));
+    }
+}
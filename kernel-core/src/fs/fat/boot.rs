@@ -0,0 +1,140 @@
+//! The boot sector, which says where the FATs, the root directory and the
+//! clusters are
+
+use super::super::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+impl FatType {
+    /// Entries at or above this end a cluster chain
+    pub fn end_of_chain(self) -> u32 {
+        match self {
+            FatType::Fat12 => 0xff8,
+            FatType::Fat16 => 0xfff8,
+            FatType::Fat32 => 0x0fff_fff8,
+        }
+    }
+
+    /// The entry written to end a chain
+    pub fn end_marker(self) -> u32 {
+        match self {
+            FatType::Fat12 => 0xfff,
+            FatType::Fat16 => 0xffff,
+            FatType::Fat32 => 0x0fff_ffff,
+        }
+    }
+}
+
+/// Where everything is on the volume, in bytes from its start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub fat_type: FatType,
+    pub cluster_size: u64,
+    pub fat_start: u64,
+    /// The size of one copy of the FAT
+    pub fat_size: u64,
+    pub fat_count: u64,
+    /// The fixed root directory of FAT12 and FAT16, empty on FAT32
+    pub root_dir_start: u64,
+    pub root_dir_size: u64,
+    pub data_start: u64,
+    /// Clusters are numbered from 2 to `cluster_count + 1`
+    pub cluster_count: u32,
+    /// The first cluster of the root directory on FAT32, 0 otherwise
+    pub root_cluster: u32,
+    /// The FSInfo sector of FAT32, which keeps a count of the free clusters
+    pub fs_info: Option<u64>,
+}
+
+fn u16_at(sector: &[u8], offset: usize) -> u64 {
+    u16::from_le_bytes([sector[offset], sector[offset + 1]]) as u64
+}
+
+fn u32_at(sector: &[u8], offset: usize) -> u64 {
+    u32::from_le_bytes(sector[offset..offset + 4].try_into().unwrap()) as u64
+}
+
+impl Layout {
+    /// Reads the BIOS parameter block. The FAT type follows from the number of
+    /// clusters alone, as the specification says, not from the type string.
+    pub fn parse(sector: &[u8; 512]) -> Result<Self> {
+        if sector[510..512] != [0x55, 0xaa] {
+            return Err(Error::InvalidArgument);
+        }
+        let bytes_per_sector = u16_at(sector, 11);
+        let sectors_per_cluster = sector[13] as u64;
+        let reserved_sectors = u16_at(sector, 14);
+        let fat_count = sector[16] as u64;
+        let root_entries = u16_at(sector, 17);
+        let total_sectors = match u16_at(sector, 19) {
+            0 => u32_at(sector, 32),
+            sectors => sectors,
+        };
+        let fat_sectors = match u16_at(sector, 22) {
+            0 => u32_at(sector, 36),
+            sectors => sectors,
+        };
+        if !matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved_sectors == 0
+            || fat_count == 0
+            || fat_sectors == 0
+        {
+            return Err(Error::InvalidArgument);
+        }
+
+        let root_dir_sectors = (root_entries * 32).div_ceil(bytes_per_sector);
+        let data_sector = reserved_sectors + fat_count * fat_sectors + root_dir_sectors;
+        let data_sectors = total_sectors
+            .checked_sub(data_sector)
+            .ok_or(Error::InvalidArgument)?;
+        let cluster_count = data_sectors / sectors_per_cluster;
+        let fat_type = match cluster_count {
+            0..4085 => FatType::Fat12,
+            4085..65525 => FatType::Fat16,
+            _ => FatType::Fat32,
+        };
+        let (root_cluster, fs_info) = match fat_type {
+            FatType::Fat32 => {
+                let root_cluster = u32_at(sector, 44) as u32;
+                if root_cluster < 2 || root_entries != 0 {
+                    return Err(Error::InvalidArgument);
+                }
+                let fs_info = match u16_at(sector, 48) {
+                    0 | 0xffff => None,
+                    fs_info => Some(fs_info * bytes_per_sector),
+                };
+                (root_cluster, fs_info)
+            }
+            _ => (0, None),
+        };
+
+        Ok(Self {
+            fat_type,
+            cluster_size: sectors_per_cluster * bytes_per_sector,
+            fat_start: reserved_sectors * bytes_per_sector,
+            fat_size: fat_sectors * bytes_per_sector,
+            fat_count,
+            root_dir_start: (data_sector - root_dir_sectors) * bytes_per_sector,
+            root_dir_size: root_entries * 32,
+            data_start: data_sector * bytes_per_sector,
+            cluster_count: cluster_count.min(0x0fff_fff5) as u32,
+            root_cluster,
+            fs_info,
+        })
+    }
+
+    /// Where `cluster`'s data starts
+    pub fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_start + (cluster as u64 - 2) * self.cluster_size
+    }
+
+    pub fn is_cluster(&self, cluster: u32) -> bool {
+        (2..self.cluster_count + 2).contains(&cluster)
+    }
+}
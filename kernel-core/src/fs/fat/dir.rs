@@ -0,0 +1,343 @@
+//! Directory entries: 32 byte short entries with an 8.3 name, each preceded
+//! by the long file name entries holding its full name in UTF-16, if it has one
+
+use alloc::{format, string::String, vec::Vec};
+
+pub const ENTRY_SIZE: u64 = 32;
+
+pub const ATTR_READ_ONLY: u8 = 0x01;
+pub const ATTR_VOLUME_ID: u8 = 0x08;
+pub const ATTR_DIRECTORY: u8 = 0x10;
+pub const ATTR_ARCHIVE: u8 = 0x20;
+const ATTR_LONG_NAME: u8 = 0x0f;
+
+/// The first byte of a deleted entry
+pub const DELETED: u8 = 0xe5;
+
+// The NT case flags: the base name or the extension is all lower case
+const LOWER_BASE: u8 = 0x08;
+const LOWER_EXTENSION: u8 = 0x10;
+
+// Where a long entry keeps its 13 UTF-16 characters
+const LONG_CHARS: [usize; 13] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// The entry holding everything but the long name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Short {
+    /// Space padded, without the dot
+    pub name: [u8; 11],
+    pub attr: u8,
+    pub case: u8,
+    pub first_cluster: u32,
+    pub size: u32,
+    pub time: u16,
+    pub date: u16,
+}
+
+fn u16_at(raw: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([raw[offset], raw[offset + 1]])
+}
+
+impl Short {
+    pub fn parse(raw: &[u8; 32]) -> Self {
+        let mut name = [0; 11];
+        name.copy_from_slice(&raw[..11]);
+        // 0xe5 is a valid first byte in some code pages, stored as 0x05
+        if name[0] == 0x05 {
+            name[0] = DELETED;
+        }
+        Self {
+            name,
+            attr: raw[11],
+            case: raw[12],
+            first_cluster: (u16_at(raw, 20) as u32) << 16 | u16_at(raw, 26) as u32,
+            size: u32::from_le_bytes(raw[28..32].try_into().unwrap()),
+            time: u16_at(raw, 22),
+            date: u16_at(raw, 24),
+        }
+    }
+
+    /// The entry as stored, with the creation and access times equal to the
+    /// modification time
+    pub fn encode(&self) -> [u8; 32] {
+        let mut raw = [0; 32];
+        raw[..11].copy_from_slice(&self.name);
+        if raw[0] == DELETED {
+            raw[0] = 0x05;
+        }
+        raw[11] = self.attr;
+        raw[12] = self.case;
+        raw[14..16].copy_from_slice(&self.time.to_le_bytes());
+        raw[16..18].copy_from_slice(&self.date.to_le_bytes());
+        raw[18..20].copy_from_slice(&self.date.to_le_bytes());
+        raw[20..22].copy_from_slice(&((self.first_cluster >> 16) as u16).to_le_bytes());
+        raw[22..24].copy_from_slice(&self.time.to_le_bytes());
+        raw[24..26].copy_from_slice(&self.date.to_le_bytes());
+        raw[26..28].copy_from_slice(&(self.first_cluster as u16).to_le_bytes());
+        raw[28..32].copy_from_slice(&self.size.to_le_bytes());
+        raw
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.attr & ATTR_DIRECTORY != 0
+    }
+
+    /// `BASE.EXT`, lower cased as the NT case flags say
+    pub fn display_name(&self) -> String {
+        let part = |bytes: &[u8], lower: bool| -> String {
+            let part = bytes.trim_ascii_end();
+            part.iter()
+                .map(|&byte| match lower {
+                    true => char::from(byte.to_ascii_lowercase()),
+                    false => char::from(byte),
+                })
+                .collect()
+        };
+        let base = part(&self.name[..8], self.case & LOWER_BASE != 0);
+        let extension = part(&self.name[8..], self.case & LOWER_EXTENSION != 0);
+        match extension.is_empty() {
+            true => base,
+            false => format!("{}.{}", base, extension),
+        }
+    }
+}
+
+/// The checksum of a short name, kept in its long entries to tie them to it
+pub fn checksum(name: &[u8; 11]) -> u8 {
+    name.iter()
+        .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte))
+}
+
+/// What a 32 byte slot of a directory holds
+pub enum Slot {
+    /// Nothing, and nothing after it either
+    End,
+    Deleted,
+    Long {
+        /// The position of this part of the name, from 1
+        order: u8,
+        /// Whether this is the last part, which is stored first
+        last: bool,
+        checksum: u8,
+        chars: [u16; 13],
+    },
+    Short(Short),
+}
+
+impl Slot {
+    pub fn parse(raw: &[u8; 32]) -> Self {
+        match raw[0] {
+            0 => Slot::End,
+            DELETED => Slot::Deleted,
+            _ if raw[11] & 0x3f == ATTR_LONG_NAME => Slot::Long {
+                order: raw[0] & 0x1f,
+                last: raw[0] & 0x40 != 0,
+                checksum: raw[13],
+                chars: LONG_CHARS.map(|offset| u16_at(raw, offset)),
+            },
+            _ => Slot::Short(Short::parse(raw)),
+        }
+    }
+}
+
+/// The long entries for `name`, in the order they are stored
+pub fn long_entries(name: &str, checksum: u8) -> Vec<[u8; 32]> {
+    let mut units: Vec<u16> = name.encode_utf16().collect();
+    let count = units.len().div_ceil(13);
+    // Terminated with a 0 unless it fills the last entry, then padded with 0xffff
+    if units.len() % 13 != 0 {
+        units.push(0);
+    }
+    units.resize(count * 13, 0xffff);
+
+    (0..count)
+        .rev()
+        .map(|index| {
+            let mut raw = [0; 32];
+            raw[0] = (index + 1) as u8;
+            if index == count - 1 {
+                raw[0] |= 0x40;
+            }
+            raw[11] = ATTR_LONG_NAME;
+            raw[13] = checksum;
+            for (unit, offset) in units[index * 13..].iter().zip(LONG_CHARS) {
+                raw[offset..offset + 2].copy_from_slice(&unit.to_le_bytes());
+            }
+            raw
+        })
+        .collect()
+}
+
+/// A long name put together from the characters of its entries
+pub fn decode_long_name(units: &[u16]) -> String {
+    let end = units
+        .iter()
+        .position(|&unit| unit == 0)
+        .unwrap_or(units.len());
+    char::decode_utf16(units[..end].iter().copied())
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Whether `name` can be stored as a long name
+pub fn valid_long_name(name: &str) -> bool {
+    name.encode_utf16().count() <= 255
+        && !name.chars().any(|c| c < ' ' || "\"*/:<>?\\|".contains(c))
+}
+
+// Allowed in short names besides upper case letters and digits
+fn short_char(byte: u8) -> bool {
+    byte.is_ascii_uppercase() || byte.is_ascii_digit() || b"!#$%&'()-@^_`{}~".contains(&byte)
+}
+
+/// The short name and case flags that store `name` exactly, if it is a plain
+/// 8.3 name in a single case per part, so it needs no long entries
+pub fn exact_short_name(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, extension) = match name.split_once('.') {
+        Some((_, "")) => return None,
+        Some((base, extension)) => (base, extension),
+        None => (name, ""),
+    };
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 {
+        return None;
+    }
+
+    let mut short = [b' '; 11];
+    let mut case = 0;
+    let (base_field, extension_field) = short.split_at_mut(8);
+    for (part, field, lower) in [
+        (base, base_field, LOWER_BASE),
+        (extension, extension_field, LOWER_EXTENSION),
+    ] {
+        let bytes = part.as_bytes();
+        if bytes.iter().any(u8::is_ascii_lowercase) {
+            if bytes.iter().any(u8::is_ascii_uppercase) {
+                return None;
+            }
+            case |= lower;
+        }
+        for (slot, byte) in field.iter_mut().zip(bytes) {
+            *slot = byte.to_ascii_uppercase();
+            if !short_char(*slot) {
+                return None;
+            }
+        }
+    }
+    Some((short, case))
+}
+
+/// The short alias of a long name, `BASE~N.EXT`, made from its upper cased
+/// first letters with everything a short name can't hold replaced by `_`
+pub fn short_alias(name: &str, tail: u32) -> [u8; 11] {
+    let name = name.trim_start_matches('.');
+    let (base, extension) = match name.rsplit_once('.') {
+        Some((base, extension)) => (base, extension),
+        None => (name, ""),
+    };
+    let convert = |part: &str| -> Vec<u8> {
+        part.chars()
+            .filter(|&c| c != ' ' && c != '.')
+            .map(
+                |c| match c.is_ascii() && short_char(c.to_ascii_uppercase() as u8) {
+                    true => c.to_ascii_uppercase() as u8,
+                    false => b'_',
+                },
+            )
+            .collect()
+    };
+
+    let tail = format!("~{}", tail);
+    let mut base = convert(base);
+    base.truncate(8 - tail.len());
+    base.extend_from_slice(tail.as_bytes());
+    let mut short = [b' '; 11];
+    short[..base.len()].copy_from_slice(&base);
+    for (slot, byte) in short[8..].iter_mut().zip(convert(extension)) {
+        *slot = byte;
+    }
+    short
+}
+
+// Days since 1970-01-01 for a date in the proleptic Gregorian calendar
+// (see http://howardhinnant.github.io/date_algorithms.html)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Seconds since the epoch for a FAT date and time, which count years from
+/// 1980 and seconds in twos
+pub fn unix_time(date: u16, time: u16) -> u64 {
+    let year = 1980 + (date >> 9) as i64;
+    let month = ((date >> 5) & 0xf).clamp(1, 12) as i64;
+    let day = (date & 0x1f).max(1) as i64;
+    let days = days_from_civil(year, month, day) as u64;
+    let seconds = (time >> 11) as u64 * 3600 + ((time >> 5) & 0x3f) as u64 * 60;
+    days * 86_400 + seconds + (time & 0x1f) as u64 * 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_names() {
+        assert_eq!(exact_short_name("README.TXT"), Some((*b"README  TXT", 0)));
+        let (short, case) = exact_short_name("readme.TXT").unwrap();
+        assert_eq!((&short, case), (b"README  TXT", LOWER_BASE));
+        assert_eq!(exact_short_name("ReadMe.txt"), None);
+        assert_eq!(exact_short_name("long-name.text"), None);
+        assert_eq!(exact_short_name("a.b.c"), None);
+        assert_eq!(exact_short_name("a+b"), None);
+
+        assert_eq!(&short_alias("long file name.text", 1), b"LONGFI~1TEX");
+        assert_eq!(&short_alias(".bashrc", 12), b"BASHR~12   ");
+        assert_eq!(&short_alias("été+1", 2), b"_T__1~2    ");
+
+        let short = Short::parse(
+            &Short {
+                name: short,
+                attr: ATTR_ARCHIVE,
+                case,
+                first_cluster: 0x12_3456,
+                size: 42,
+                time: 0,
+                date: 0x21,
+            }
+            .encode(),
+        );
+        assert_eq!(short.display_name(), "readme.TXT");
+        assert_eq!(short.first_cluster, 0x12_3456);
+        assert_eq!(unix_time(short.date, short.time), 315_532_800);
+    }
+
+    #[test]
+    fn long_names() {
+        let name = "A name that needs three entries";
+        let entries = long_entries(name, 0x5a);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0][0], 0x43);
+        assert_eq!(entries[2][0], 0x01);
+
+        let mut units = Vec::new();
+        for raw in entries.iter().rev() {
+            let Slot::Long {
+                checksum, chars, ..
+            } = Slot::parse(raw)
+            else {
+                panic!("not a long entry");
+            };
+            assert_eq!(checksum, 0x5a);
+            units.extend_from_slice(&chars);
+        }
+        assert_eq!(decode_long_name(&units), name);
+        // Exactly 13 characters have no terminator
+        assert_eq!(long_entries("thirteen char", 0).len(), 1);
+        assert!(!valid_long_name("what?"));
+    }
+}
@@ -0,0 +1,926 @@
+//! FAT12, FAT16 and FAT32 volumes, with long file names, on any block device.
+//!
+//! An inode number is the position on the volume of the short entry of the
+//! file, the root directory, which has none, is 0. Symlinks and permissions
+//! can't be stored, beyond the read only attribute. There is no clock to stamp
+//! files with yet, so new entries are dated 1980-01-01, the FAT epoch, and
+//! writes leave the dates alone.
+
+mod boot;
+mod dir;
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub use boot::FatType;
+
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+
+use spin::Mutex;
+
+use super::{DirEntry, Error, FileSystem, FileType, InodeId, Metadata, Result};
+use crate::block::{BlockDevice, BLOCK_SIZE};
+use boot::Layout;
+use dir::{Short, Slot, DELETED, ENTRY_SIZE};
+
+const ROOT: InodeId = 0;
+
+// 1980-01-01, for new entries
+const DEFAULT_DATE: u16 = 1 << 5 | 1;
+
+// The FSInfo signatures, at 0, 484 and 508
+const FS_INFO_SIGNATURES: [u32; 3] = [0x4161_5252, 0x6141_7272, 0xaa55_0000];
+
+// Where the slots of a directory are
+#[derive(Debug, Clone, Copy)]
+enum Dir {
+    /// The root directory of FAT12 and FAT16, between the FATs and the clusters
+    FixedRoot,
+    /// Any other directory, starting at this cluster
+    Chain(u32),
+}
+
+// A file found in a directory
+struct Found {
+    name: String,
+    short: Short,
+    /// Every slot of the file, the long entries then the short one
+    slots: Vec<u64>,
+}
+
+impl Found {
+    fn inode(&self) -> InodeId {
+        *self.slots.last().unwrap()
+    }
+
+    // Names are compared ignoring case, and the short alias works too
+    fn is(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.to_lowercase()
+            || self.short.display_name().eq_ignore_ascii_case(name)
+    }
+}
+
+// A long name being put together, from its last part to its first
+struct LongName {
+    units: Vec<u16>,
+    order: u8,
+    checksum: u8,
+    slots: Vec<u64>,
+}
+
+struct Volume {
+    device: Arc<dyn BlockDevice>,
+    layout: Layout,
+    /// Where the search for a free cluster starts
+    next_free: u32,
+    /// From the FSInfo sector, if it has a valid count
+    free_count: Option<u32>,
+    /// The last block read through it, since the FATs and directories are
+    /// read a few bytes at a time
+    cache: Option<(u64, [u8; BLOCK_SIZE])>,
+}
+
+impl Volume {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let mut done = 0;
+        while done < buf.len() {
+            let position = offset + done as u64;
+            let number = position / BLOCK_SIZE as u64;
+            let start = (position % BLOCK_SIZE as u64) as usize;
+            let whole = (buf.len() - done) / BLOCK_SIZE * BLOCK_SIZE;
+            // Whole blocks go straight into `buf`, the cache is kept up to
+            // date by `write_at` so it can't be newer
+            if start == 0 && whole > 0 {
+                self.device
+                    .read_blocks(number, &mut buf[done..done + whole])?;
+                done += whole;
+                continue;
+            }
+            if self.cache.map(|(cached, _)| cached) != Some(number) {
+                let mut block = [0; BLOCK_SIZE];
+                self.device.read_blocks(number, &mut block)?;
+                self.cache = Some((number, block));
+            }
+            let block = &self.cache.as_ref().unwrap().1;
+            let len = (BLOCK_SIZE - start).min(buf.len() - done);
+            buf[done..done + len].copy_from_slice(&block[start..start + len]);
+            done += len;
+        }
+        Ok(())
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        crate::block::write_bytes(&*self.device, offset, buf)?;
+        if let Some((number, block)) = &mut self.cache {
+            let block_start = *number * BLOCK_SIZE as u64;
+            let start = offset.max(block_start);
+            let end = (offset + buf.len() as u64).min(block_start + BLOCK_SIZE as u64);
+            if start < end {
+                block[(start - block_start) as usize..(end - block_start) as usize]
+                    .copy_from_slice(&buf[(start - offset) as usize..(end - offset) as usize]);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_u32(&mut self, offset: u64) -> Result<u32> {
+        let mut bytes = [0; 4];
+        self.read_at(offset, &mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    // The FAT entry of `cluster`, from the first FAT
+    fn fat_entry(&mut self, cluster: u32) -> Result<u32> {
+        let start = self.layout.fat_start;
+        Ok(match self.layout.fat_type {
+            FatType::Fat12 => {
+                // Two entries are packed into three bytes
+                let mut bytes = [0; 2];
+                self.read_at(start + cluster as u64 * 3 / 2, &mut bytes)?;
+                let pair = u16::from_le_bytes(bytes);
+                match cluster % 2 {
+                    0 => pair & 0xfff,
+                    _ => pair >> 4,
+                }
+                .into()
+            }
+            FatType::Fat16 => {
+                let mut bytes = [0; 2];
+                self.read_at(start + cluster as u64 * 2, &mut bytes)?;
+                u16::from_le_bytes(bytes).into()
+            }
+            FatType::Fat32 => self.read_u32(start + cluster as u64 * 4)? & 0x0fff_ffff,
+        })
+    }
+
+    // Sets the FAT entry of `cluster` in every FAT
+    fn set_fat_entry(&mut self, cluster: u32, value: u32) -> Result<()> {
+        for fat in 0..self.layout.fat_count {
+            let start = self.layout.fat_start + fat * self.layout.fat_size;
+            match self.layout.fat_type {
+                FatType::Fat12 => {
+                    let offset = start + cluster as u64 * 3 / 2;
+                    let mut bytes = [0; 2];
+                    self.read_at(offset, &mut bytes)?;
+                    let pair = u16::from_le_bytes(bytes);
+                    let value = value as u16 & 0xfff;
+                    let pair = match cluster % 2 {
+                        0 => pair & 0xf000 | value,
+                        _ => pair & 0x000f | value << 4,
+                    };
+                    self.write_at(offset, &pair.to_le_bytes())?;
+                }
+                FatType::Fat16 => {
+                    self.write_at(start + cluster as u64 * 2, &(value as u16).to_le_bytes())?
+                }
+                FatType::Fat32 => {
+                    // The top four bits are reserved, and kept
+                    let offset = start + cluster as u64 * 4;
+                    let value = self.read_u32(offset)? & 0xf000_0000 | value & 0x0fff_ffff;
+                    self.write_at(offset, &value.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The clusters of the chain starting at `first`, none if it is 0
+    fn chain(&mut self, first: u32) -> Result<Vec<u32>> {
+        let mut chain = Vec::new();
+        let mut cluster = first;
+        while cluster != 0 {
+            // A chain running outside the volume or longer than it is corrupt
+            if !self.layout.is_cluster(cluster) || chain.len() > self.layout.cluster_count as usize
+            {
+                return Err(Error::Io);
+            }
+            chain.push(cluster);
+            cluster = match self.fat_entry(cluster)? {
+                next if next >= self.layout.fat_type.end_of_chain() => 0,
+                0 => return Err(Error::Io),
+                next => next,
+            };
+        }
+        Ok(chain)
+    }
+
+    // Takes a free cluster, filled with zeros, and links it after `previous`
+    fn allocate(&mut self, previous: Option<u32>) -> Result<u32> {
+        let count = self.layout.cluster_count;
+        for index in 0..count {
+            let cluster = 2 + (self.next_free - 2 + index) % count;
+            if self.fat_entry(cluster)? != 0 {
+                continue;
+            }
+            self.write_at(
+                self.layout.cluster_offset(cluster),
+                &vec![0; self.layout.cluster_size as usize],
+            )?;
+            self.set_fat_entry(cluster, self.layout.fat_type.end_marker())?;
+            if let Some(previous) = previous {
+                self.set_fat_entry(previous, cluster)?;
+            }
+            self.next_free = 2 + (cluster - 1) % count;
+            if let Some(free) = &mut self.free_count {
+                *free = free.saturating_sub(1);
+            }
+            self.write_fs_info()?;
+            return Ok(cluster);
+        }
+        Err(Error::NoSpace)
+    }
+
+    fn free(&mut self, clusters: &[u32]) -> Result<()> {
+        for &cluster in clusters {
+            self.set_fat_entry(cluster, 0)?;
+        }
+        if let Some(free) = &mut self.free_count {
+            *free += clusters.len() as u32;
+        }
+        self.write_fs_info()
+    }
+
+    fn write_fs_info(&mut self) -> Result<()> {
+        let Some(offset) = self.layout.fs_info else {
+            return Ok(());
+        };
+        let mut counts = [0; 8];
+        counts[..4].copy_from_slice(&self.free_count.unwrap_or(u32::MAX).to_le_bytes());
+        counts[4..].copy_from_slice(&self.next_free.to_le_bytes());
+        self.write_at(offset + 488, &counts)
+    }
+
+    // The short entry of a file or directory other than the root
+    fn entry(&mut self, inode: InodeId) -> Result<Short> {
+        let mut raw = [0; 32];
+        self.read_at(inode, &mut raw)?;
+        match Slot::parse(&raw) {
+            // Removed since it was looked up
+            Slot::Short(short) if inode != ROOT => Ok(short),
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    fn write_entry(&mut self, inode: InodeId, short: &Short) -> Result<()> {
+        self.write_at(inode, &short.encode())
+    }
+
+    fn dir(&mut self, inode: InodeId) -> Result<Dir> {
+        if inode == ROOT {
+            return Ok(match self.layout.fat_type {
+                FatType::Fat32 => Dir::Chain(self.layout.root_cluster),
+                _ => Dir::FixedRoot,
+            });
+        }
+        match self.entry(inode)? {
+            short if short.is_directory() => Ok(Dir::Chain(short.first_cluster)),
+            _ => Err(Error::NotADirectory),
+        }
+    }
+
+    // The position of every slot of `dir`
+    fn slots(&mut self, dir: Dir) -> Result<Vec<u64>> {
+        Ok(match dir {
+            Dir::FixedRoot => (0..self.layout.root_dir_size / ENTRY_SIZE)
+                .map(|index| self.layout.root_dir_start + index * ENTRY_SIZE)
+                .collect(),
+            Dir::Chain(first) => {
+                let layout = self.layout;
+                self.chain(first)?
+                    .into_iter()
+                    .flat_map(|cluster| {
+                        let start = layout.cluster_offset(cluster);
+                        (0..layout.cluster_size / ENTRY_SIZE)
+                            .map(move |index| start + index * ENTRY_SIZE)
+                    })
+                    .collect()
+            }
+        })
+    }
+
+    // The files of `dir`, without `.`, `..` and the volume label. Long names
+    // that don't belong to the short entry after them are ignored, as the
+    // ones left by systems that don't know about them.
+    fn files(&mut self, dir: Dir) -> Result<Vec<Found>> {
+        let mut files = Vec::new();
+        let mut long: Option<LongName> = None;
+        for offset in self.slots(dir)? {
+            let mut raw = [0; 32];
+            self.read_at(offset, &mut raw)?;
+            match Slot::parse(&raw) {
+                Slot::End => break,
+                Slot::Deleted => long = None,
+                Slot::Long {
+                    order,
+                    last: true,
+                    checksum,
+                    chars,
+                } if order > 0 => {
+                    let mut units = vec![0; order as usize * 13];
+                    units[(order as usize - 1) * 13..].copy_from_slice(&chars);
+                    long = Some(LongName {
+                        units,
+                        order,
+                        checksum,
+                        slots: vec![offset],
+                    });
+                }
+                Slot::Long {
+                    order,
+                    last: false,
+                    checksum,
+                    chars,
+                } => {
+                    long = long.filter(|long| {
+                        long.order == order + 1 && long.checksum == checksum && order > 0
+                    });
+                    if let Some(long) = &mut long {
+                        let start = (order as usize - 1) * 13;
+                        long.units[start..start + 13].copy_from_slice(&chars);
+                        long.order = order;
+                        long.slots.push(offset);
+                    }
+                }
+                Slot::Long { .. } => long = None,
+                Slot::Short(short) => {
+                    let long = long.take().filter(|long| {
+                        long.order == 1 && long.checksum == dir::checksum(&short.name)
+                    });
+                    if short.attr & dir::ATTR_VOLUME_ID != 0 || short.name[0] == b'.' {
+                        continue;
+                    }
+                    let (name, mut slots) = match long {
+                        Some(long) => (dir::decode_long_name(&long.units), long.slots),
+                        None => (short.display_name(), Vec::new()),
+                    };
+                    slots.push(offset);
+                    files.push(Found { name, short, slots });
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn find(&mut self, dir: InodeId, name: &str) -> Result<Found> {
+        let dir = self.dir(dir)?;
+        self.files(dir)?
+            .into_iter()
+            .find(|found| found.is(name))
+            .ok_or(Error::NotFound)
+    }
+
+    // `count` free slots in a row in `dir`, growing it if there aren't any
+    fn free_slots(&mut self, dir: Dir, count: usize) -> Result<Vec<u64>> {
+        let mut run = Vec::new();
+        for offset in self.slots(dir)? {
+            let mut first = [0];
+            self.read_at(offset, &mut first)?;
+            match first[0] {
+                0 | DELETED => run.push(offset),
+                _ => run.clear(),
+            }
+            if run.len() == count {
+                return Ok(run);
+            }
+        }
+
+        let Dir::Chain(first) = dir else {
+            return Err(Error::NoSpace);
+        };
+        let mut last = self.chain(first)?.last().copied();
+        while run.len() < count {
+            let cluster = self.allocate(last)?;
+            let start = self.layout.cluster_offset(cluster);
+            run.extend(
+                (0..self.layout.cluster_size / ENTRY_SIZE).map(|index| start + index * ENTRY_SIZE),
+            );
+            last = Some(cluster);
+        }
+        run.truncate(count);
+        Ok(run)
+    }
+
+    fn create(&mut self, dir_inode: InodeId, name: &str, directory: bool) -> Result<InodeId> {
+        if !super::valid_name(name) || !dir::valid_long_name(name) {
+            return Err(Error::InvalidPath);
+        }
+        let dir = self.dir(dir_inode)?;
+        let files = self.files(dir)?;
+        if files.iter().any(|found| found.is(name)) {
+            return Err(Error::AlreadyExists);
+        }
+
+        // A long name is only needed if the short one can't hold the name
+        let taken = |short: &[u8; 11]| files.iter().any(|found| &found.short.name == short);
+        let (short_name, case, long) = match dir::exact_short_name(name) {
+            Some((short, case)) if !taken(&short) => (short, case, false),
+            _ => {
+                let short = (1..)
+                    .map(|tail| dir::short_alias(name, tail))
+                    .find(|short| !taken(short))
+                    .unwrap();
+                (short, 0, true)
+            }
+        };
+        let mut entries = match long {
+            true => dir::long_entries(name, dir::checksum(&short_name)),
+            false => Vec::new(),
+        };
+        let slots = self.free_slots(dir, entries.len() + 1)?;
+
+        let first_cluster = match directory {
+            true => self.allocate(None)?,
+            false => 0,
+        };
+        let short = Short {
+            name: short_name,
+            attr: match directory {
+                true => dir::ATTR_DIRECTORY,
+                false => dir::ATTR_ARCHIVE,
+            },
+            case,
+            first_cluster,
+            size: 0,
+            time: 0,
+            date: DEFAULT_DATE,
+        };
+        if directory {
+            // `..` points at cluster 0 when the parent is the root, even on FAT32
+            let parent = match dir {
+                Dir::Chain(cluster) if dir_inode != ROOT => cluster,
+                _ => 0,
+            };
+            let start = self.layout.cluster_offset(first_cluster);
+            for (index, (dots, cluster)) in [(&b"."[..], first_cluster), (b"..", parent)]
+                .into_iter()
+                .enumerate()
+            {
+                let mut name = [b' '; 11];
+                name[..dots.len()].copy_from_slice(dots);
+                let dot = Short {
+                    name,
+                    first_cluster: cluster,
+                    ..short
+                };
+                self.write_entry(start + index as u64 * ENTRY_SIZE, &dot)?;
+            }
+        }
+
+        entries.push(short.encode());
+        for (slot, raw) in slots.iter().zip(&entries) {
+            self.write_at(*slot, raw)?;
+        }
+        Ok(*slots.last().unwrap())
+    }
+
+    fn read(&mut self, inode: InodeId, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let short = self.file(inode)?;
+        let size = short.size as u64;
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let cluster_size = self.layout.cluster_size;
+        let chain = self.chain(short.first_cluster)?;
+
+        let mut done = 0;
+        while done < len {
+            let position = offset + done as u64;
+            let cluster = *chain
+                .get((position / cluster_size) as usize)
+                .ok_or(Error::Io)?;
+            let within = position % cluster_size;
+            let part = ((cluster_size - within) as usize).min(len - done);
+            let start = self.layout.cluster_offset(cluster) + within;
+            self.read_at(start, &mut buf[done..done + part])?;
+            done += part;
+        }
+        Ok(len)
+    }
+
+    // The short entry of a file, not a directory
+    fn file(&mut self, inode: InodeId) -> Result<Short> {
+        match self.entry(inode) {
+            Ok(short) if short.is_directory() => Err(Error::IsADirectory),
+            Err(_) if inode == ROOT => Err(Error::IsADirectory),
+            result => result,
+        }
+    }
+
+    fn write(&mut self, inode: InodeId, offset: u64, buf: &[u8]) -> Result<usize> {
+        let mut short = self.file(inode)?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let end = offset + buf.len() as u64;
+        if end > u32::MAX as u64 {
+            return Err(Error::NoSpace);
+        }
+
+        // The clusters past the end of the file are zeroed when they are
+        // allocated, but the end of the last one may hold old data
+        let zeros = vec![0; self.layout.cluster_size as usize];
+        while (short.size as u64) < offset {
+            let size = short.size as u64;
+            let len = (offset - size).min(zeros.len() as u64) as usize;
+            self.write_data(inode, &mut short, size, &zeros[..len])?;
+        }
+        self.write_data(inode, &mut short, offset, buf)?;
+        Ok(buf.len())
+    }
+
+    // Writes `data` at `offset` in the file, allocating the clusters it needs
+    fn write_data(
+        &mut self,
+        inode: InodeId,
+        short: &mut Short,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        let cluster_size = self.layout.cluster_size;
+        let end = offset + data.len() as u64;
+        let mut chain = self.chain(short.first_cluster)?;
+        while (chain.len() as u64) < end.div_ceil(cluster_size) {
+            let cluster = match self.allocate(chain.last().copied()) {
+                Ok(cluster) => cluster,
+                Err(error) => {
+                    // Keep the clusters linked so far
+                    self.write_entry(inode, short)?;
+                    return Err(error);
+                }
+            };
+            if chain.is_empty() {
+                short.first_cluster = cluster;
+            }
+            chain.push(cluster);
+        }
+
+        let mut done = 0;
+        while done < data.len() {
+            let position = offset + done as u64;
+            let cluster = chain[(position / cluster_size) as usize];
+            let within = position % cluster_size;
+            let part = ((cluster_size - within) as usize).min(data.len() - done);
+            let start = self.layout.cluster_offset(cluster) + within;
+            self.write_at(start, &data[done..done + part])?;
+            done += part;
+        }
+        short.size = short.size.max(end as u32);
+        self.write_entry(inode, short)
+    }
+
+    fn truncate(&mut self, inode: InodeId, size: u64) -> Result<()> {
+        let mut short = self.file(inode)?;
+        if size > u32::MAX as u64 {
+            return Err(Error::NoSpace);
+        }
+        if size > short.size as u64 {
+            let zeros = vec![0; (size - short.size as u64) as usize];
+            return self.write(inode, short.size as u64, &zeros).map(|_| ());
+        }
+
+        let chain = self.chain(short.first_cluster)?;
+        let keep = size.div_ceil(self.layout.cluster_size) as usize;
+        short.size = size as u32;
+        if keep == 0 {
+            short.first_cluster = 0;
+        }
+        self.write_entry(inode, &short)?;
+        if keep < chain.len() {
+            if keep > 0 {
+                self.set_fat_entry(chain[keep - 1], self.layout.fat_type.end_marker())?;
+            }
+            self.free(&chain[keep..])?;
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, dir: InodeId, name: &str) -> Result<()> {
+        let found = self.find(dir, name)?;
+        let short = found.short;
+        if short.is_directory() && !self.files(Dir::Chain(short.first_cluster))?.is_empty() {
+            return Err(Error::DirectoryNotEmpty);
+        }
+        // The entry goes first, lost clusters are better than shared ones
+        for slot in found.slots {
+            self.write_at(slot, &[DELETED])?;
+        }
+        let chain = self.chain(short.first_cluster)?;
+        self.free(&chain)
+    }
+}
+
+/// A FAT volume. The FATs, directories and files are read and written on the
+/// device directly, only the last block read is cached.
+pub struct FatFs {
+    volume: Mutex<Volume>,
+}
+
+impl FatFs {
+    /// Opens the volume on `device`, failing with `Error::InvalidArgument` if
+    /// it doesn't hold one
+    pub fn new(device: Arc<dyn BlockDevice>) -> Result<Self> {
+        let mut sector = [0; 512];
+        crate::block::read_bytes(&*device, 0, &mut sector)?;
+        let layout = Layout::parse(&sector)?;
+        let end = layout.cluster_offset(layout.cluster_count + 2);
+        if end > device.block_count() * BLOCK_SIZE as u64 {
+            return Err(Error::InvalidArgument);
+        }
+
+        let mut volume = Volume {
+            device,
+            layout,
+            next_free: 2,
+            free_count: None,
+            cache: None,
+        };
+        if let Some(offset) = layout.fs_info {
+            let mut valid = true;
+            for (at, signature) in [0, 484, 508].into_iter().zip(FS_INFO_SIGNATURES) {
+                valid &= volume.read_u32(offset + at)? == signature;
+            }
+            if valid {
+                volume.free_count = Some(volume.read_u32(offset + 488)?)
+                    .filter(|&free| free <= layout.cluster_count);
+                volume.next_free = Some(volume.read_u32(offset + 492)?)
+                    .filter(|&next| layout.is_cluster(next))
+                    .unwrap_or(2);
+            } else {
+                volume.layout.fs_info = None;
+            }
+        }
+        Ok(Self {
+            volume: Mutex::new(volume),
+        })
+    }
+
+    pub fn fat_type(&self) -> FatType {
+        self.volume.lock().layout.fat_type
+    }
+}
+
+impl FileSystem for FatFs {
+    fn name(&self) -> &'static str {
+        match self.fat_type() {
+            FatType::Fat12 => "fat12",
+            FatType::Fat16 => "fat16",
+            FatType::Fat32 => "fat32",
+        }
+    }
+
+    fn root(&self) -> InodeId {
+        ROOT
+    }
+
+    fn lookup(&self, dir: InodeId, name: &str) -> Result<InodeId> {
+        Ok(self.volume.lock().find(dir, name)?.inode())
+    }
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata> {
+        if inode == ROOT {
+            return Ok(Metadata {
+                inode,
+                file_type: FileType::Directory,
+                size: 0,
+                mode: 0o755,
+                mtime: 0,
+            });
+        }
+        let short = self.volume.lock().entry(inode)?;
+        let (file_type, mode) = match short.is_directory() {
+            true => (FileType::Directory, 0o755),
+            false => (FileType::File, 0o644),
+        };
+        Ok(Metadata {
+            inode,
+            file_type,
+            size: short.size as u64,
+            mode: match short.attr & dir::ATTR_READ_ONLY {
+                0 => mode,
+                _ => mode & !0o222,
+            },
+            mtime: dir::unix_time(short.date, short.time),
+        })
+    }
+
+    fn read(&self, inode: InodeId, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        self.volume.lock().read(inode, offset, buf)
+    }
+
+    fn read_dir(&self, dir: InodeId) -> Result<Vec<DirEntry>> {
+        let mut volume = self.volume.lock();
+        let dir = volume.dir(dir)?;
+        Ok(volume
+            .files(dir)?
+            .into_iter()
+            .map(|found| DirEntry {
+                inode: found.inode(),
+                file_type: match found.short.is_directory() {
+                    true => FileType::Directory,
+                    false => FileType::File,
+                },
+                name: found.name,
+            })
+            .collect())
+    }
+
+    fn write(&self, inode: InodeId, offset: u64, buf: &[u8]) -> Result<usize> {
+        self.volume.lock().write(inode, offset, buf)
+    }
+
+    fn truncate(&self, inode: InodeId, size: u64) -> Result<()> {
+        self.volume.lock().truncate(inode, size)
+    }
+
+    fn create(&self, dir: InodeId, name: &str, file_type: FileType) -> Result<InodeId> {
+        match file_type {
+            FileType::Symlink => Err(Error::Unsupported),
+            _ => {
+                let directory = file_type == FileType::Directory;
+                self.volume.lock().create(dir, name, directory)
+            }
+        }
+    }
+
+    fn symlink(&self, _dir: InodeId, _name: &str, _target: &str) -> Result<InodeId> {
+        Err(Error::Unsupported)
+    }
+
+    fn remove(&self, dir: InodeId, name: &str) -> Result<()> {
+        self.volume.lock().remove(dir, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::super::{OpenFlags, SeekFrom, Vfs};
+    use super::*;
+    use crate::block::RamDisk;
+
+    const TYPES: [FatType; 3] = [FatType::Fat12, FatType::Fat16, FatType::Fat32];
+
+    fn mount(disk: &Arc<RamDisk>) -> (Vfs, Arc<FatFs>) {
+        let fs = Arc::new(FatFs::new(disk.clone()).unwrap());
+        let vfs = Vfs::new();
+        vfs.mount("/", fs.clone()).unwrap();
+        (vfs, fs)
+    }
+
+    fn names(vfs: &Vfs, path: &str) -> Vec<String> {
+        let entries = vfs.read_dir(path).unwrap();
+        entries.into_iter().map(|entry| entry.name).collect()
+    }
+
+    #[test]
+    fn files_and_directories() {
+        for fat_type in TYPES {
+            let disk = Arc::new(testing::format(fat_type));
+            let (vfs, fs) = mount(&disk);
+            assert_eq!(fs.fat_type(), fat_type);
+
+            // Over two clusters, so the chain is followed
+            let data: Vec<u8> = (0..1300).map(|byte| byte as u8).collect();
+            vfs.create_dir("/Documents").unwrap();
+            vfs.write("/Documents/A long file name.text", &data)
+                .unwrap();
+            vfs.write("/README.TXT", b"read me").unwrap();
+            vfs.write("/notes.md", b"short and lower case").unwrap();
+            vfs.create_dir("/Documents/nested").unwrap();
+
+            // Everything is still there for another mount of the volume
+            let (vfs, _) = mount(&disk);
+            assert_eq!(names(&vfs, "/"), ["Documents", "README.TXT", "notes.md"]);
+            assert_eq!(
+                names(&vfs, "/Documents"),
+                ["A long file name.text", "nested"]
+            );
+            assert_eq!(vfs.read("/documents/a LONG file name.text").unwrap(), data);
+            assert_eq!(vfs.read("/Documents/ALONGF~1.TEX").unwrap(), data);
+            assert_eq!(vfs.read("/readme.txt").unwrap(), b"read me");
+            assert_eq!(
+                vfs.canonicalize("/Documents/nested/..").unwrap(),
+                "/Documents"
+            );
+            let metadata = vfs.metadata("/notes.md").unwrap();
+            assert_eq!((metadata.size, metadata.mode), (20, 0o644));
+            assert_eq!(metadata.mtime, 315_532_800);
+
+            assert_eq!(vfs.remove("/Documents"), Err(Error::DirectoryNotEmpty));
+            vfs.remove("/Documents/nested").unwrap();
+            vfs.remove("/Documents/A long file name.text").unwrap();
+            vfs.remove("/Documents").unwrap();
+            assert_eq!(names(&vfs, "/"), ["README.TXT", "notes.md"]);
+            assert_eq!(vfs.symlink("/notes.md", "/link"), Err(Error::Unsupported));
+            assert_eq!(vfs.write("/what?", b""), Err(Error::InvalidPath));
+        }
+    }
+
+    #[test]
+    fn directories_grow() {
+        for fat_type in TYPES {
+            let disk = Arc::new(testing::format(fat_type));
+            let (vfs, _) = mount(&disk);
+            // 512 byte clusters hold 16 entries, these take 3 each
+            vfs.create_dir("/many").unwrap();
+            for index in 0..40 {
+                vfs.write(&format!("/many/file number {:02}", index), b"")
+                    .unwrap();
+            }
+            let names = names(&vfs, "/many");
+            assert_eq!(names.len(), 40);
+            assert_eq!(names[39], "file number 39");
+        }
+
+        // The root of FAT12 and FAT16 has a fixed size
+        let disk = Arc::new(testing::format(FatType::Fat12));
+        let (vfs, _) = mount(&disk);
+        for index in 0..224 {
+            vfs.write(&format!("/F{}", index), b"").unwrap();
+        }
+        assert_eq!(vfs.write("/one-more", b""), Err(Error::NoSpace));
+        vfs.remove("/F100").unwrap();
+        vfs.write("/F224", b"").unwrap();
+    }
+
+    #[test]
+    fn short_aliases_are_unique() {
+        let disk = Arc::new(testing::format(FatType::Fat16));
+        let (vfs, _) = mount(&disk);
+        vfs.write("/Long name one.txt", b"1").unwrap();
+        vfs.write("/Long name two.txt", b"2").unwrap();
+        assert_eq!(vfs.read("/LONGNA~1.TXT").unwrap(), b"1");
+        assert_eq!(vfs.read("/LONGNA~2.TXT").unwrap(), b"2");
+
+        // Both names of a file are the same file
+        vfs.write("/LONGNA~1.TXT", b"3").unwrap();
+        vfs.write("/longna~3.txt", b"4").unwrap();
+        assert_eq!(vfs.read("/long NAME one.txt").unwrap(), b"3");
+        assert_eq!(
+            names(&vfs, "/"),
+            ["Long name one.txt", "Long name two.txt", "longna~3.txt"]
+        );
+    }
+
+    #[test]
+    fn clusters_are_freed() {
+        let disk = Arc::new(testing::format(FatType::Fat32));
+        let (vfs, fs) = mount(&disk);
+        let free = || fs.volume.lock().free_count.unwrap();
+        let before = free();
+
+        let mut file = vfs
+            .open(
+                "/sparse",
+                OpenFlags::CREATE | OpenFlags::READ | OpenFlags::WRITE,
+            )
+            .unwrap();
+        file.seek(SeekFrom::Start(2000)).unwrap();
+        file.write_all(b"end").unwrap();
+        assert_eq!(free(), before - 4);
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data.len(), 2003);
+        assert!(data[..2000].iter().all(|&byte| byte == 0));
+
+        // Old data past a truncation doesn't come back when the file grows
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(&[1; 600]).unwrap();
+        file.set_len(1).unwrap();
+        assert_eq!(free(), before - 1);
+        file.set_len(600).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        data.clear();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data[..2], [1, 0]);
+        assert!(data[1..].iter().all(|&byte| byte == 0));
+
+        vfs.remove("/sparse").unwrap();
+        assert_eq!(free(), before);
+        // The count is kept on the volume
+        let (_, fs) = mount(&disk);
+        assert_eq!(fs.volume.lock().free_count, Some(before));
+    }
+
+    #[test]
+    fn fat12_entries_share_bytes() {
+        let disk = Arc::new(testing::format(FatType::Fat12));
+        let fs = FatFs::new(disk).unwrap();
+        let mut volume = fs.volume.lock();
+        volume.set_fat_entry(2, 0xabc).unwrap();
+        volume.set_fat_entry(3, 0x123).unwrap();
+        assert_eq!(volume.fat_entry(2), Ok(0xabc));
+        assert_eq!(volume.fat_entry(3), Ok(0x123));
+        let start = volume.layout.fat_start;
+        let mut bytes = [0; 3];
+        volume.read_at(start + 3, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xbc, 0x3a, 0x12]);
+        assert_eq!(volume.chain(2), Err(Error::Io));
+        assert_eq!(
+            FatFs::new(Arc::new(RamDisk::new(8))).err(),
+            Some(Error::InvalidArgument)
+        );
+    }
+}
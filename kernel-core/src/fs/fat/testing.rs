@@ -0,0 +1,89 @@
+//! Empty volumes laid out the way `mkfs.fat -s 1` lays them out, for the tests
+
+use alloc::vec;
+
+use super::FatType;
+use crate::block::RamDisk;
+
+const SECTOR: usize = 512;
+
+/// A freshly formatted volume, about as small as its type allows
+pub fn format(fat_type: FatType) -> RamDisk {
+    // A 1.44 MB floppy, 16 MiB, and just over 65525 clusters
+    let (sectors, reserved, root_entries, entry_bits, media) = match fat_type {
+        FatType::Fat12 => (2880, 1, 224, 12, 0xf0),
+        FatType::Fat16 => (32768, 4, 512, 16, 0xf8),
+        FatType::Fat32 => (67000, 32, 0, 32, 0xf8),
+    };
+    let root_sectors = root_entries * 32 / SECTOR;
+    let mut fat_sectors = 1;
+    let clusters = loop {
+        let clusters = sectors - reserved - 2 * fat_sectors - root_sectors;
+        let needed = ((clusters + 2) * entry_bits).div_ceil(8).div_ceil(SECTOR);
+        if needed <= fat_sectors {
+            break clusters;
+        }
+        fat_sectors = needed;
+    };
+
+    let mut image = vec![0u8; sectors * SECTOR];
+    let boot = &mut image[..SECTOR];
+    boot[..11].copy_from_slice(b"\xeb\x3c\x90mkfs.fat");
+    boot[11..13].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+    boot[13] = 1;
+    boot[14..16].copy_from_slice(&(reserved as u16).to_le_bytes());
+    boot[16] = 2;
+    boot[17..19].copy_from_slice(&(root_entries as u16).to_le_bytes());
+    match u16::try_from(sectors) {
+        Ok(sectors) => boot[19..21].copy_from_slice(&sectors.to_le_bytes()),
+        Err(_) => boot[32..36].copy_from_slice(&(sectors as u32).to_le_bytes()),
+    }
+    boot[21] = media;
+    let extended = match fat_type {
+        FatType::Fat32 => {
+            boot[36..40].copy_from_slice(&(fat_sectors as u32).to_le_bytes());
+            boot[44] = 2; // the root directory's cluster
+            boot[48] = 1; // the FSInfo sector
+            boot[50] = 6; // the backup boot sector
+            64
+        }
+        _ => {
+            boot[22..24].copy_from_slice(&(fat_sectors as u16).to_le_bytes());
+            36
+        }
+    };
+    boot[extended] = 0x80;
+    boot[extended + 2] = 0x29;
+    boot[extended + 7..extended + 18].copy_from_slice(b"NO NAME    ");
+    boot[extended + 18..extended + 26].copy_from_slice(match fat_type {
+        FatType::Fat12 => b"FAT12   ",
+        FatType::Fat16 => b"FAT16   ",
+        FatType::Fat32 => b"FAT32   ",
+    });
+    boot[510..512].copy_from_slice(&[0x55, 0xaa]);
+
+    // The first two entries hold the media byte and an end of chain marker,
+    // on FAT32 the third ends the root directory's chain
+    let fat_start: &[u8] = match fat_type {
+        FatType::Fat12 => &[media, 0xff, 0xff],
+        FatType::Fat16 => &[media, 0xff, 0xff, 0xff],
+        FatType::Fat32 => &[
+            media, 0xff, 0xff, 0x0f, 0xff, 0xff, 0xff, 0x0f, 0xff, 0xff, 0xff, 0x0f,
+        ],
+    };
+    for fat in 0..2 {
+        let start = (reserved + fat * fat_sectors) * SECTOR;
+        image[start..start + fat_start.len()].copy_from_slice(fat_start);
+    }
+
+    if fat_type == FatType::Fat32 {
+        let fs_info = &mut image[SECTOR..2 * SECTOR];
+        fs_info[..4].copy_from_slice(&0x4161_5252u32.to_le_bytes());
+        fs_info[484..488].copy_from_slice(&0x6141_7272u32.to_le_bytes());
+        fs_info[488..492].copy_from_slice(&(clusters as u32 - 1).to_le_bytes());
+        fs_info[492..496].copy_from_slice(&3u32.to_le_bytes());
+        fs_info[508..512].copy_from_slice(&0xaa55_0000u32.to_le_bytes());
+        image.copy_within(..2 * SECTOR, 6 * SECTOR);
+    }
+    RamDisk::from_vec(image)
+}
@@ -0,0 +1,156 @@
+//! Files and directories. File systems implement `FileSystem`, which works on
+//! inode numbers, and are mounted into a `Vfs`, which resolves paths across them
+//! and hands out `File`s.
+
+pub mod archive;
+pub mod fat;
+pub mod tmpfs;
+pub mod vfs;
+
+pub use archive::ArchiveFs;
+pub use fat::FatFs;
+pub use tmpfs::TmpFs;
+pub use vfs::{File, Inode, OpenFlags, SeekFrom, Vfs};
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    NotFound,
+    AlreadyExists,
+    NotADirectory,
+    IsADirectory,
+    DirectoryNotEmpty,
+    /// Writing to a read only file system
+    ReadOnly,
+    /// Reading or writing a file that wasn't opened for it
+    PermissionDenied,
+    /// An empty name, or `.` or `..` where a new name is needed
+    InvalidPath,
+    InvalidArgument,
+    /// Too many symlinks followed, probably a loop
+    TooManyLinks,
+    /// Unmounting a file system that is in use, or removing a mount point
+    Busy,
+    /// No free space left on the device
+    NoSpace,
+    /// Something the file system can't store, like a symlink on FAT
+    Unsupported,
+    /// The device failed, or the file system on it is corrupted
+    Io,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::NotFound => "no such file or directory",
+            Error::AlreadyExists => "file exists",
+            Error::NotADirectory => "not a directory",
+            Error::IsADirectory => "is a directory",
+            Error::DirectoryNotEmpty => "directory not empty",
+            Error::ReadOnly => "read only file system",
+            Error::PermissionDenied => "permission denied",
+            Error::InvalidPath => "invalid path",
+            Error::InvalidArgument => "invalid argument",
+            Error::TooManyLinks => "too many levels of symbolic links",
+            Error::Busy => "device or resource busy",
+            Error::NoSpace => "no space left on device",
+            Error::Unsupported => "operation not supported",
+            Error::Io => "input/output error",
+        })
+    }
+}
+
+/// Identifies an inode within its file system
+pub type InodeId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub inode: InodeId,
+    pub file_type: FileType,
+    /// In bytes, the length of the target for symlinks
+    pub size: u64,
+    /// Permission bits
+    pub mode: u32,
+    /// Modification time, in seconds since the epoch, 0 if unknown
+    pub mtime: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub inode: InodeId,
+    pub file_type: FileType,
+}
+
+/// A file system, like a tmpfs or a FAT volume. Every method takes `&self`, so
+/// implementations lock their own state. The ones changing anything default to
+/// `Error::ReadOnly`.
+pub trait FileSystem: Send + Sync {
+    /// Shown in the mount table, e.g. `tmpfs`
+    fn name(&self) -> &'static str;
+
+    fn root(&self) -> InodeId;
+
+    /// The inode called `name` in the directory `dir`. `.` and `..` are the
+    /// `Vfs`'s business, they are never looked up.
+    fn lookup(&self, dir: InodeId, name: &str) -> Result<InodeId>;
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata>;
+
+    /// Reads from `offset` into `buf`, returning how many bytes were read, 0 at
+    /// the end of the file
+    fn read(&self, inode: InodeId, offset: u64, buf: &mut [u8]) -> Result<usize>;
+
+    /// The entries of `dir`, without `.` and `..`
+    fn read_dir(&self, dir: InodeId) -> Result<Vec<DirEntry>>;
+
+    fn read_link(&self, _inode: InodeId) -> Result<String> {
+        Err(Error::InvalidArgument)
+    }
+
+    /// Writes `buf` at `offset`, growing the file as needed
+    fn write(&self, _inode: InodeId, _offset: u64, _buf: &[u8]) -> Result<usize> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Cuts the file off at `size`, or extends it with zeros
+    fn truncate(&self, _inode: InodeId, _size: u64) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Creates an empty file or directory called `name` in `dir`
+    fn create(&self, _dir: InodeId, _name: &str, _file_type: FileType) -> Result<InodeId> {
+        Err(Error::ReadOnly)
+    }
+
+    fn symlink(&self, _dir: InodeId, _name: &str, _target: &str) -> Result<InodeId> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Removes a file, a symlink or an empty directory
+    fn remove(&self, _dir: InodeId, _name: &str) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+}
+
+impl From<crate::block::Error> for Error {
+    fn from(_: crate::block::Error) -> Self {
+        Error::Io
+    }
+}
+
+// Whether `name` can be given to a new file
+fn valid_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..") && !name.contains('/')
+}
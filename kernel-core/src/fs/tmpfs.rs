@@ -0,0 +1,241 @@
+//! A file system kept entirely in memory
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use spin::Mutex;
+
+use super::{DirEntry, Error, FileSystem, FileType, InodeId, Metadata, Result};
+
+const ROOT: InodeId = 1;
+
+enum Data {
+    File(Vec<u8>),
+    Directory(BTreeMap<String, InodeId>),
+    Symlink(String),
+}
+
+impl Data {
+    fn file_type(&self) -> FileType {
+        match self {
+            Data::File(_) => FileType::File,
+            Data::Directory(_) => FileType::Directory,
+            Data::Symlink(_) => FileType::Symlink,
+        }
+    }
+}
+
+struct Inner {
+    nodes: BTreeMap<InodeId, Data>,
+    next_id: InodeId,
+}
+
+impl Inner {
+    fn node(&self, inode: InodeId) -> Result<&Data> {
+        self.nodes.get(&inode).ok_or(Error::NotFound)
+    }
+
+    fn file(&mut self, inode: InodeId) -> Result<&mut Vec<u8>> {
+        match self.nodes.get_mut(&inode) {
+            Some(Data::File(data)) => Ok(data),
+            Some(Data::Directory(_)) => Err(Error::IsADirectory),
+            Some(Data::Symlink(_)) => Err(Error::InvalidArgument),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    fn directory(&mut self, inode: InodeId) -> Result<&mut BTreeMap<String, InodeId>> {
+        match self.nodes.get_mut(&inode) {
+            Some(Data::Directory(entries)) => Ok(entries),
+            Some(_) => Err(Error::NotADirectory),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    fn add(&mut self, dir: InodeId, name: &str, data: Data) -> Result<InodeId> {
+        if !super::valid_name(name) {
+            return Err(Error::InvalidPath);
+        }
+        let id = self.next_id;
+        let entries = self.directory(dir)?;
+        if entries.contains_key(name) {
+            return Err(Error::AlreadyExists);
+        }
+        entries.insert(String::from(name), id);
+        self.nodes.insert(id, data);
+        self.next_id += 1;
+        Ok(id)
+    }
+}
+
+/// Files and directories in memory, gone when it is dropped. Times aren't
+/// kept, there is no wall clock yet.
+pub struct TmpFs {
+    inner: Mutex<Inner>,
+}
+
+impl Default for TmpFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TmpFs {
+    /// An empty root directory
+    pub fn new() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(ROOT, Data::Directory(BTreeMap::new()));
+        Self {
+            inner: Mutex::new(Inner {
+                nodes,
+                next_id: ROOT + 1,
+            }),
+        }
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn name(&self) -> &'static str {
+        "tmpfs"
+    }
+
+    fn root(&self) -> InodeId {
+        ROOT
+    }
+
+    fn lookup(&self, dir: InodeId, name: &str) -> Result<InodeId> {
+        let mut inner = self.inner.lock();
+        let entries = inner.directory(dir)?;
+        entries.get(name).copied().ok_or(Error::NotFound)
+    }
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata> {
+        let inner = self.inner.lock();
+        let node = inner.node(inode)?;
+        let (size, mode) = match node {
+            Data::File(data) => (data.len(), 0o644),
+            Data::Directory(entries) => (entries.len(), 0o755),
+            Data::Symlink(target) => (target.len(), 0o777),
+        };
+        Ok(Metadata {
+            inode,
+            file_type: node.file_type(),
+            size: size as u64,
+            mode,
+            mtime: 0,
+        })
+    }
+
+    fn read(&self, inode: InodeId, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let mut inner = self.inner.lock();
+        let data = inner.file(inode)?;
+        let start = (offset as usize).min(data.len());
+        let read = buf.len().min(data.len() - start);
+        buf[..read].copy_from_slice(&data[start..start + read]);
+        Ok(read)
+    }
+
+    fn read_dir(&self, dir: InodeId) -> Result<Vec<DirEntry>> {
+        let mut inner = self.inner.lock();
+        let entries = inner.directory(dir)?.clone();
+        entries
+            .into_iter()
+            .map(|(name, inode)| {
+                let file_type = inner.node(inode)?.file_type();
+                Ok(DirEntry {
+                    name,
+                    inode,
+                    file_type,
+                })
+            })
+            .collect()
+    }
+
+    fn read_link(&self, inode: InodeId) -> Result<String> {
+        match self.inner.lock().node(inode)? {
+            Data::Symlink(target) => Ok(target.clone()),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+
+    fn write(&self, inode: InodeId, offset: u64, buf: &[u8]) -> Result<usize> {
+        let mut inner = self.inner.lock();
+        let data = inner.file(inode)?;
+        let start = offset as usize;
+        let end = start + buf.len();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, inode: InodeId, size: u64) -> Result<()> {
+        let mut inner = self.inner.lock();
+        inner.file(inode)?.resize(size as usize, 0);
+        Ok(())
+    }
+
+    fn create(&self, dir: InodeId, name: &str, file_type: FileType) -> Result<InodeId> {
+        let data = match file_type {
+            FileType::File => Data::File(Vec::new()),
+            FileType::Directory => Data::Directory(BTreeMap::new()),
+            FileType::Symlink => return Err(Error::InvalidArgument),
+        };
+        self.inner.lock().add(dir, name, data)
+    }
+
+    fn symlink(&self, dir: InodeId, name: &str, target: &str) -> Result<InodeId> {
+        let data = Data::Symlink(String::from(target));
+        self.inner.lock().add(dir, name, data)
+    }
+
+    fn remove(&self, dir: InodeId, name: &str) -> Result<()> {
+        let mut inner = self.inner.lock();
+        let inode = *inner.directory(dir)?.get(name).ok_or(Error::NotFound)?;
+        if let Data::Directory(entries) = inner.node(inode)? {
+            if !entries.is_empty() {
+                return Err(Error::DirectoryNotEmpty);
+            }
+        }
+        // Files that are still open can't be read any more
+        inner.directory(dir)?.remove(name);
+        inner.nodes.remove(&inode);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inodes_are_created_and_removed() {
+        let fs = TmpFs::new();
+        let dir = fs.create(ROOT, "dir", FileType::Directory).unwrap();
+        let file = fs.create(dir, "file", FileType::File).unwrap();
+        assert_eq!(fs.lookup(ROOT, "dir"), Ok(dir));
+        assert_eq!(fs.lookup(dir, "file"), Ok(file));
+        assert_eq!(fs.lookup(file, "x"), Err(Error::NotADirectory));
+        assert_eq!(
+            fs.create(dir, "file", FileType::File),
+            Err(Error::AlreadyExists)
+        );
+        assert_eq!(
+            fs.create(dir, "a/b", FileType::File),
+            Err(Error::InvalidPath)
+        );
+
+        assert_eq!(fs.write(file, 2, b"xy"), Ok(2));
+        let mut buf = [0xff; 8];
+        assert_eq!(fs.read(file, 0, &mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"\0\0xy");
+        assert_eq!(fs.read(file, 10, &mut buf), Ok(0));
+        assert_eq!(fs.read(dir, 0, &mut buf), Err(Error::IsADirectory));
+
+        assert_eq!(fs.remove(ROOT, "dir"), Err(Error::DirectoryNotEmpty));
+        fs.remove(dir, "file").unwrap();
+        assert_eq!(fs.metadata(file).err(), Some(Error::NotFound));
+        fs.remove(ROOT, "dir").unwrap();
+        assert_eq!(fs.read_dir(ROOT), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,642 @@
+//! The mount table and path resolution
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+use spin::{Mutex, RwLock};
+
+use super::{DirEntry, Error, FileSystem, FileType, InodeId, Metadata, Result};
+
+/// How many symlinks one lookup follows before giving up, like Linux
+const MAX_SYMLINKS: usize = 40;
+
+/// An inode of a mounted file system
+#[derive(Clone)]
+pub struct Inode {
+    fs: Arc<dyn FileSystem>,
+    id: InodeId,
+}
+
+impl Inode {
+    pub fn id(&self) -> InodeId {
+        self.id
+    }
+
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.fs.metadata(self.id)
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct OpenFlags: u32 {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        /// Create the file if it doesn't exist
+        const CREATE = 1 << 2;
+        /// With `CREATE`, fail if it exists
+        const EXCLUSIVE = 1 << 3;
+        /// Empty the file when opening it for writing
+        const TRUNCATE = 1 << 4;
+        /// Every write goes to the end of the file
+        const APPEND = 1 << 5;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
+/// The directory tree, made of mounted file systems. Paths not starting with
+/// `/` are relative to the current directory.
+pub struct Vfs {
+    // By the canonical path of the mount point
+    mounts: RwLock<BTreeMap<String, Arc<dyn FileSystem>>>,
+    // Canonical, empty for `/`, so that `new` can be const
+    cwd: Mutex<String>,
+}
+
+// The directories walked through to reach a path, from the root, so that `..`
+// goes back the way it came, also across mount points
+struct Walk {
+    stack: Vec<(String, Inode)>,
+}
+
+impl Walk {
+    fn inode(&self) -> &Inode {
+        &self.stack.last().unwrap().1
+    }
+
+    fn path(&self) -> String {
+        if self.stack.len() == 1 {
+            return String::from("/");
+        }
+        let mut path = String::new();
+        for (name, _) in &self.stack[1..] {
+            path.push('/');
+            path.push_str(name);
+        }
+        path
+    }
+}
+
+impl Default for Vfs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vfs {
+    /// An empty tree, mount something at `/` first
+    pub const fn new() -> Self {
+        Self {
+            mounts: RwLock::new(BTreeMap::new()),
+            cwd: Mutex::new(String::new()),
+        }
+    }
+
+    /// Mounts `fs` on the directory at `path`, hiding what is in it
+    pub fn mount(&self, path: &str, fs: Arc<dyn FileSystem>) -> Result<()> {
+        let path = if self.mounts.read().is_empty() && path == "/" {
+            String::from("/")
+        } else {
+            let walk = self.walk(path, true)?;
+            if walk.inode().metadata()?.file_type != FileType::Directory {
+                return Err(Error::NotADirectory);
+            }
+            walk.path()
+        };
+
+        let mut mounts = self.mounts.write();
+        if mounts.contains_key(&path) {
+            return Err(Error::Busy);
+        }
+        mounts.insert(path, fs);
+        Ok(())
+    }
+
+    /// Unmounts the file system mounted at `path`. Fails while files on it are
+    /// open, or other file systems are mounted below it.
+    pub fn unmount(&self, path: &str) -> Result<()> {
+        let path = self.walk(path, true)?.path();
+        let mut mounts = self.mounts.write();
+        let fs = mounts.get(&path).ok_or(Error::InvalidArgument)?;
+        let below = format!("{}/", path.trim_end_matches('/'));
+        let nested = mounts
+            .keys()
+            .any(|other| *other != path && other.starts_with(&below));
+        // Open files hold on to the file system
+        if nested || Arc::strong_count(fs) > 1 {
+            return Err(Error::Busy);
+        }
+        mounts.remove(&path);
+        Ok(())
+    }
+
+    /// The mount points and the names of the file systems on them, sorted
+    pub fn mounts(&self) -> Vec<(String, &'static str)> {
+        let mounts = self.mounts.read();
+        mounts
+            .iter()
+            .map(|(path, fs)| (path.clone(), fs.name()))
+            .collect()
+    }
+
+    pub fn cwd(&self) -> String {
+        match self.cwd.lock().as_str() {
+            "" => String::from("/"),
+            cwd => String::from(cwd),
+        }
+    }
+
+    pub fn set_cwd(&self, path: &str) -> Result<()> {
+        let walk = self.walk(path, true)?;
+        if walk.inode().metadata()?.file_type != FileType::Directory {
+            return Err(Error::NotADirectory);
+        }
+        *self.cwd.lock() = walk.path();
+        Ok(())
+    }
+
+    /// The absolute path of `path`, without `.`, `..` and symlinks
+    pub fn canonicalize(&self, path: &str) -> Result<String> {
+        Ok(self.walk(path, true)?.path())
+    }
+
+    /// The inode at `path`, following symlinks
+    pub fn lookup(&self, path: &str) -> Result<Inode> {
+        Ok(self.walk(path, true)?.inode().clone())
+    }
+
+    /// Follows symlinks
+    pub fn metadata(&self, path: &str) -> Result<Metadata> {
+        self.lookup(path)?.metadata()
+    }
+
+    /// Doesn't follow a symlink at the end of `path`
+    pub fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        self.walk(path, false)?.inode().metadata()
+    }
+
+    pub fn open(&self, path: &str, flags: OpenFlags) -> Result<File> {
+        if !flags.intersects(OpenFlags::READ | OpenFlags::WRITE) {
+            return Err(Error::InvalidArgument);
+        }
+        let inode = match self.walk(path, true) {
+            Ok(_) if flags.contains(OpenFlags::CREATE | OpenFlags::EXCLUSIVE) => {
+                return Err(Error::AlreadyExists)
+            }
+            Ok(walk) => walk.inode().clone(),
+            Err(Error::NotFound) if flags.contains(OpenFlags::CREATE) => {
+                self.create(path, FileType::File)?
+            }
+            Err(error) => return Err(error),
+        };
+
+        let metadata = inode.metadata()?;
+        if metadata.file_type == FileType::Directory && flags.contains(OpenFlags::WRITE) {
+            return Err(Error::IsADirectory);
+        }
+        if flags.contains(OpenFlags::WRITE | OpenFlags::TRUNCATE) && metadata.size > 0 {
+            inode.fs.truncate(inode.id, 0)?;
+        }
+        Ok(File {
+            inode,
+            offset: 0,
+            flags,
+        })
+    }
+
+    /// The whole contents of the file at `path`
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let mut file = self.open(path, OpenFlags::READ)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Replaces the contents of the file at `path`, creating it if needed
+    pub fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        let flags = OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE;
+        self.open(path, flags)?.write_all(data)
+    }
+
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let inode = self.lookup(path)?;
+        inode.fs.read_dir(inode.id)
+    }
+
+    pub fn create_dir(&self, path: &str) -> Result<()> {
+        self.create(path, FileType::Directory).map(|_| ())
+    }
+
+    /// Creates a symlink at `path` pointing to `target`, which isn't checked
+    pub fn symlink(&self, target: &str, path: &str) -> Result<()> {
+        let (parent, name) = self.parent(path)?;
+        parent.fs.symlink(parent.id, name, target).map(|_| ())
+    }
+
+    pub fn read_link(&self, path: &str) -> Result<String> {
+        let walk = self.walk(path, false)?;
+        let inode = walk.inode();
+        inode.fs.read_link(inode.id)
+    }
+
+    /// Removes a file, a symlink or an empty directory. A symlink is removed
+    /// itself, not what it points to.
+    pub fn remove(&self, path: &str) -> Result<()> {
+        let walk = self.walk(path, false)?;
+        if self.mounts.read().contains_key(&walk.path()) {
+            return Err(Error::Busy);
+        }
+        let (parent, name) = self.parent(path)?;
+        parent.fs.remove(parent.id, name)
+    }
+
+    fn create(&self, path: &str, file_type: FileType) -> Result<Inode> {
+        let (parent, name) = self.parent(path)?;
+        let id = parent.fs.create(parent.id, name, file_type)?;
+        Ok(Inode { fs: parent.fs, id })
+    }
+
+    // The directory that holds, or would hold, what `path` names, and the name
+    fn parent<'a>(&self, path: &'a str) -> Result<(Inode, &'a str)> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = match path.rsplit_once('/') {
+            Some(("", name)) => ("/", name),
+            Some((parent, name)) => (parent, name),
+            None => (".", path),
+        };
+        if !super::valid_name(name) {
+            return Err(Error::InvalidPath);
+        }
+        let parent = self.lookup(parent)?;
+        if parent.metadata()?.file_type != FileType::Directory {
+            return Err(Error::NotADirectory);
+        }
+        Ok((parent, name))
+    }
+
+    // Resolves `path` one name at a time, switching to the file systems mounted
+    // on the way. A symlink at the end is only followed if `follow` is set.
+    fn walk(&self, path: &str, follow: bool) -> Result<Walk> {
+        let mounts = self.mounts.read();
+        let root = mounts.get("/").ok_or(Error::NotFound)?;
+        let root = Inode {
+            fs: root.clone(),
+            id: root.root(),
+        };
+
+        let mut names: VecDeque<String> = VecDeque::new();
+        if !path.starts_with('/') {
+            names.extend(split(&self.cwd.lock()));
+        }
+        names.extend(split(path));
+
+        let mut walk = Walk {
+            stack: alloc::vec![(String::new(), root.clone())],
+        };
+        let mut links = 0;
+        while let Some(name) = names.pop_front() {
+            let dir = walk.inode();
+            if dir.metadata()?.file_type != FileType::Directory {
+                return Err(Error::NotADirectory);
+            }
+            match name.as_str() {
+                "." => continue,
+                ".." => {
+                    if walk.stack.len() > 1 {
+                        walk.stack.pop();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let mut inode = Inode {
+                fs: dir.fs.clone(),
+                id: dir.fs.lookup(dir.id, &name)?,
+            };
+            walk.stack.push((name, inode.clone()));
+            if let Some(fs) = mounts.get(&walk.path()) {
+                inode = Inode {
+                    fs: fs.clone(),
+                    id: fs.root(),
+                };
+                walk.stack.last_mut().unwrap().1 = inode.clone();
+            }
+
+            let last = names.is_empty();
+            if inode.metadata()?.file_type == FileType::Symlink && (follow || !last) {
+                links += 1;
+                if links > MAX_SYMLINKS {
+                    return Err(Error::TooManyLinks);
+                }
+                // Carry on from the directory holding the link, or the root
+                walk.stack.pop();
+                let target = inode.fs.read_link(inode.id)?;
+                if target.starts_with('/') {
+                    walk.stack.truncate(1);
+                }
+                for name in split(&target).rev() {
+                    names.push_front(name);
+                }
+            }
+        }
+        Ok(walk)
+    }
+}
+
+fn split(path: &str) -> impl DoubleEndedIterator<Item = String> + '_ {
+    path.split('/')
+        .filter(|name| !name.is_empty())
+        .map(ToString::to_string)
+}
+
+/// An open file or directory
+pub struct File {
+    inode: Inode,
+    offset: u64,
+    flags: OpenFlags,
+}
+
+impl File {
+    pub fn inode(&self) -> &Inode {
+        &self.inode
+    }
+
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.inode.metadata()
+    }
+
+    /// Reads from the current offset, returning 0 at the end of the file
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if !self.flags.contains(OpenFlags::READ) {
+            return Err(Error::PermissionDenied);
+        }
+        if self.metadata()?.file_type == FileType::Directory {
+            return Err(Error::IsADirectory);
+        }
+        let read = self.inode.fs.read(self.inode.id, self.offset, buf)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+
+    /// Reads the rest of the file, appending it to `data`
+    pub fn read_to_end(&mut self, data: &mut Vec<u8>) -> Result<usize> {
+        let start = data.len();
+        let mut buf = [0; 512];
+        loop {
+            match self.read(&mut buf)? {
+                0 => return Ok(data.len() - start),
+                read => data.extend_from_slice(&buf[..read]),
+            }
+        }
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if !self.flags.contains(OpenFlags::WRITE) {
+            return Err(Error::PermissionDenied);
+        }
+        if self.flags.contains(OpenFlags::APPEND) {
+            self.offset = self.metadata()?.size;
+        }
+        let written = self.inode.fs.write(self.inode.id, self.offset, buf)?;
+        self.offset += written as u64;
+        Ok(written)
+    }
+
+    pub fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Error::InvalidArgument),
+                written => buf = &buf[written..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the offset, which may go past the end of the file. Returns the new
+    /// offset.
+    pub fn seek(&mut self, position: SeekFrom) -> Result<u64> {
+        let (base, delta) = match position {
+            SeekFrom::Start(offset) => (offset, 0),
+            SeekFrom::End(delta) => (self.metadata()?.size, delta),
+            SeekFrom::Current(delta) => (self.offset, delta),
+        };
+        self.offset = base
+            .checked_add_signed(delta)
+            .ok_or(Error::InvalidArgument)?;
+        Ok(self.offset)
+    }
+
+    pub fn set_len(&mut self, size: u64) -> Result<()> {
+        if !self.flags.contains(OpenFlags::WRITE) {
+            return Err(Error::PermissionDenied);
+        }
+        self.inode.fs.truncate(self.inode.id, size)
+    }
+
+    pub fn read_dir(&self) -> Result<Vec<DirEntry>> {
+        self.inode.fs.read_dir(self.inode.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TmpFs;
+    use super::*;
+
+    fn vfs() -> Vfs {
+        let vfs = Vfs::new();
+        vfs.mount("/", Arc::new(TmpFs::new())).unwrap();
+        vfs
+    }
+
+    #[test]
+    fn files_are_read_and_written() {
+        let vfs = vfs();
+        vfs.write("/hello.txt", b"Hello").unwrap();
+        assert_eq!(vfs.read("/hello.txt").unwrap(), b"Hello");
+
+        let mut file = vfs
+            .open("/hello.txt", OpenFlags::READ | OpenFlags::WRITE)
+            .unwrap();
+        assert_eq!(file.seek(SeekFrom::End(0)), Ok(5));
+        file.write_all(b", world").unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(-5)), Ok(7));
+        let mut buf = [0; 16];
+        assert_eq!(file.read(&mut buf), Ok(5));
+        assert_eq!(&buf[..5], b"world");
+        assert_eq!(file.read(&mut buf), Ok(0));
+        assert_eq!(
+            file.seek(SeekFrom::Current(-20)),
+            Err(Error::InvalidArgument)
+        );
+
+        // Writing past the end leaves a hole of zeros
+        file.seek(SeekFrom::Start(14)).unwrap();
+        file.write_all(b"!").unwrap();
+        assert_eq!(vfs.read("/hello.txt").unwrap(), b"Hello, world\0\0!");
+        assert_eq!(vfs.metadata("/hello.txt").unwrap().size, 15);
+    }
+
+    #[test]
+    fn open_flags() {
+        let vfs = vfs();
+        assert_eq!(vfs.open("/a", OpenFlags::READ).err(), Some(Error::NotFound));
+        let flags = OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::EXCLUSIVE;
+        let mut file = vfs.open("/a", flags).unwrap();
+        file.write_all(b"abc").unwrap();
+        assert_eq!(vfs.open("/a", flags).err(), Some(Error::AlreadyExists));
+
+        let mut buf = [0; 4];
+        assert_eq!(file.read(&mut buf), Err(Error::PermissionDenied));
+        let mut file = vfs.open("/a", OpenFlags::READ).unwrap();
+        assert_eq!(file.write(b"x"), Err(Error::PermissionDenied));
+
+        let mut file = vfs
+            .open("/a", OpenFlags::WRITE | OpenFlags::APPEND)
+            .unwrap();
+        file.write_all(b"d").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(b"e").unwrap();
+        assert_eq!(vfs.read("/a").unwrap(), b"abcde");
+
+        vfs.open("/a", OpenFlags::WRITE | OpenFlags::TRUNCATE)
+            .unwrap();
+        assert_eq!(vfs.read("/a").unwrap(), b"");
+        assert_eq!(
+            vfs.open("/", OpenFlags::WRITE).err(),
+            Some(Error::IsADirectory)
+        );
+    }
+
+    #[test]
+    fn paths_are_resolved() {
+        let vfs = vfs();
+        vfs.create_dir("/usr").unwrap();
+        vfs.create_dir("/usr/share/").unwrap();
+        vfs.write("/usr/share/font", b"font").unwrap();
+
+        assert_eq!(
+            vfs.canonicalize("/usr/./share/../share//font").unwrap(),
+            "/usr/share/font"
+        );
+        assert_eq!(vfs.canonicalize("/../..").unwrap(), "/");
+        assert_eq!(
+            vfs.metadata("/usr/share/font/..").err(),
+            Some(Error::NotADirectory)
+        );
+        assert_eq!(vfs.create_dir("/usr/missing/dir"), Err(Error::NotFound));
+        assert_eq!(vfs.create_dir("/usr/.."), Err(Error::InvalidPath));
+        assert_eq!(vfs.create_dir("/usr"), Err(Error::AlreadyExists));
+
+        // Relative to the current directory
+        assert_eq!(vfs.cwd(), "/");
+        vfs.set_cwd("usr").unwrap();
+        assert_eq!(vfs.read("share/font").unwrap(), b"font");
+        vfs.set_cwd("share/..//share").unwrap();
+        assert_eq!(vfs.cwd(), "/usr/share");
+        assert_eq!(vfs.read("../../usr/share/font").unwrap(), b"font");
+        assert_eq!(vfs.set_cwd("font"), Err(Error::NotADirectory));
+        vfs.write("new", b"").unwrap();
+        assert!(vfs.metadata("/usr/share/new").is_ok());
+    }
+
+    #[test]
+    fn symlinks_are_followed() {
+        let vfs = vfs();
+        vfs.create_dir("/data").unwrap();
+        vfs.create_dir("/data/fonts").unwrap();
+        vfs.write("/data/fonts/mono", b"mono").unwrap();
+        vfs.symlink("data/fonts", "/fonts").unwrap();
+        vfs.symlink("/data/fonts/mono", "/data/default").unwrap();
+        vfs.symlink("..", "/data/fonts/up").unwrap();
+
+        assert_eq!(vfs.read("/fonts/mono").unwrap(), b"mono");
+        assert_eq!(vfs.read("/data/default").unwrap(), b"mono");
+        assert_eq!(
+            vfs.canonicalize("/fonts/up/default").unwrap(),
+            "/data/fonts/mono"
+        );
+        // `..` after a symlink goes to the parent of its target
+        assert_eq!(vfs.canonicalize("/fonts/..").unwrap(), "/data");
+
+        assert_eq!(vfs.read_link("/fonts").unwrap(), "data/fonts");
+        let metadata = vfs.symlink_metadata("/fonts").unwrap();
+        assert_eq!(metadata.file_type, FileType::Symlink);
+        assert_eq!(
+            vfs.metadata("/fonts").unwrap().file_type,
+            FileType::Directory
+        );
+
+        vfs.symlink("/loop", "/loop").unwrap();
+        assert_eq!(vfs.read("/loop").err(), Some(Error::TooManyLinks));
+        // Removing a symlink leaves its target alone
+        vfs.remove("/fonts").unwrap();
+        assert!(vfs.metadata("/data/fonts").is_ok());
+    }
+
+    #[test]
+    fn mounts() {
+        let vfs = vfs();
+        vfs.create_dir("/mnt").unwrap();
+        vfs.write("/mnt/hidden", b"").unwrap();
+        assert_eq!(
+            vfs.mount("/mnt/hidden", Arc::new(TmpFs::new())),
+            Err(Error::NotADirectory)
+        );
+        vfs.mount("/mnt", Arc::new(TmpFs::new())).unwrap();
+        assert_eq!(vfs.mount("/mnt/", Arc::new(TmpFs::new())), Err(Error::Busy));
+
+        // The mount hides the directory below it
+        assert_eq!(vfs.metadata("/mnt/hidden").err(), Some(Error::NotFound));
+        vfs.write("/mnt/file", b"on the mount").unwrap();
+        assert_eq!(vfs.read("/mnt/../mnt/file").unwrap(), b"on the mount");
+        assert_eq!(vfs.canonicalize("/mnt/..").unwrap(), "/");
+        assert_eq!(vfs.remove("/mnt"), Err(Error::Busy));
+        assert_eq!(
+            vfs.mounts(),
+            [
+                (String::from("/"), "tmpfs"),
+                (String::from("/mnt"), "tmpfs")
+            ]
+        );
+
+        let file = vfs.open("/mnt/file", OpenFlags::READ).unwrap();
+        assert_eq!(vfs.unmount("/mnt"), Err(Error::Busy));
+        drop(file);
+        vfs.unmount("/mnt").unwrap();
+        assert!(vfs.metadata("/mnt/hidden").is_ok());
+        assert_eq!(vfs.unmount("/mnt"), Err(Error::InvalidArgument));
+    }
+
+    #[test]
+    fn directories_are_listed() {
+        let vfs = vfs();
+        vfs.create_dir("/dir").unwrap();
+        vfs.write("/dir/b", b"").unwrap();
+        vfs.create_dir("/dir/a").unwrap();
+        let names: Vec<String> = vfs
+            .read_dir("/dir")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+
+        assert_eq!(vfs.remove("/dir"), Err(Error::DirectoryNotEmpty));
+        vfs.remove("/dir/a").unwrap();
+        vfs.remove("/dir/b").unwrap();
+        vfs.remove("/dir").unwrap();
+        assert!(vfs.read_dir("/").unwrap().is_empty());
+    }
+}
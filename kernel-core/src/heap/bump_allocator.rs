@@ -0,0 +1,94 @@
+use super::utils::{align_up, Locked};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::null_mut;
+
+pub struct BumpAllocator {
+    heap_start: usize,
+    heap_end: usize,
+    next: usize,
+    allocations: usize,
+}
+
+impl BumpAllocator {
+    pub const fn new() -> Self {
+        Self {
+            heap_start: 0,
+            heap_end: 0,
+            next: 0,
+            allocations: 0,
+        }
+    }
+
+    /// # Safety
+    /// The heap must be unused memory that stays mapped for as long as the
+    /// allocator is, and `init` must only be called once
+    pub unsafe fn init(&mut self, heap_start: usize, heap_size: usize) {
+        self.heap_start = heap_start;
+        self.heap_end = heap_start + heap_size;
+        self.next = heap_start;
+    }
+}
+
+impl Default for BumpAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for Locked<BumpAllocator> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut allocator = self.lock();
+        let alloc_start = align_up(allocator.next, layout.align());
+        let alloc_end = match alloc_start.checked_add(layout.size()) {
+            Some(end) => end,
+            None => return null_mut(),
+        };
+        if alloc_end > allocator.heap_end {
+            null_mut()
+        } else {
+            allocator.next = alloc_end;
+            allocator.allocations += 1;
+            alloc_start as *mut u8
+        }
+    }
+
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
+        let mut allocator = self.lock();
+
+        allocator.allocations -= 1;
+        if allocator.allocations == 0 {
+            allocator.next = allocator.heap_start;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap::testing::{exercise, Arena};
+
+    #[test]
+    fn random_allocations() {
+        let arena = Arena::new(64 * 1024);
+        for seed in 1..=16 {
+            let allocator = Locked::new(BumpAllocator::new());
+            unsafe { allocator.lock().init(arena.start(), arena.size()) };
+            assert!(exercise(&allocator, &arena, seed, 2000) > 0);
+
+            // Everything was freed, so it starts over
+            assert_eq!(allocator.lock().next, arena.start());
+        }
+    }
+
+    #[test]
+    fn fails_when_full() {
+        let arena = Arena::new(4096);
+        let allocator = Locked::new(BumpAllocator::new());
+        unsafe {
+            allocator.lock().init(arena.start(), arena.size());
+            let layout = Layout::from_size_align(4096, 8).unwrap();
+            assert_eq!(allocator.alloc(layout) as usize, arena.start());
+            assert!(allocator.alloc(Layout::new::<u8>()).is_null());
+        }
+    }
+}
@@ -6,15 +6,26 @@ use super::utils::Locked;
 
 pub struct LinkedListAllocator {
     head: ListNode,
+    // Bytes handed out, including the padding added by `size_align`
+    used: usize,
 }
 
 impl LinkedListAllocator {
     pub const fn new() -> Self {
         Self {
             head: ListNode::new(0),
+            used: 0,
         }
     }
 
+    /// How much of the heap is allocated
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// # Safety
+    /// The heap must be unused memory that stays mapped for as long as the
+    /// allocator is, and `init` must only be called once
     pub unsafe fn init(&mut self, heap_start: usize, heap_size: usize) {
         self.add_free_region(heap_start, heap_size);
     }
@@ -37,7 +48,7 @@ impl LinkedListAllocator {
         let mut current = &mut self.head;
 
         while let Some(ref mut region) = current.next {
-            if let Ok(alloc_start) = Self::alloc_from_region(&region, size, align) {
+            if let Some(alloc_start) = Self::alloc_from_region(region, size, align) {
                 let next = region.next.take();
                 let ret = Some((current.next.take().unwrap(), alloc_start));
                 current.next = next;
@@ -49,24 +60,26 @@ impl LinkedListAllocator {
         None
     }
 
-    pub fn alloc_from_region(region: &ListNode, size: usize, align: usize) -> Result<usize, ()> {
+    /// Where an allocation of `size` bytes aligned to `align` would start in
+    /// `region`, if it fits
+    pub fn alloc_from_region(region: &ListNode, size: usize, align: usize) -> Option<usize> {
         let alloc_start = align_up(region.start_addr(), align);
-        let alloc_end = alloc_start.checked_add(size).ok_or(())?;
+        let alloc_end = alloc_start.checked_add(size)?;
 
         if alloc_end > region.end_addr() {
             // region too small
-            return Err(());
+            return None;
         }
 
         let excess_size = region.end_addr() - alloc_end;
         if excess_size > 0 && excess_size < core::mem::size_of::<ListNode>() {
             // rest of region too small to hold a ListNode (required because the
             // allocation splits the region in a used and a free part)
-            return Err(());
+            return None;
         }
 
         // region suitable for allocation
-        Ok(alloc_start)
+        Some(alloc_start)
     }
 
     pub fn size_align(layout: Layout) -> (usize, usize) {
@@ -79,6 +92,12 @@ impl LinkedListAllocator {
     }
 }
 
+impl Default for LinkedListAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 unsafe impl GlobalAlloc for Locked<LinkedListAllocator> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let mut allocator = self.lock();
@@ -90,6 +109,7 @@ unsafe impl GlobalAlloc for Locked<LinkedListAllocator> {
             if excess_size > 0 {
                 allocator.add_free_region(alloc_end, excess_size);
             }
+            allocator.used += size;
             alloc_start as *mut u8
         } else {
             core::ptr::null_mut()
@@ -99,5 +119,56 @@ unsafe impl GlobalAlloc for Locked<LinkedListAllocator> {
         let mut allocator = self.lock();
         let (size, _) = LinkedListAllocator::size_align(layout);
         allocator.add_free_region(ptr as usize, size);
+        allocator.used -= size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap::testing::{exercise, Arena};
+    use core::mem::size_of;
+
+    #[test]
+    fn alloc_from_region_checks_the_fit() {
+        let region = ListNode::new(64);
+        let start = region.start_addr();
+        let alloc = |size| LinkedListAllocator::alloc_from_region(&region, size, 8);
+
+        assert_eq!(alloc(64), Some(start));
+        assert_eq!(alloc(65), None);
+        // The rest must be able to hold a node, or be empty
+        let fits = 64 - size_of::<ListNode>();
+        assert_eq!(alloc(fits), Some(start));
+        assert_eq!(alloc(fits + 1), None);
+        assert_eq!(alloc(usize::MAX), None);
+    }
+
+    #[test]
+    fn alloc_from_region_aligns_the_start() {
+        let arena = Arena::new(8192);
+        // A region starting just past a page boundary
+        let region = unsafe {
+            let node = (arena.start() + 16) as *mut ListNode;
+            node.write(ListNode::new(8192 - 16));
+            &*node
+        };
+        let alloc = |size| LinkedListAllocator::alloc_from_region(region, size, 4096);
+
+        assert_eq!(alloc(64), Some(arena.start() + 4096));
+        assert_eq!(alloc(4096), Some(arena.start() + 4096));
+        // The part before the page boundary doesn't count
+        assert_eq!(alloc(4097), None);
+    }
+
+    #[test]
+    fn random_allocations() {
+        let arena = Arena::new(64 * 1024);
+        for seed in 1..=16 {
+            let allocator = Locked::new(LinkedListAllocator::new());
+            unsafe { allocator.lock().init(arena.start(), arena.size()) };
+            assert!(exercise(&allocator, &arena, seed, 10_000) > 0);
+            assert_eq!(allocator.lock().used(), 0, "seed {}", seed);
+        }
     }
 }
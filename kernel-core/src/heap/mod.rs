@@ -0,0 +1,6 @@
+pub mod bump_allocator;
+pub mod linked_list_allocator;
+pub mod utils;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,150 @@
+//! Helpers for testing the allocators on the host
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    vec::Vec,
+};
+
+/// Memory from the host allocator to run a kernel allocator in
+pub struct Arena {
+    start: *mut u8,
+    layout: Layout,
+}
+
+impl Arena {
+    pub fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, 4096).unwrap();
+        let start = unsafe { std::alloc::alloc(layout) };
+        assert!(!start.is_null(), "host allocation failed");
+        Self { start, layout }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start as usize
+    }
+
+    pub fn size(&self) -> usize {
+        self.layout.size()
+    }
+
+    pub fn end(&self) -> usize {
+        self.start() + self.size()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        unsafe { std::alloc::dealloc(self.start, self.layout) }
+    }
+}
+
+/// xorshift64*, enough to shuffle allocation patterns around without a
+/// dependency. Seeded, so a failure can be reproduced.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Zero is a fixed point
+        Self(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+struct Allocation {
+    ptr: *mut u8,
+    layout: Layout,
+    fill: u8,
+}
+
+impl Allocation {
+    fn start(&self) -> usize {
+        self.ptr as usize
+    }
+
+    fn end(&self) -> usize {
+        self.start() + self.layout.size()
+    }
+}
+
+/// Allocates and frees randomly sized and aligned blocks from `allocator`,
+/// which manages `arena`, checking that every block lies inside the arena,
+/// is aligned, doesn't overlap another one and keeps its contents until it is
+/// freed. Frees everything at the end and returns how many allocations
+/// succeeded.
+pub fn exercise(allocator: &impl GlobalAlloc, arena: &Arena, seed: u64, steps: usize) -> usize {
+    let mut rng = Rng::new(seed);
+    let mut live: Vec<Allocation> = Vec::new();
+    let mut succeeded = 0;
+
+    for step in 0..steps {
+        if live.is_empty() || rng.below(2) == 0 {
+            let size = 1 + rng.below(256) as usize;
+            let align = 1 << rng.below(7);
+            let layout = Layout::from_size_align(size, align).unwrap();
+            let ptr = unsafe { allocator.alloc(layout) };
+            if ptr.is_null() {
+                // Out of memory, or too fragmented
+                continue;
+            }
+
+            let allocation = Allocation {
+                ptr,
+                layout,
+                fill: step as u8,
+            };
+            assert_eq!(allocation.start() % align, 0, "seed {}: misaligned", seed);
+            assert!(
+                allocation.start() >= arena.start() && allocation.end() <= arena.end(),
+                "seed {}: {:#x}..{:#x} is outside the arena",
+                seed,
+                allocation.start(),
+                allocation.end()
+            );
+            for other in &live {
+                assert!(
+                    allocation.end() <= other.start() || other.end() <= allocation.start(),
+                    "seed {}: {:#x}..{:#x} overlaps {:#x}..{:#x}",
+                    seed,
+                    allocation.start(),
+                    allocation.end(),
+                    other.start(),
+                    other.end()
+                );
+            }
+            unsafe { allocation.ptr.write_bytes(allocation.fill, size) };
+            live.push(allocation);
+            succeeded += 1;
+        } else {
+            let index = rng.below(live.len() as u64) as usize;
+            free(allocator, live.swap_remove(index), seed);
+        }
+    }
+
+    for allocation in live {
+        free(allocator, allocation, seed);
+    }
+    succeeded
+}
+
+fn free(allocator: &impl GlobalAlloc, allocation: Allocation, seed: u64) {
+    let contents = unsafe { std::slice::from_raw_parts(allocation.ptr, allocation.layout.size()) };
+    assert!(
+        contents.iter().all(|&byte| byte == allocation.fill),
+        "seed {}: {:#x}..{:#x} was overwritten",
+        seed,
+        allocation.start(),
+        allocation.end()
+    );
+    unsafe { allocator.dealloc(allocation.ptr, allocation.layout) };
+}
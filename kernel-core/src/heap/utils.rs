@@ -9,9 +9,15 @@ impl<A> Locked<A> {
         }
     }
 
-    pub fn lock(&self) -> spin::MutexGuard<A> {
+    pub fn lock(&self) -> spin::MutexGuard<'_, A> {
         self.inner.lock()
     }
+
+    /// # Safety
+    /// Whoever holds the lock must never run again
+    pub unsafe fn force_unlock(&self) {
+        self.inner.force_unlock()
+    }
 }
 
 pub fn align_up(addr: usize, align: usize) -> usize {
@@ -36,3 +42,23 @@ impl ListNode {
         self.start_addr() + self.size
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_up_rounds_to_the_next_multiple() {
+        assert_eq!(align_up(0, 8), 0);
+        assert_eq!(align_up(1, 8), 8);
+        assert_eq!(align_up(8, 8), 8);
+        assert_eq!(align_up(9, 1), 9);
+        assert_eq!(align_up(0x1001, 0x1000), 0x2000);
+    }
+
+    #[test]
+    fn list_node_covers_its_size() {
+        let node = ListNode::new(64);
+        assert_eq!(node.end_addr() - node.start_addr(), 64);
+    }
+}
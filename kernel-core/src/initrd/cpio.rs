@@ -0,0 +1,140 @@
+//! newc cpio archives, the format of Linux initramfs images: a 110 byte header
+//! of hexadecimal fields, then the name and the contents, each padded to 4
+//! bytes. An entry called `TRAILER!!!` ends the archive.
+
+use super::{c_str, Entry, Error, Kind};
+use crate::heap::utils::align_up;
+
+const HEADER_SIZE: usize = 110;
+const TRAILER: &str = "TRAILER!!!";
+
+// The header starts with the magic number, followed by 8 digit fields in this order
+const MODE: usize = 1;
+const MTIME: usize = 5;
+const FILE_SIZE: usize = 6;
+const NAME_SIZE: usize = 11;
+
+// File types in the mode
+const TYPE_MASK: u32 = 0o170000;
+const TYPE_FILE: u32 = 0o100000;
+const TYPE_DIRECTORY: u32 = 0o040000;
+const TYPE_SYMLINK: u32 = 0o120000;
+
+/// Whether `data` starts with a newc header, with or without checksums
+pub fn has_magic(data: &[u8]) -> bool {
+    matches!(data.get(..6), Some(b"070701" | b"070702"))
+}
+
+/// Parses the entry at `offset`, returning it and the offset of the next one,
+/// or `None` at the end of the archive
+pub fn parse(data: &[u8], offset: usize) -> Result<Option<(Entry<'_>, usize)>, Error> {
+    if offset == data.len() {
+        return Ok(None);
+    }
+    let header = data
+        .get(offset..offset + HEADER_SIZE)
+        .ok_or(Error::Truncated)?;
+    if !has_magic(header) {
+        return Err(Error::BadHeader);
+    }
+
+    // Includes the NUL at the end
+    let name_size = field(header, NAME_SIZE)? as usize;
+    if name_size == 0 {
+        return Err(Error::BadHeader);
+    }
+    let name_start = offset + HEADER_SIZE;
+    let name = data
+        .get(name_start..name_start + name_size)
+        .ok_or(Error::Truncated)?;
+    let name = c_str(name)?;
+    if name == TRAILER {
+        return Ok(None);
+    }
+
+    let size = field(header, FILE_SIZE)? as usize;
+    let start = align_up(name_start + name_size, 4);
+    let contents = data.get(start..start + size).ok_or(Error::Truncated)?;
+    let mode = field(header, MODE)?;
+    let kind = match mode & TYPE_MASK {
+        TYPE_FILE => Kind::File,
+        TYPE_DIRECTORY => Kind::Directory,
+        TYPE_SYMLINK => Kind::Symlink,
+        _ => Kind::Other,
+    };
+
+    let entry = Entry {
+        prefix: "",
+        name,
+        kind,
+        mode: mode & 0o7777,
+        mtime: field(header, MTIME)? as u64,
+        data: if kind == Kind::File { contents } else { &[] },
+        // A symlink's contents are its target
+        link: match kind {
+            Kind::Symlink => core::str::from_utf8(contents).map_err(|_| Error::BadHeader)?,
+            _ => "",
+        },
+    };
+    Ok(Some((entry, align_up(start + size, 4))))
+}
+
+// The header field at `index`, 8 hexadecimal digits
+fn field(header: &[u8], index: usize) -> Result<u32, Error> {
+    let start = 6 + index * 8;
+    let digits = core::str::from_utf8(&header[start..start + 8]).map_err(|_| Error::BadHeader)?;
+    u32::from_str_radix(digits, 16).map_err(|_| Error::BadHeader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{cpio, Member};
+    use super::super::Archive;
+    use super::*;
+
+    #[test]
+    fn the_trailer_ends_the_archive() {
+        let mut data = cpio(&[Member::file("a", b"1")]);
+        // Padding after the trailer, and an entry that shouldn't be read
+        data.extend_from_slice(&[0; 512]);
+        data.extend_from_slice(&cpio(&[Member::file("b", b"2")]));
+
+        let archive = Archive::new(&data).unwrap();
+        assert_eq!(archive.entries().count(), 1);
+        assert_eq!(archive.read("a"), Some(&b"1"[..]));
+        assert_eq!(archive.read("b"), None);
+    }
+
+    #[test]
+    fn contents_are_padded() {
+        // Names and contents of every length modulo 4
+        let files = [
+            Member::file("a", b"1"),
+            Member::file("ab", b"12"),
+            Member::file("abc", b"123"),
+            Member::file("abcd", b"1234"),
+        ];
+        let data = cpio(&files);
+        let archive = Archive::new(&data).unwrap();
+        for file in &files {
+            assert_eq!(archive.read(file.path), Some(file.data));
+        }
+        let mode = archive.get("abc").unwrap().mode;
+        assert_eq!(mode, 0o644);
+    }
+
+    #[test]
+    fn corrupt_archives_are_errors() {
+        let data = cpio(&[Member::file("a", b"data")]);
+
+        let mut corrupt = data.clone();
+        corrupt[6 + FILE_SIZE * 8] = b'x';
+        let mut entries = Archive::new(&corrupt).unwrap().entries();
+        assert_eq!(entries.next().unwrap().unwrap_err(), Error::BadHeader);
+        assert!(entries.next().is_none());
+
+        let truncated = &data[..HEADER_SIZE + 4];
+        let mut entries = Archive::new(truncated).unwrap().entries();
+        assert_eq!(entries.next().unwrap().unwrap_err(), Error::Truncated);
+    }
+}
@@ -0,0 +1,287 @@
+//! Readers for the archives GRUB can load as the initial ramdisk, USTAR tar and
+//! newc cpio. Entries borrow from the archive, nothing is copied or allocated.
+
+pub mod cpio;
+pub mod tar;
+
+#[cfg(test)]
+pub(crate) mod testing;
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Neither a USTAR nor a newc archive
+    UnknownFormat,
+    /// An entry runs past the end of the archive
+    Truncated,
+    /// A header field that doesn't parse
+    BadHeader,
+    /// A tar header whose checksum doesn't match
+    BadChecksum,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::UnknownFormat => "not a tar or cpio archive",
+            Error::Truncated => "archive is truncated",
+            Error::BadHeader => "malformed header",
+            Error::BadChecksum => "header checksum mismatch",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// POSIX USTAR, e.g. `tar --format=ustar`
+    Tar,
+    /// The "new" ASCII cpio format, e.g. `cpio -o -H newc`
+    Cpio,
+}
+
+impl Format {
+    /// Recognizes an archive by its magic number
+    pub fn detect(data: &[u8]) -> Option<Format> {
+        if cpio::has_magic(data) {
+            Some(Format::Cpio)
+        } else if tar::has_magic(data) {
+            Some(Format::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Directory,
+    Symlink,
+    /// Devices, hard links, extended headers and such, which are skipped
+    Other,
+}
+
+/// A file or directory in an archive
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    // USTAR splits long paths into a prefix and a name, cpio only has a name
+    prefix: &'a str,
+    name: &'a str,
+    pub kind: Kind,
+    /// Permission bits
+    pub mode: u32,
+    /// Modification time, in seconds since the epoch
+    pub mtime: u64,
+    /// The contents, empty for anything but files
+    pub data: &'a [u8],
+    link: &'a str,
+}
+
+impl<'a> Entry<'a> {
+    /// The path, relative to the root of the archive
+    pub fn path(&self) -> Path<'a> {
+        Path {
+            prefix: trim(self.prefix),
+            name: trim(self.name),
+        }
+    }
+
+    /// Where a symlink points to
+    pub fn link_target(&self) -> Option<&'a str> {
+        match self.kind {
+            Kind::Symlink => Some(self.link),
+            _ => None,
+        }
+    }
+}
+
+/// The path of an entry, without the leading `./` or `/` and trailing `/`
+/// archivers add. The root directory is the empty path.
+#[derive(Debug, Clone, Copy)]
+pub struct Path<'a> {
+    prefix: &'a str,
+    name: &'a str,
+}
+
+impl Path<'_> {
+    /// Whether this is `path`, which is trimmed the same way
+    pub fn is(&self, path: &str) -> bool {
+        let path = trim(path);
+        if self.prefix.is_empty() {
+            return path == self.name;
+        }
+        path.strip_prefix(self.prefix)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .is_some_and(|rest| rest == self.name)
+    }
+}
+
+impl fmt::Display for Path<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.prefix.is_empty() {
+            f.write_str(self.name)
+        } else {
+            write!(f, "{}/{}", self.prefix, self.name)
+        }
+    }
+}
+
+// Drops the `./` and `/` in front of a path, and the `/` after a directory
+fn trim(mut path: &str) -> &str {
+    loop {
+        if let Some(rest) = path.strip_prefix("./") {
+            path = rest;
+        } else if let Some(rest) = path.strip_prefix('/') {
+            path = rest;
+        } else {
+            break;
+        }
+    }
+    let path = path.trim_end_matches('/');
+    if path == "." {
+        ""
+    } else {
+        path
+    }
+}
+
+/// A tar or cpio archive in memory
+#[derive(Debug, Clone, Copy)]
+pub struct Archive<'a> {
+    data: &'a [u8],
+    format: Format,
+}
+
+impl<'a> Archive<'a> {
+    /// Checks the magic number, the entries are only parsed when read
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let format = Format::detect(data).ok_or(Error::UnknownFormat)?;
+        Ok(Self { data, format })
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The entries in the order they were archived. Stops after the first
+    /// error, which is returned.
+    pub fn entries(&self) -> Entries<'a> {
+        Entries {
+            data: self.data,
+            offset: 0,
+            format: self.format,
+            done: false,
+        }
+    }
+
+    /// The entry at `path`, relative to the root of the archive. Searches up
+    /// to the first malformed entry.
+    pub fn get(&self, path: &str) -> Option<Entry<'a>> {
+        self.entries()
+            .map_while(Result::ok)
+            .find(|entry| entry.path().is(path))
+    }
+
+    /// The contents of the file at `path`. Symlinks aren't followed.
+    pub fn read(&self, path: &str) -> Option<&'a [u8]> {
+        self.get(path)
+            .filter(|entry| entry.kind == Kind::File)
+            .map(|entry| entry.data)
+    }
+}
+
+pub struct Entries<'a> {
+    data: &'a [u8],
+    offset: usize,
+    format: Format,
+    done: bool,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let parsed = match self.format {
+            Format::Tar => tar::parse(self.data, self.offset),
+            Format::Cpio => cpio::parse(self.data, self.offset),
+        };
+        match parsed {
+            Ok(Some((entry, next))) => {
+                self.offset = next;
+                Some(Ok(entry))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+// The bytes of a string field, up to the first NUL
+fn c_str(field: &[u8]) -> Result<&str, Error> {
+    let end = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+    core::str::from_utf8(&field[..end]).map_err(|_| Error::BadHeader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{cpio, tar, Member};
+    use super::*;
+
+    #[test]
+    fn paths_are_trimmed() {
+        assert_eq!(trim("./fonts/"), "fonts");
+        assert_eq!(trim("/bin/sh"), "bin/sh");
+        assert_eq!(trim("././a"), "a");
+        assert_eq!(trim("."), "");
+        assert_eq!(trim("./"), "");
+        assert_eq!(trim(".hidden"), ".hidden");
+    }
+
+    #[test]
+    fn both_formats_read_the_same() {
+        let files = [
+            Member::dir("./fonts/"),
+            Member::file("./fonts/8x16.psf", b"font"),
+            Member::file("./hello.txt", b"Hello\n"),
+            Member::symlink("./greeting", "hello.txt"),
+        ];
+        for data in [tar(&files), cpio(&files)] {
+            let archive = Archive::new(&data).unwrap();
+            assert_eq!(archive.entries().count(), 4);
+            assert!(archive.entries().all(|entry| entry.is_ok()));
+
+            assert_eq!(archive.read("fonts/8x16.psf"), Some(&b"font"[..]));
+            assert_eq!(archive.read("/hello.txt"), Some(&b"Hello\n"[..]));
+            assert_eq!(archive.read("fonts"), None);
+            assert_eq!(archive.read("missing"), None);
+            assert_eq!(archive.get("fonts/").unwrap().kind, Kind::Directory);
+
+            let link = archive.get("greeting").unwrap();
+            assert_eq!(link.kind, Kind::Symlink);
+            assert_eq!(link.link_target(), Some("hello.txt"));
+            assert_eq!(archive.read("greeting"), None);
+        }
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        assert_eq!(Archive::new(b"").unwrap_err(), Error::UnknownFormat);
+        assert_eq!(Archive::new(&[0; 1024]).unwrap_err(), Error::UnknownFormat);
+        let files = [Member::file("a", b"")];
+        assert_eq!(Format::detect(&tar(&files)), Some(Format::Tar));
+        assert_eq!(Format::detect(&cpio(&files)), Some(Format::Cpio));
+    }
+}
@@ -0,0 +1,155 @@
+//! POSIX USTAR archives: a 512 byte header before each entry, whose contents
+//! are padded to 512 bytes, and two blocks of zeros at the end
+
+use super::{c_str, Entry, Error, Kind};
+use crate::heap::utils::align_up;
+
+const BLOCK_SIZE: usize = 512;
+
+// Offsets of the header fields
+const NAME: usize = 0;
+const MODE: usize = 100;
+const SIZE: usize = 124;
+const MTIME: usize = 136;
+const CHECKSUM: usize = 148;
+const TYPE: usize = 156;
+const LINK_NAME: usize = 157;
+const MAGIC: usize = 257;
+const PREFIX: usize = 345;
+
+/// Whether `data` starts with a USTAR header. GNU tar writes `ustar  \0`,
+/// POSIX `ustar\0` followed by the version.
+pub fn has_magic(data: &[u8]) -> bool {
+    data.get(MAGIC..MAGIC + 5) == Some(b"ustar")
+}
+
+/// Parses the entry at `offset`, returning it and the offset of the next one,
+/// or `None` at the end of the archive
+pub fn parse(data: &[u8], offset: usize) -> Result<Option<(Entry<'_>, usize)>, Error> {
+    if offset == data.len() {
+        // Some archivers leave out the zero blocks
+        return Ok(None);
+    }
+    let header = data
+        .get(offset..offset + BLOCK_SIZE)
+        .ok_or(Error::Truncated)?;
+    if header.iter().all(|&byte| byte == 0) {
+        return Ok(None);
+    }
+    if !has_magic(header) {
+        return Err(Error::BadHeader);
+    }
+    if octal(&header[CHECKSUM..CHECKSUM + 8])? != checksum(header) {
+        return Err(Error::BadChecksum);
+    }
+
+    let size = octal(&header[SIZE..SIZE + 12])? as usize;
+    let start = offset + BLOCK_SIZE;
+    let contents = data.get(start..start + size).ok_or(Error::Truncated)?;
+    let kind = match header[TYPE] {
+        b'0' | b'\0' => Kind::File,
+        b'5' => Kind::Directory,
+        b'2' => Kind::Symlink,
+        _ => Kind::Other,
+    };
+    // Only POSIX archives have the prefix, GNU ones keep other fields there
+    let prefix = match &header[MAGIC..MAGIC + 6] {
+        b"ustar\0" => c_str(&header[PREFIX..PREFIX + 155])?,
+        _ => "",
+    };
+
+    let entry = Entry {
+        prefix,
+        name: c_str(&header[NAME..NAME + 100])?,
+        kind,
+        mode: octal(&header[MODE..MODE + 8])? as u32 & 0o7777,
+        mtime: octal(&header[MTIME..MTIME + 12])?,
+        data: if kind == Kind::File { contents } else { &[] },
+        link: c_str(&header[LINK_NAME..LINK_NAME + 100])?,
+    };
+    Ok(Some((entry, start + align_up(size, BLOCK_SIZE))))
+}
+
+// The sum of the header bytes, with the checksum field counted as spaces
+fn checksum(header: &[u8]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(index, &byte)| match index {
+            CHECKSUM..=155 => b' ' as u64,
+            _ => byte as u64,
+        })
+        .sum()
+}
+
+// A number in octal digits, padded with spaces or NULs
+fn octal(field: &[u8]) -> Result<u64, Error> {
+    let digits = field
+        .iter()
+        .skip_while(|&&byte| byte == b' ')
+        .take_while(|&&byte| byte != b' ' && byte != 0);
+    let mut value: u64 = 0;
+    for &digit in digits {
+        if !(b'0'..=b'7').contains(&digit) {
+            return Err(Error::BadHeader);
+        }
+        value = value
+            .checked_mul(8)
+            .map(|value| value + (digit - b'0') as u64)
+            .ok_or(Error::BadHeader)?;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{tar, Member};
+    use super::super::Archive;
+    use super::*;
+
+    #[test]
+    fn octal_fields() {
+        assert_eq!(octal(b"0000644\0"), Ok(0o644));
+        assert_eq!(octal(b"   755 \0"), Ok(0o755));
+        assert_eq!(octal(b"\0\0\0\0"), Ok(0));
+        assert_eq!(octal(b"0009\0"), Err(Error::BadHeader));
+    }
+
+    #[test]
+    fn long_paths_use_the_prefix() {
+        let mut data = tar(&[Member::file("font.psf", b"psf")]);
+        data[PREFIX..PREFIX + 11].copy_from_slice(b"share/fonts");
+        fix_checksum(&mut data);
+
+        let archive = Archive::new(&data).unwrap();
+        let entry = archive.entries().next().unwrap().unwrap();
+        assert_eq!(format!("{}", entry.path()), "share/fonts/font.psf");
+        assert_eq!(archive.read("/share/fonts/font.psf"), Some(&b"psf"[..]));
+        assert_eq!(archive.read("font.psf"), None);
+    }
+
+    #[test]
+    fn corrupt_archives_are_errors() {
+        let data = tar(&[Member::file("a", b"data"), Member::file("b", b"")]);
+
+        let mut corrupt = data.clone();
+        corrupt[NAME] = b'x';
+        let mut entries = Archive::new(&corrupt).unwrap().entries();
+        assert_eq!(entries.next().unwrap().unwrap_err(), Error::BadChecksum);
+        assert!(entries.next().is_none());
+
+        // Cut off in the middle of the first file's contents
+        let truncated = &data[..BLOCK_SIZE + 2];
+        let mut entries = Archive::new(truncated).unwrap().entries();
+        assert_eq!(entries.next().unwrap().unwrap_err(), Error::Truncated);
+
+        // Without the zero blocks at the end
+        let unterminated = &data[..3 * BLOCK_SIZE];
+        assert_eq!(Archive::new(unterminated).unwrap().entries().count(), 2);
+    }
+
+    fn fix_checksum(header: &mut [u8]) {
+        let sum = format!("{:06o}\0 ", checksum(&header[..BLOCK_SIZE]));
+        header[CHECKSUM..CHECKSUM + 8].copy_from_slice(sum.as_bytes());
+    }
+}
@@ -0,0 +1,118 @@
+//! Builds small archives for the tests, the way `tar --format=ustar` and
+//! `cpio -H newc` would
+
+use super::Kind;
+
+pub struct Member {
+    pub path: &'static str,
+    pub kind: Kind,
+    pub data: &'static [u8],
+}
+
+impl Member {
+    pub fn file(path: &'static str, data: &'static [u8]) -> Self {
+        Self {
+            path,
+            kind: Kind::File,
+            data,
+        }
+    }
+
+    pub fn dir(path: &'static str) -> Self {
+        Self {
+            path,
+            kind: Kind::Directory,
+            data: &[],
+        }
+    }
+
+    /// The target is stored as the data
+    pub fn symlink(path: &'static str, target: &'static str) -> Self {
+        Self {
+            path,
+            kind: Kind::Symlink,
+            data: target.as_bytes(),
+        }
+    }
+
+    fn mode(&self) -> u32 {
+        match self.kind {
+            Kind::Directory => 0o755,
+            Kind::Symlink => 0o777,
+            _ => 0o644,
+        }
+    }
+}
+
+pub fn tar(files: &[Member]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for file in files {
+        let mut header = [0u8; 512];
+        let mut put = |offset: usize, value: &[u8]| {
+            header[offset..offset + value.len()].copy_from_slice(value);
+        };
+        let (size, link, typ) = match file.kind {
+            Kind::Directory => (0, &[][..], b'5'),
+            Kind::Symlink => (0, file.data, b'2'),
+            _ => (file.data.len(), &[][..], b'0'),
+        };
+        put(0, file.path.as_bytes());
+        put(100, format!("{:07o}\0", file.mode()).as_bytes());
+        put(124, format!("{:011o}\0", size).as_bytes());
+        put(136, format!("{:011o}\0", 1_700_000_000).as_bytes());
+        put(156, &[typ]);
+        put(157, link);
+        put(257, b"ustar\x0000");
+        put(148, b"        ");
+        let sum: u32 = header.iter().map(|&byte| byte as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+
+        data.extend_from_slice(&header);
+        if typ == b'0' {
+            data.extend_from_slice(file.data);
+            data.resize(data.len().next_multiple_of(512), 0);
+        }
+    }
+    data.resize(data.len() + 1024, 0);
+    data
+}
+
+pub fn cpio(files: &[Member]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut entry = |path: &str, mode: u32, contents: &[u8]| {
+        let fields = [
+            1,
+            mode,
+            0,
+            0,
+            1,
+            1_700_000_000,
+            contents.len() as u32,
+            0,
+            0,
+            0,
+            0,
+            path.len() as u32 + 1,
+            0,
+        ];
+        data.extend_from_slice(b"070701");
+        for field in fields {
+            data.extend_from_slice(format!("{:08x}", field).as_bytes());
+        }
+        data.extend_from_slice(path.as_bytes());
+        data.push(0);
+        data.resize(data.len().next_multiple_of(4), 0);
+        data.extend_from_slice(contents);
+        data.resize(data.len().next_multiple_of(4), 0);
+    };
+    for file in files {
+        let typ = match file.kind {
+            Kind::Directory => 0o040000,
+            Kind::Symlink => 0o120000,
+            _ => 0o100000,
+        };
+        entry(file.path, typ | file.mode(), file.data);
+    }
+    entry("TRAILER!!!", 0, &[]);
+    data
+}
@@ -0,0 +1,14 @@
+//! The hardware independent logic of the kernel. `no_std` like the kernel,
+//! except for the unit tests, which run on the host.
+#![cfg_attr(not(test), no_std)]
+#![feature(const_mut_refs)]
+
+extern crate alloc;
+
+pub mod block;
+pub mod color;
+pub mod fs;
+pub mod heap;
+pub mod initrd;
+pub mod memory;
+pub mod paging;
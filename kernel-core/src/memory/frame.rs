@@ -0,0 +1,75 @@
+use crate::paging::PAGE_SIZE;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Frame {
+    pub number: u64,
+}
+
+impl Frame {
+    pub fn new(number: u64) -> Self {
+        Self { number }
+    }
+    pub fn containing_address(address: u64) -> Frame {
+        Frame {
+            number: address / PAGE_SIZE,
+        }
+    }
+
+    pub fn start_address(&self) -> u64 {
+        self.number * PAGE_SIZE
+    }
+    pub fn range_inclusive(start: Frame, end: Frame) -> FrameIter {
+        FrameIter { start, end }
+    }
+}
+
+pub struct FrameIter {
+    start: Frame,
+    end: Frame,
+}
+
+impl Iterator for FrameIter {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        if self.start <= self.end {
+            let frame = self.start.clone();
+            self.start.number += 1;
+            Some(frame)
+        } else {
+            None
+        }
+    }
+}
+
+pub trait FrameAllocator {
+    fn allocate_frame(&mut self) -> Option<Frame>;
+    fn deallocate_frame(&mut self, frame: Frame);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn containing_address_rounds_down() {
+        assert_eq!(Frame::containing_address(0), Frame::new(0));
+        assert_eq!(Frame::containing_address(0xfff), Frame::new(0));
+        assert_eq!(Frame::containing_address(0x1000), Frame::new(1));
+        assert_eq!(
+            Frame::containing_address(0x1234_5678).start_address(),
+            0x1234_5000
+        );
+    }
+
+    #[test]
+    fn range_is_inclusive() {
+        let numbers: [u64; 3] = [4, 5, 6];
+        let frames = Frame::range_inclusive(Frame::new(4), Frame::new(6));
+        assert!(frames.map(|frame| frame.number).eq(numbers));
+        assert_eq!(
+            Frame::range_inclusive(Frame::new(1), Frame::new(0)).count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use multiboot2::{ElfSection, ElfSectionFlags};
+
+use crate::memory::frame::Frame;
+
+bitflags::bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct EntryFlags: u64 {
+        const PRESENT = 1 << 0;
+        const WRITABLE = 1 << 1;
+        const USER_ACCESSIBLE = 1 << 2;
+        const WRITE_THROUGH = 1 << 3;
+        const NO_CACHE = 1 << 4;
+        const ACCESSED = 1 << 5;
+        const DIRTY = 1 << 6;
+        const HUGE_PAGE = 1 << 7;
+        const GLOBAL = 1 << 8;
+        const NO_EXECUTE = 1 << 63;
+    }
+}
+
+impl EntryFlags {
+    pub fn from_elf_section_flags(section: &ElfSection) -> EntryFlags {
+        Self::from_elf_flags(section.flags())
+    }
+
+    pub fn from_elf_flags(elf_flags: ElfSectionFlags) -> EntryFlags {
+        let mut flags = EntryFlags::empty();
+
+        if elf_flags.contains(ElfSectionFlags::ALLOCATED) {
+            // section is loaded to memory
+            flags |= EntryFlags::PRESENT;
+        }
+        if elf_flags.contains(ElfSectionFlags::WRITABLE) {
+            flags |= EntryFlags::WRITABLE;
+        }
+        if !elf_flags.contains(ElfSectionFlags::EXECUTABLE) {
+            flags |= EntryFlags::NO_EXECUTE;
+        }
+
+        flags
+    }
+}
+
+// Only ever made by the kernel, in place, as part of a page table
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct Entry(u64);
+
+impl Entry {
+    pub fn is_unused(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn set_unused(&mut self) {
+        self.0 = 0;
+    }
+
+    pub fn flags(&self) -> EntryFlags {
+        EntryFlags::from_bits_truncate(self.0)
+    }
+
+    pub fn pointed_frame(&self) -> Option<Frame> {
+        if self.flags().contains(EntryFlags::PRESENT) {
+            Some(Frame::containing_address(self.0 & 0x000fffff_fffff000))
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, frame: Frame, flags: EntryFlags) {
+        assert!(frame.start_address() & !0x000fffff_fffff000 == 0);
+        self.0 = frame.start_address() | flags.bits();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_of_elf_sections() {
+        // .text
+        let text =
+            EntryFlags::from_elf_flags(ElfSectionFlags::ALLOCATED | ElfSectionFlags::EXECUTABLE);
+        assert_eq!(text, EntryFlags::PRESENT);
+        // .data
+        let data =
+            EntryFlags::from_elf_flags(ElfSectionFlags::ALLOCATED | ElfSectionFlags::WRITABLE);
+        assert_eq!(
+            data,
+            EntryFlags::PRESENT | EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE
+        );
+        // .rodata
+        let rodata = EntryFlags::from_elf_flags(ElfSectionFlags::ALLOCATED);
+        assert_eq!(rodata, EntryFlags::PRESENT | EntryFlags::NO_EXECUTE);
+        // Not loaded, e.g. .symtab
+        assert_eq!(
+            EntryFlags::from_elf_flags(ElfSectionFlags::empty()),
+            EntryFlags::NO_EXECUTE
+        );
+    }
+
+    #[test]
+    fn entry_keeps_frame_and_flags() {
+        let mut entry = Entry(0);
+        assert!(entry.is_unused());
+        assert_eq!(entry.pointed_frame(), None);
+
+        let flags = EntryFlags::PRESENT | EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE;
+        entry.set(Frame::new(0x12345), flags);
+        assert_eq!(entry.flags(), flags);
+        assert_eq!(entry.pointed_frame(), Some(Frame::new(0x12345)));
+
+        entry.set(Frame::new(0x12345), EntryFlags::WRITABLE);
+        assert_eq!(entry.pointed_frame(), None);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod entry;
+pub mod page;
+
+pub const PAGE_SIZE: u64 = 4096; // 4KB
+
+pub type PhysAddr = usize;
+pub type VirtAddr = usize;
@@ -0,0 +1,110 @@
+use super::{VirtAddr, PAGE_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Page {
+    number: usize,
+}
+
+impl Page {
+    pub fn new(number: usize) -> Page {
+        Page { number }
+    }
+    pub fn containing_address(address: VirtAddr) -> Page {
+        assert!(
+            !(0x0000_8000_0000_0000..0xffff_8000_0000_0000).contains(&address),
+            "invalid address: 0x{:x}",
+            address
+        );
+        Page {
+            number: address / PAGE_SIZE as usize,
+        }
+    }
+
+    pub fn start_address(&self) -> usize {
+        self.number * PAGE_SIZE as usize
+    }
+
+    pub fn p4_index(&self) -> usize {
+        (self.number >> 27) & 0o777
+    }
+    pub fn p3_index(&self) -> usize {
+        (self.number >> 18) & 0o777
+    }
+    pub fn p2_index(&self) -> usize {
+        (self.number >> 9) & 0o777
+    }
+    pub fn p1_index(&self) -> usize {
+        self.number & 0o777
+    }
+
+    pub fn range_inclusive(start: Page, end: Page) -> PageIter {
+        PageIter { start, end }
+    }
+}
+
+pub struct PageIter {
+    start: Page,
+    end: Page,
+}
+
+impl Iterator for PageIter {
+    type Item = Page;
+
+    fn next(&mut self) -> Option<Page> {
+        if self.start <= self.end {
+            let page = self.start;
+            self.start.number += 1;
+            Some(page)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_indices() {
+        let address = (1 << 39) | (2 << 30) | (3 << 21) | (4 << 12) | 0x567;
+        let page = Page::containing_address(address);
+        assert_eq!(page.p4_index(), 1);
+        assert_eq!(page.p3_index(), 2);
+        assert_eq!(page.p2_index(), 3);
+        assert_eq!(page.p1_index(), 4);
+        assert_eq!(page.start_address(), address & !0xfff);
+    }
+
+    #[test]
+    fn higher_half_indices() {
+        let page = Page::containing_address(0xffff_8000_0000_0000);
+        assert_eq!(page.p4_index(), 256);
+        assert_eq!(page.p3_index(), 0);
+
+        let page = Page::containing_address(0xffff_ffff_ffff_ffff);
+        assert_eq!(
+            [
+                page.p4_index(),
+                page.p3_index(),
+                page.p2_index(),
+                page.p1_index()
+            ],
+            [511; 4]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid address")]
+    fn non_canonical_address() {
+        Page::containing_address(0x0000_8000_0000_0000);
+    }
+
+    #[test]
+    fn range_is_inclusive() {
+        let start = Page::containing_address(0x1000);
+        let end = Page::containing_address(0x3fff);
+        let pages: [Page; 3] = [Page::new(1), Page::new(2), Page::new(3)];
+        assert!(Page::range_inclusive(start, end).eq(pages));
+    }
+}
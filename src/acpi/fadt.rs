@@ -0,0 +1,68 @@
+use super::{sdt::Sdt, GenericAddress};
+
+/// FADT flag: the reset register is supported
+const RESET_REG_SUP: u32 = 1 << 10;
+
+/// The Fixed ACPI Description Table (signature "FACP")
+#[derive(Debug, Clone, Copy)]
+pub struct Fadt {
+    pub revision: u8,
+    pub firmware_ctrl: u64,
+    pub dsdt: u64,
+    pub sci_interrupt: u16,
+    pub smi_command_port: u32,
+    pub acpi_enable: u8,
+    pub acpi_disable: u8,
+    pub pm1a_event_block: u32,
+    pub pm1b_event_block: u32,
+    pub pm1a_control_block: u32,
+    pub pm1b_control_block: u32,
+    pub pm_timer_block: u32,
+    pub pm1_control_length: u8,
+    pub century: u8,
+    pub boot_architecture_flags: u16,
+    pub flags: u32,
+    pub reset_register: Option<GenericAddress>,
+    pub reset_value: u8,
+}
+
+impl Fadt {
+    pub fn parse(sdt: &Sdt) -> Self {
+        // Fields past the ACPI 1.0 layout only exist in longer tables
+        let firmware_ctrl = match sdt.get::<u64>(132) {
+            Some(address) if address != 0 => address,
+            _ => sdt.read::<u32>(36) as u64,
+        };
+        let dsdt = match sdt.get::<u64>(140) {
+            Some(address) if address != 0 => address,
+            _ => sdt.read::<u32>(40) as u64,
+        };
+        let flags = sdt.get(112).unwrap_or(0);
+        let reset_register = if flags & RESET_REG_SUP != 0 {
+            sdt.get::<GenericAddress>(116)
+        } else {
+            None
+        };
+
+        Fadt {
+            revision: sdt.revision(),
+            firmware_ctrl,
+            dsdt,
+            sci_interrupt: sdt.read(46),
+            smi_command_port: sdt.read(48),
+            acpi_enable: sdt.read(52),
+            acpi_disable: sdt.read(53),
+            pm1a_event_block: sdt.read(56),
+            pm1b_event_block: sdt.read(60),
+            pm1a_control_block: sdt.read(64),
+            pm1b_control_block: sdt.read(68),
+            pm_timer_block: sdt.read(76),
+            pm1_control_length: sdt.read(89),
+            century: sdt.read(108),
+            boot_architecture_flags: sdt.get(109).unwrap_or(0),
+            flags,
+            reset_register,
+            reset_value: sdt.get(128).unwrap_or(0),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use super::{sdt::Sdt, GenericAddress};
+
+/// The High Precision Event Timer description table (signature "HPET")
+#[derive(Debug, Clone, Copy)]
+pub struct Hpet {
+    pub hardware_revision: u8,
+    pub comparator_count: u8,
+    pub counter_is_64bit: bool,
+    pub legacy_replacement: bool,
+    pub pci_vendor_id: u16,
+    pub base_address: GenericAddress,
+    pub number: u8,
+    pub minimum_tick: u16,
+    pub page_protection: u8,
+}
+
+impl Hpet {
+    pub fn parse(sdt: &Sdt) -> Self {
+        let block_id: u32 = sdt.read(36);
+        Hpet {
+            hardware_revision: block_id as u8,
+            comparator_count: ((block_id >> 8) & 0x1F) as u8 + 1,
+            counter_is_64bit: block_id & (1 << 13) != 0,
+            legacy_replacement: block_id & (1 << 15) != 0,
+            pci_vendor_id: (block_id >> 16) as u16,
+            base_address: sdt.read(40),
+            number: sdt.read(52),
+            minimum_tick: sdt.read(53),
+            page_protection: sdt.read(55),
+        }
+    }
+}
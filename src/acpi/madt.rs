@@ -0,0 +1,199 @@
+use alloc::vec::Vec;
+
+use super::sdt::Sdt;
+
+const ENTRIES_OFFSET: usize = 44;
+
+const ENTRY_LOCAL_APIC: u8 = 0;
+const ENTRY_IO_APIC: u8 = 1;
+const ENTRY_INTERRUPT_SOURCE_OVERRIDE: u8 = 2;
+const ENTRY_LOCAL_APIC_NMI: u8 = 4;
+const ENTRY_LOCAL_APIC_ADDRESS_OVERRIDE: u8 = 5;
+const ENTRY_LOCAL_X2APIC: u8 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    ConformsToBus,
+    ActiveHigh,
+    ActiveLow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerMode {
+    ConformsToBus,
+    Edge,
+    Level,
+}
+
+// Decodes the MPS INTI flags used by overrides and NMI entries
+fn inti_flags(flags: u16) -> (Polarity, TriggerMode) {
+    let polarity = match flags & 0b11 {
+        0b01 => Polarity::ActiveHigh,
+        0b11 => Polarity::ActiveLow,
+        _ => Polarity::ConformsToBus,
+    };
+    let trigger = match (flags >> 2) & 0b11 {
+        0b01 => TriggerMode::Edge,
+        0b11 => TriggerMode::Level,
+        _ => TriggerMode::ConformsToBus,
+    };
+    (polarity, trigger)
+}
+
+// The length an entry needs to hold the fields read from it
+fn min_length(entry_type: u8) -> usize {
+    match entry_type {
+        ENTRY_LOCAL_APIC => 8,
+        ENTRY_IO_APIC => 12,
+        ENTRY_INTERRUPT_SOURCE_OVERRIDE => 10,
+        ENTRY_LOCAL_APIC_NMI => 6,
+        ENTRY_LOCAL_APIC_ADDRESS_OVERRIDE => 12,
+        ENTRY_LOCAL_X2APIC => 16,
+        _ => 2,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Processor {
+    pub processor_uid: u32,
+    pub apic_id: u32,
+    pub enabled: bool,
+    pub online_capable: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: u32,
+    pub gsi_base: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptSourceOverride {
+    pub bus: u8,
+    pub irq: u8,
+    pub gsi: u32,
+    pub polarity: Polarity,
+    pub trigger: TriggerMode,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LocalApicNmi {
+    /// 0xFF means all processors
+    pub processor_uid: u8,
+    pub polarity: Polarity,
+    pub trigger: TriggerMode,
+    pub lint: u8,
+}
+
+/// The Multiple APIC Description Table (signature "APIC")
+#[derive(Debug)]
+pub struct Madt {
+    pub local_apic_address: u64,
+    pub flags: u32,
+    pub processors: Vec<Processor>,
+    pub io_apics: Vec<IoApic>,
+    pub overrides: Vec<InterruptSourceOverride>,
+    pub local_apic_nmis: Vec<LocalApicNmi>,
+}
+
+impl Madt {
+    pub fn parse(sdt: &Sdt) -> Self {
+        let mut madt = Madt {
+            local_apic_address: sdt.read::<u32>(36) as u64,
+            flags: sdt.read(40),
+            processors: Vec::new(),
+            io_apics: Vec::new(),
+            overrides: Vec::new(),
+            local_apic_nmis: Vec::new(),
+        };
+
+        let mut offset = ENTRIES_OFFSET;
+        while offset + 2 <= sdt.length() {
+            let entry_type: u8 = sdt.read(offset);
+            let length = sdt.read::<u8>(offset + 1) as usize;
+            // A corrupt entry would have us read past the table, or loop forever
+            if length < 2 || offset + length > sdt.length() {
+                break;
+            }
+            // Too short for its fields, skipped rather than read past its end
+            if length < min_length(entry_type) {
+                log::warn!("MADT entry of type {} is only {} bytes", entry_type, length);
+                offset += length;
+                continue;
+            }
+
+            match entry_type {
+                ENTRY_LOCAL_APIC => {
+                    let flags: u32 = sdt.read(offset + 4);
+                    madt.processors.push(Processor {
+                        processor_uid: sdt.read::<u8>(offset + 2) as u32,
+                        apic_id: sdt.read::<u8>(offset + 3) as u32,
+                        enabled: flags & 1 != 0,
+                        online_capable: flags & 2 != 0,
+                    });
+                }
+                ENTRY_IO_APIC => madt.io_apics.push(IoApic {
+                    id: sdt.read(offset + 2),
+                    address: sdt.read(offset + 4),
+                    gsi_base: sdt.read(offset + 8),
+                }),
+                ENTRY_INTERRUPT_SOURCE_OVERRIDE => {
+                    let (polarity, trigger) = inti_flags(sdt.read(offset + 8));
+                    madt.overrides.push(InterruptSourceOverride {
+                        bus: sdt.read(offset + 2),
+                        irq: sdt.read(offset + 3),
+                        gsi: sdt.read(offset + 4),
+                        polarity,
+                        trigger,
+                    });
+                }
+                ENTRY_LOCAL_APIC_NMI => {
+                    let (polarity, trigger) = inti_flags(sdt.read(offset + 3));
+                    madt.local_apic_nmis.push(LocalApicNmi {
+                        processor_uid: sdt.read(offset + 2),
+                        polarity,
+                        trigger,
+                        lint: sdt.read(offset + 5),
+                    });
+                }
+                ENTRY_LOCAL_APIC_ADDRESS_OVERRIDE => {
+                    madt.local_apic_address = sdt.read(offset + 4);
+                }
+                ENTRY_LOCAL_X2APIC => {
+                    let flags: u32 = sdt.read(offset + 8);
+                    madt.processors.push(Processor {
+                        processor_uid: sdt.read(offset + 12),
+                        apic_id: sdt.read(offset + 4),
+                        enabled: flags & 1 != 0,
+                        online_capable: flags & 2 != 0,
+                    });
+                }
+                _ => {}
+            }
+
+            offset += length;
+        }
+
+        madt
+    }
+
+    /// Whether the system also has 8259 PICs that need to be masked
+    pub fn has_legacy_pics(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    /// Finds the override for an ISA `irq`, if the firmware reported one
+    pub fn isa_override(&self, irq: u8) -> Option<&InterruptSourceOverride> {
+        self.overrides
+            .iter()
+            .find(|entry| entry.bus == 0 && entry.irq == irq)
+    }
+
+    /// The global system interrupt an ISA `irq` is connected to
+    pub fn isa_irq_to_gsi(&self, irq: u8) -> u32 {
+        self.isa_override(irq)
+            .map(|entry| entry.gsi)
+            .unwrap_or(irq as u32)
+    }
+}
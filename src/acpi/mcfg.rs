@@ -0,0 +1,50 @@
+use alloc::vec::Vec;
+
+use super::sdt::Sdt;
+
+const ENTRIES_OFFSET: usize = 44;
+const ENTRY_SIZE: usize = 16;
+
+/// An enhanced configuration space (ECAM) window for a range of PCI buses
+#[derive(Debug, Clone, Copy)]
+pub struct McfgEntry {
+    pub base_address: u64,
+    pub segment_group: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+impl McfgEntry {
+    /// Physical address of the configuration space of a PCI function
+    pub fn config_address(&self, bus: u8, device: u8, function: u8) -> Option<u64> {
+        if bus < self.start_bus || bus > self.end_bus || device >= 32 || function >= 8 {
+            return None;
+        }
+        let offset =
+            ((bus - self.start_bus) as u64) << 20 | (device as u64) << 15 | (function as u64) << 12;
+        Some(self.base_address + offset)
+    }
+}
+
+/// The PCI Express memory mapped configuration table (signature "MCFG")
+#[derive(Debug)]
+pub struct Mcfg {
+    pub entries: Vec<McfgEntry>,
+}
+
+impl Mcfg {
+    pub fn parse(sdt: &Sdt) -> Self {
+        let mut entries = Vec::new();
+        let mut offset = ENTRIES_OFFSET;
+        while offset + ENTRY_SIZE <= sdt.length() {
+            entries.push(McfgEntry {
+                base_address: sdt.read(offset),
+                segment_group: sdt.read(offset + 8),
+                start_bus: sdt.read(offset + 10),
+                end_bus: sdt.read(offset + 11),
+            });
+            offset += ENTRY_SIZE;
+        }
+        Mcfg { entries }
+    }
+}
@@ -0,0 +1,174 @@
+pub mod fadt;
+pub mod hpet;
+pub mod madt;
+pub mod mcfg;
+pub mod sdt;
+
+use fadt::Fadt;
+use hpet::Hpet;
+use madt::Madt;
+use mcfg::Mcfg;
+use multiboot2::BootInformation;
+use sdt::{Sdt, HEADER_SIZE};
+use spin::Once;
+
+use crate::{
+    memory::frame::{Frame, FrameAllocator},
+    paging::{entry::EntryFlags, mapper::Mapper, page::Page},
+};
+
+#[derive(Debug)]
+pub enum AcpiError {
+    NoRsdp,
+    InvalidRsdp,
+    InvalidTable([u8; 4]),
+    InvalidChecksum([u8; 4]),
+    UnexpectedSignature([u8; 4]),
+}
+
+/// ACPI Generic Address Structure, describes a register in memory or I/O space
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct GenericAddress {
+    pub address_space: u8,
+    pub bit_width: u8,
+    pub bit_offset: u8,
+    pub access_size: u8,
+    pub address: u64,
+}
+
+impl GenericAddress {
+    pub const SYSTEM_MEMORY: u8 = 0;
+    pub const SYSTEM_IO: u8 = 1;
+    pub const PCI_CONFIG: u8 = 2;
+}
+
+pub struct AcpiTables {
+    pub revision: u8,
+    pub madt: Option<Madt>,
+    pub fadt: Option<Fadt>,
+    pub hpet: Option<Hpet>,
+    pub mcfg: Option<Mcfg>,
+    pub dsdt: Option<Sdt>,
+}
+
+static TABLES: Once<AcpiTables> = Once::new();
+
+/// The parsed ACPI tables, or None if `init` failed or has not been called yet
+pub fn tables() -> Option<&'static AcpiTables> {
+    TABLES.get()
+}
+
+// Identity maps the physical region read only, skipping pages that are already mapped
+pub(crate) fn map_region(
+    address: usize,
+    size: usize,
+    mapper: &mut Mapper,
+    allocator: &mut impl FrameAllocator,
+) {
+    if size == 0 {
+        return;
+    }
+    let start = Page::containing_address(address);
+    let end = Page::containing_address(address + size - 1);
+    for page in Page::range_inclusive(start, end) {
+        if mapper.translate_page(page).is_some() {
+            continue;
+        }
+        let frame = Frame::containing_address(page.start_address() as u64);
+        mapper.identity_map(frame, EntryFlags::NO_EXECUTE, allocator);
+    }
+}
+
+pub fn init(
+    boot_info: &BootInformation,
+    mapper: &mut Mapper,
+    allocator: &mut impl FrameAllocator,
+) -> Result<(), AcpiError> {
+    // Prefer the XSDT from the ACPI 2.0+ RSDP, it holds 64 bit table pointers
+    let (revision, root_address, entry_size) = if let Some(rsdp) = boot_info.rsdp_v2_tag() {
+        if !rsdp.checksum_is_valid() {
+            return Err(AcpiError::InvalidRsdp);
+        }
+        (rsdp.revision(), rsdp.xsdt_address(), 8)
+    } else if let Some(rsdp) = boot_info.rsdp_v1_tag() {
+        if !rsdp.checksum_is_valid() {
+            return Err(AcpiError::InvalidRsdp);
+        }
+        (rsdp.revision(), rsdp.rsdt_address(), 4)
+    } else {
+        return Err(AcpiError::NoRsdp);
+    };
+
+    let root = sdt::load(root_address, mapper, allocator)?;
+    let expected = if entry_size == 8 { b"XSDT" } else { b"RSDT" };
+    if root.signature() != *expected {
+        return Err(AcpiError::UnexpectedSignature(root.signature()));
+    }
+
+    let mut tables = AcpiTables {
+        revision,
+        madt: None,
+        fadt: None,
+        hpet: None,
+        mcfg: None,
+        dsdt: None,
+    };
+
+    let entries = (root.length() - HEADER_SIZE) / entry_size;
+    for index in 0..entries {
+        let offset = HEADER_SIZE + index * entry_size;
+        let address = if entry_size == 8 {
+            root.read::<u64>(offset) as usize
+        } else {
+            root.read::<u32>(offset) as usize
+        };
+
+        let table = match sdt::load(address, mapper, allocator) {
+            Ok(table) => table,
+            Err(error) => {
+                log::warn!("skipping table at {:#x}: {:?}", address, error);
+                continue;
+            }
+        };
+
+        match &table.signature() {
+            b"APIC" => tables.madt = Some(Madt::parse(&table)),
+            b"FACP" => tables.fadt = Some(Fadt::parse(&table)),
+            b"HPET" => tables.hpet = Some(Hpet::parse(&table)),
+            b"MCFG" => tables.mcfg = Some(Mcfg::parse(&table)),
+            _ => {}
+        }
+    }
+
+    // The DSDT is not listed in the root table, only referenced by the FADT
+    if let Some(fadt) = &tables.fadt {
+        if fadt.dsdt != 0 {
+            // A broken DSDT only loses what needs it, the other tables are still usable
+            let dsdt = sdt::load(fadt.dsdt as usize, mapper, allocator).and_then(|dsdt| {
+                let signature = dsdt.signature();
+                if signature == *b"DSDT" {
+                    Ok(dsdt)
+                } else {
+                    Err(AcpiError::UnexpectedSignature(signature))
+                }
+            });
+            match dsdt {
+                Ok(dsdt) => tables.dsdt = Some(dsdt),
+                Err(error) => log::warn!("skipping the DSDT at {:#x}: {:?}", fadt.dsdt, error),
+            }
+        }
+    }
+
+    if let Some(madt) = &tables.madt {
+        log::info!(
+            "revision {}, {} processors, {} I/O APICs",
+            revision,
+            madt.processors.len(),
+            madt.io_apics.len()
+        );
+    }
+
+    TABLES.call_once(|| tables);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use core::mem::size_of;
+
+use super::{map_region, AcpiError};
+use crate::{memory::frame::FrameAllocator, paging::mapper::Mapper};
+
+pub const HEADER_SIZE: usize = size_of::<SdtHeader>();
+
+/// The header shared by every ACPI system description table
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: u32,
+    pub creator_revision: u32,
+}
+
+/// A mapped and checksum validated ACPI table
+#[derive(Debug, Clone, Copy)]
+pub struct Sdt {
+    address: usize,
+    header: SdtHeader,
+}
+
+impl Sdt {
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    pub fn header(&self) -> SdtHeader {
+        self.header
+    }
+
+    pub fn signature(&self) -> [u8; 4] {
+        self.header.signature
+    }
+
+    pub fn revision(&self) -> u8 {
+        self.header.revision
+    }
+
+    pub fn length(&self) -> usize {
+        self.header.length as usize
+    }
+
+    /// The whole table, including the header
+    pub fn bytes(&self) -> &'static [u8] {
+        unsafe { core::slice::from_raw_parts(self.address as *const u8, self.length()) }
+    }
+
+    /// The table contents following the header
+    pub fn data(&self) -> &'static [u8] {
+        &self.bytes()[HEADER_SIZE..]
+    }
+
+    /// Reads a `T` at `offset` bytes from the start of the table, if the table is long enough
+    pub fn get<T: Copy>(&self, offset: usize) -> Option<T> {
+        if offset + size_of::<T>() > self.length() {
+            return None;
+        }
+        Some(unsafe { core::ptr::read_unaligned((self.address + offset) as *const T) })
+    }
+
+    pub fn read<T: Copy>(&self, offset: usize) -> T {
+        self.get(offset)
+            .expect("read past the end of an ACPI table")
+    }
+}
+
+pub fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Maps the table at the physical `address` and validates its checksum
+pub fn load(
+    address: usize,
+    mapper: &mut Mapper,
+    allocator: &mut impl FrameAllocator,
+) -> Result<Sdt, AcpiError> {
+    // Map the header first to find out how long the table is
+    map_region(address, HEADER_SIZE, mapper, allocator);
+    let header = unsafe { core::ptr::read_unaligned(address as *const SdtHeader) };
+
+    let length = header.length as usize;
+    if length < HEADER_SIZE {
+        return Err(AcpiError::InvalidTable(header.signature));
+    }
+    map_region(address, length, mapper, allocator);
+
+    let sdt = Sdt { address, header };
+    if checksum(sdt.bytes()) != 0 {
+        return Err(AcpiError::InvalidChecksum(header.signature));
+    }
+    Ok(sdt)
+}
@@ -10,6 +10,9 @@ long_mode_start:
   mov fs, ax
   mov gs, ax
 
+  ; a null frame pointer ends the chain walked by backtraces
+  xor rbp, rbp
+
   extern rust_main
   call rust_main
   hlt
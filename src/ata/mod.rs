@@ -0,0 +1,375 @@
+//! Disks on the legacy IDE ports, read and written a sector at a time with PIO.
+//! Slow, but there with QEMU's default `-machine pc`. On q35 the disks sit
+//! behind an AHCI controller instead, which isn't supported yet.
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use kernel_core::block::{self, BlockDevice, BLOCK_SIZE};
+use spin::{Mutex, Once};
+use x86_64::instructions::port::Port;
+
+use crate::println;
+
+// Registers, from the I/O base of a bus
+const DATA: u16 = 0;
+const ERROR: u16 = 1;
+const SECTOR_COUNT: u16 = 2;
+const LBA_LOW: u16 = 3;
+const LBA_MID: u16 = 4;
+const LBA_HIGH: u16 = 5;
+const DRIVE: u16 = 6;
+// Reads give the status register, writes send commands
+const COMMAND: u16 = 7;
+
+// Status register bits
+const ERR: u8 = 1 << 0;
+const DRQ: u8 = 1 << 3;
+const DF: u8 = 1 << 5;
+const BSY: u8 = 1 << 7;
+
+// Device control register bit, completion is polled instead
+const NO_INTERRUPTS: u8 = 1 << 1;
+
+const READ_SECTORS: u8 = 0x20;
+const READ_SECTORS_EXT: u8 = 0x24;
+const WRITE_SECTORS: u8 = 0x30;
+const WRITE_SECTORS_EXT: u8 = 0x34;
+const FLUSH_CACHE: u8 = 0xE7;
+const FLUSH_CACHE_EXT: u8 = 0xEA;
+const IDENTIFY: u8 = 0xEC;
+
+// How often the status register is polled before giving up
+const TIMEOUT: usize = 1_000_000;
+
+// Sectors per command, the count register holds a byte with LBA28
+const MAX_SECTORS: usize = 255;
+
+// LBA28 reaches the first 128 GiB, LBA48 is needed past it
+const LBA28_LIMIT: u64 = 1 << 28;
+
+#[derive(Debug)]
+pub enum AtaError {
+    Timeout,
+    /// The drive set ERR or DF, with the error register
+    Device(u8),
+}
+
+// A channel of the IDE controller, whose master and slave drive share the
+// registers
+struct Bus {
+    io: u16,
+    control: u16,
+}
+
+static BUSES: [Mutex<Bus>; 2] = [
+    Mutex::new(Bus {
+        io: 0x1F0,
+        control: 0x3F6,
+    }),
+    Mutex::new(Bus {
+        io: 0x170,
+        control: 0x376,
+    }),
+];
+
+impl Bus {
+    fn register(&self, register: u16) -> Port<u8> {
+        Port::new(self.io + register)
+    }
+
+    fn status(&self) -> u8 {
+        unsafe { self.register(COMMAND).read() }
+    }
+
+    // Reading the alternate status four times gives the drive the 400ns it
+    // needs to update the status after a command or a drive select
+    fn delay(&self) {
+        let mut alternate_status: Port<u8> = Port::new(self.control);
+        for _ in 0..4 {
+            unsafe { alternate_status.read() };
+        }
+    }
+
+    // Polls until the drive isn't busy and every bit of `ready` is set
+    fn wait(&self, ready: u8) -> Result<(), AtaError> {
+        for _ in 0..TIMEOUT {
+            let status = self.status();
+            if status & BSY == 0 {
+                if status & (ERR | DF) != 0 {
+                    return Err(AtaError::Device(unsafe { self.register(ERROR).read() }));
+                }
+                if status & ready == ready {
+                    return Ok(());
+                }
+            }
+            core::hint::spin_loop();
+        }
+        Err(AtaError::Timeout)
+    }
+
+    // Selects the drive and sets up a transfer of `count` sectors from `lba`
+    fn select(&self, slave: bool, lba: u64, count: u16, lba48: bool) {
+        let slave = (slave as u8) << 4;
+        unsafe {
+            if lba48 {
+                self.register(DRIVE).write(0x40 | slave);
+                self.delay();
+                // The high bytes go first, through the same registers
+                self.register(SECTOR_COUNT).write((count >> 8) as u8);
+                self.register(LBA_LOW).write((lba >> 24) as u8);
+                self.register(LBA_MID).write((lba >> 32) as u8);
+                self.register(LBA_HIGH).write((lba >> 40) as u8);
+            } else {
+                self.register(DRIVE)
+                    .write(0xE0 | slave | (lba >> 24) as u8 & 0x0F);
+                self.delay();
+            }
+            self.register(SECTOR_COUNT).write(count as u8);
+            self.register(LBA_LOW).write(lba as u8);
+            self.register(LBA_MID).write((lba >> 8) as u8);
+            self.register(LBA_HIGH).write((lba >> 16) as u8);
+        }
+    }
+
+    fn command(&self, command: u8) {
+        unsafe { self.register(COMMAND).write(command) };
+        self.delay();
+    }
+
+    fn read_data(&self, buf: &mut [u8]) {
+        let mut data: Port<u16> = Port::new(self.io + DATA);
+        for word in buf.chunks_exact_mut(2) {
+            word.copy_from_slice(&unsafe { data.read() }.to_le_bytes());
+        }
+    }
+
+    fn write_data(&self, buf: &[u8]) {
+        let mut data: Port<u16> = Port::new(self.io + DATA);
+        for word in buf.chunks_exact(2) {
+            unsafe { data.write(u16::from_le_bytes([word[0], word[1]])) };
+        }
+    }
+}
+
+/// An ATA hard disk
+pub struct Disk {
+    /// 0 and 1 on the primary bus, 2 and 3 on the secondary one
+    pub index: usize,
+    pub model: String,
+    sectors: u64,
+    bus: &'static Mutex<Bus>,
+    slave: bool,
+}
+
+impl Disk {
+    /// `ata0` to `ata3`
+    pub fn name(&self) -> String {
+        format!("ata{}", self.index)
+    }
+
+    fn read(&self, bus: &Bus, lba: u64, buf: &mut [u8]) -> Result<(), AtaError> {
+        let count = buf.len() / BLOCK_SIZE;
+        let lba48 = lba + count as u64 > LBA28_LIMIT;
+        bus.wait(0)?;
+        bus.select(self.slave, lba, count as u16, lba48);
+        bus.command(if lba48 {
+            READ_SECTORS_EXT
+        } else {
+            READ_SECTORS
+        });
+        for sector in buf.chunks_exact_mut(BLOCK_SIZE) {
+            bus.wait(DRQ)?;
+            bus.read_data(sector);
+            bus.delay();
+        }
+        Ok(())
+    }
+
+    fn write(&self, bus: &Bus, lba: u64, buf: &[u8]) -> Result<(), AtaError> {
+        let count = buf.len() / BLOCK_SIZE;
+        let lba48 = lba + count as u64 > LBA28_LIMIT;
+        bus.wait(0)?;
+        bus.select(self.slave, lba, count as u16, lba48);
+        bus.command(if lba48 {
+            WRITE_SECTORS_EXT
+        } else {
+            WRITE_SECTORS
+        });
+        for sector in buf.chunks_exact(BLOCK_SIZE) {
+            bus.wait(DRQ)?;
+            bus.write_data(sector);
+            bus.delay();
+        }
+        // Out of the drive's write cache, so nothing is lost on power off
+        bus.wait(0)?;
+        bus.command(if lba48 { FLUSH_CACHE_EXT } else { FLUSH_CACHE });
+        bus.wait(0)
+    }
+}
+
+impl BlockDevice for Disk {
+    fn block_count(&self) -> u64 {
+        self.sectors
+    }
+
+    fn read_blocks(&self, block: u64, buf: &mut [u8]) -> Result<(), block::Error> {
+        if block + (buf.len() / BLOCK_SIZE) as u64 > self.sectors {
+            return Err(block::Error::OutOfRange);
+        }
+        let bus = self.bus.lock();
+        for (index, chunk) in buf.chunks_mut(MAX_SECTORS * BLOCK_SIZE).enumerate() {
+            let lba = block + (index * MAX_SECTORS) as u64;
+            self.read(&bus, lba, chunk).map_err(|error| {
+                log::warn!(
+                    "{}: reading sector {} failed: {:?}",
+                    self.name(),
+                    lba,
+                    error
+                );
+                block::Error::Io
+            })?;
+        }
+        Ok(())
+    }
+
+    fn write_blocks(&self, block: u64, buf: &[u8]) -> Result<(), block::Error> {
+        if block + (buf.len() / BLOCK_SIZE) as u64 > self.sectors {
+            return Err(block::Error::OutOfRange);
+        }
+        let bus = self.bus.lock();
+        for (index, chunk) in buf.chunks(MAX_SECTORS * BLOCK_SIZE).enumerate() {
+            let lba = block + (index * MAX_SECTORS) as u64;
+            self.write(&bus, lba, chunk).map_err(|error| {
+                log::warn!(
+                    "{}: writing sector {} failed: {:?}",
+                    self.name(),
+                    lba,
+                    error
+                );
+                block::Error::Io
+            })?;
+        }
+        Ok(())
+    }
+}
+
+// Asks the drive what it is, `None` for an empty slot or a drive that isn't a
+// hard disk, like the CD-ROM on the secondary master
+fn identify(bus: &'static Mutex<Bus>, index: usize) -> Option<Disk> {
+    let slave = index % 2 == 1;
+    let locked = bus.lock();
+    unsafe { Port::new(locked.control).write(NO_INTERRUPTS) };
+    // A bus without drives floats high
+    if locked.status() == 0xFF {
+        return None;
+    }
+    locked.select(slave, 0, 0, false);
+    unsafe { locked.register(DRIVE).write(0xA0 | (slave as u8) << 4) };
+    locked.delay();
+    locked.command(IDENTIFY);
+    if locked.status() == 0 {
+        return None;
+    }
+    // ATAPI and SATA devices abort, leaving their signature in the LBA registers
+    let _ = locked.wait(0);
+    let signature = unsafe {
+        (
+            locked.register(LBA_MID).read(),
+            locked.register(LBA_HIGH).read(),
+        )
+    };
+    if signature != (0, 0) {
+        return None;
+    }
+    locked.wait(DRQ).ok()?;
+    let mut identity = [0; BLOCK_SIZE];
+    locked.read_data(&mut identity);
+
+    let word = |index: usize| u16::from_le_bytes([identity[index * 2], identity[index * 2 + 1]]);
+    let lba28_sectors = word(60) as u64 | (word(61) as u64) << 16;
+    let lba48_sectors = (100..104)
+        .rev()
+        .fold(0, |sectors, index| sectors << 16 | word(index) as u64);
+    let sectors = match word(83) & 1 << 10 {
+        0 => lba28_sectors,
+        _ => lba48_sectors,
+    };
+    // Strings are stored as big endian words, padded with spaces
+    let model = (27..47)
+        .flat_map(|index| word(index).to_be_bytes())
+        .map(char::from)
+        .collect::<String>();
+    drop(locked);
+
+    Some(Disk {
+        index,
+        model: String::from(model.trim()),
+        sectors,
+        bus,
+        slave,
+    })
+}
+
+static DISKS: Once<Vec<Arc<Disk>>> = Once::new();
+
+/// Looks for hard disks on the four IDE slots
+pub fn init() {
+    DISKS.call_once(|| {
+        let disks: Vec<Arc<Disk>> = (0..4)
+            .filter_map(|index| identify(&BUSES[index / 2], index))
+            .filter(|disk| disk.sectors > 0)
+            .map(Arc::new)
+            .collect();
+        for disk in &disks {
+            log::info!(
+                "{}: {}, {} MiB",
+                disk.name(),
+                disk.model,
+                disk.sectors * BLOCK_SIZE as u64 / (1024 * 1024)
+            );
+        }
+        disks
+    });
+}
+
+/// The disks found by `init`
+pub fn disks() -> &'static [Arc<Disk>] {
+    match DISKS.get() {
+        Some(disks) => disks,
+        None => &[],
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static ATA: crate::shell::Command = crate::shell::Command {
+    name: "ata",
+    help: "list the ATA disks",
+    run: ata,
+};
+
+fn ata(_args: &[&str]) {
+    if disks().is_empty() {
+        println!("no disks");
+    }
+    for disk in disks() {
+        println!(
+            "{}: {:>10} sectors  {}",
+            disk.name(),
+            disk.sectors,
+            disk.model
+        );
+    }
+}
+
+crate::test_cases! {
+    fn disk_has_a_boot_sector() {
+        let disk = disks().first().expect("no disk, is it attached to the primary bus?");
+        let mut sector = [0; BLOCK_SIZE];
+        disk.read_blocks(0, &mut sector).unwrap();
+        assert_eq!(sector[510..], [0x55, 0xAA]);
+        assert_eq!(
+            disk.read_blocks(disk.block_count(), &mut sector),
+            Err(block::Error::OutOfRange)
+        );
+    }
+}
@@ -0,0 +1,118 @@
+pub mod symbols;
+
+use core::{arch::asm, fmt};
+
+/// Deeper frames are left out
+const MAX_FRAMES: usize = 32;
+
+/// The return addresses on the call stack, found by following the chain of
+/// saved frame pointers. The target spec makes rustc keep frame pointers in
+/// every function, and `long_mode_start` ends the chain with a null rbp.
+pub struct Backtrace {
+    addresses: [u64; MAX_FRAMES],
+    len: usize,
+    // Whether the first address is the faulting instruction rather than a
+    // return address
+    starts_at_instruction: bool,
+}
+
+impl Backtrace {
+    /// The call stack of the caller
+    #[inline(never)]
+    pub fn capture() -> Self {
+        Self::walk(frame_pointer(), 0, None)
+    }
+
+    /// The call stack of the code interrupted by an exception, starting at the
+    /// instruction that caused it. Must be called by the exception handler.
+    #[inline(never)]
+    pub fn from_exception(instruction_pointer: u64) -> Self {
+        // Skips the handler and its `handler!` wrapper, which doesn't touch rbp
+        Self::walk(frame_pointer(), 2, Some(instruction_pointer))
+    }
+
+    fn walk(mut frame_pointer: u64, mut skip: usize, instruction_pointer: Option<u64>) -> Self {
+        let mut backtrace = Self {
+            addresses: [0; MAX_FRAMES],
+            len: 0,
+            starts_at_instruction: instruction_pointer.is_some(),
+        };
+        if let Some(instruction_pointer) = instruction_pointer {
+            backtrace.push(instruction_pointer);
+        }
+
+        // Each frame starts with the caller's frame pointer, followed by the
+        // return address
+        while frame_pointer != 0 && frame_pointer % 8 == 0 && backtrace.len < MAX_FRAMES {
+            let frame = frame_pointer as *const u64;
+            let (previous, return_address) = unsafe { (*frame, *frame.add(1)) };
+            if skip > 0 {
+                skip -= 1;
+            } else {
+                backtrace.push(return_address);
+            }
+            // The stack grows down, anything else means the chain is corrupted
+            if previous <= frame_pointer {
+                break;
+            }
+            frame_pointer = previous;
+        }
+        backtrace
+    }
+
+    fn push(&mut self, address: u64) {
+        if self.len < MAX_FRAMES {
+            self.addresses[self.len] = address;
+            self.len += 1;
+        }
+    }
+
+    pub fn addresses(&self) -> &[u64] {
+        &self.addresses[..self.len]
+    }
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "backtrace:")?;
+        for (index, &address) in self.addresses().iter().enumerate() {
+            write!(f, "{:>4}: {:#018x}", index, address)?;
+            // A return address points after the call, which may be the start of
+            // the next function
+            let call = if index == 0 && self.starts_at_instruction {
+                address
+            } else {
+                address.saturating_sub(1)
+            };
+            match symbols::lookup(call) {
+                Some((name, offset)) => writeln!(
+                    f,
+                    " - {:#}+{:#x}",
+                    rustc_demangle::demangle(name),
+                    offset + (address - call)
+                )?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[inline(always)]
+fn frame_pointer() -> u64 {
+    let rbp: u64;
+    unsafe { asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags)) };
+    rbp
+}
+
+crate::test_cases! {
+    fn backtrace_names_functions() {
+        use alloc::format;
+
+        let backtrace = Backtrace::capture();
+        let caller = *backtrace.addresses().first().expect("empty backtrace");
+        let (name, _) = symbols::lookup(caller - 1).expect("no symbol for the caller");
+        let name = format!("{:#}", rustc_demangle::demangle(name));
+        assert!(name.contains("backtrace_names_functions"), "{}", name);
+    }
+}
@@ -0,0 +1,90 @@
+use core::{
+    mem::{align_of, size_of},
+    slice, str,
+};
+
+use multiboot2::BootInformation;
+use spin::Once;
+
+// Symbol type in the low nibble of `info`
+const TYPE_MASK: u8 = 0xf;
+const TYPE_FUNCTION: u8 = 2;
+
+/// An entry of the ELF64 symbol table
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Symbol {
+    name: u32,
+    info: u8,
+    other: u8,
+    section_index: u16,
+    value: u64,
+    size: u64,
+}
+
+struct SymbolTable {
+    symbols: &'static [Symbol],
+    strings: &'static [u8],
+}
+
+impl SymbolTable {
+    fn name(&self, symbol: &Symbol) -> Option<&'static str> {
+        let strings: &'static [u8] = self.strings;
+        let name = strings.get(symbol.name as usize..)?;
+        let end = name.iter().position(|&byte| byte == 0)?;
+        str::from_utf8(&name[..end]).ok()
+    }
+}
+
+static SYMBOLS: Once<SymbolTable> = Once::new();
+
+/// Finds the kernel's symbol table among the ELF sections GRUB loaded. They
+/// aren't allocated sections, `paging::remap_kernel` maps them separately.
+pub fn init(boot_info: &BootInformation) {
+    let Some(sections) = boot_info.elf_sections() else {
+        log::warn!("no ELF sections, backtraces won't show function names");
+        return;
+    };
+
+    let (mut symbols, mut strings) = (None, None);
+    for section in sections {
+        let range = (section.start_address() as usize, section.size() as usize);
+        match section.name() {
+            Ok(".symtab") => symbols = Some(range),
+            Ok(".strtab") => strings = Some(range),
+            _ => {}
+        }
+    }
+
+    let (Some((symbols, symbols_size)), Some((strings, strings_size))) = (symbols, strings) else {
+        log::warn!("no symbol table, backtraces won't show function names");
+        return;
+    };
+    if symbols == 0 || strings == 0 || symbols % align_of::<Symbol>() != 0 {
+        log::warn!("symbol table at {:#x} wasn't loaded properly", symbols);
+        return;
+    }
+
+    let table = unsafe {
+        SymbolTable {
+            symbols: slice::from_raw_parts(
+                symbols as *const Symbol,
+                symbols_size / size_of::<Symbol>(),
+            ),
+            strings: slice::from_raw_parts(strings as *const u8, strings_size),
+        }
+    };
+    log::debug!("{} symbols at {:#x}", table.symbols.len(), symbols);
+    SYMBOLS.call_once(|| table);
+}
+
+/// The mangled name of the function containing `address`, and the offset of
+/// `address` into it
+pub fn lookup(address: u64) -> Option<(&'static str, u64)> {
+    let table = SYMBOLS.get()?;
+    let symbol = table.symbols.iter().find(|symbol| {
+        symbol.info & TYPE_MASK == TYPE_FUNCTION
+            && (symbol.value..symbol.value + symbol.size).contains(&address)
+    })?;
+    Some((table.name(symbol)?, address - symbol.value))
+}
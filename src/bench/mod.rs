@@ -0,0 +1,197 @@
+use core::{fmt, hint::black_box, time::Duration};
+
+use x86_64::instructions::interrupts;
+
+use crate::{
+    clock::{self, tsc},
+    cmdline,
+    panic::{exit_qemu, QemuExitCode},
+    println, serial_println,
+};
+
+#[linkme::distributed_slice]
+pub static BENCHES: [Bench];
+
+/// A benchmark registered by `bench_cases!`
+pub struct Bench {
+    pub name: &'static str,
+    pub module: &'static str,
+    pub run: fn(&mut Bencher),
+}
+
+// How long one sample should take, the number of iterations is picked to match
+const SAMPLE_TIME: Duration = Duration::from_millis(1);
+// Used if the TSC frequency is unknown
+const DEFAULT_SAMPLE_CYCLES: u64 = 1_000_000;
+const SAMPLES: u64 = 32;
+// For routines the compiler managed to optimize away anyway
+const MAX_ITERATIONS: u64 = 1 << 24;
+
+/// Cycles per iteration, over all samples
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub iterations: u64,
+    pub min: u64,
+    pub mean: u64,
+    pub max: u64,
+}
+
+/// Times the routine passed to `iter`
+pub struct Bencher {
+    stats: Option<Stats>,
+}
+
+impl Bencher {
+    /// Runs `routine` in batches that take about `SAMPLE_TIME` each and
+    /// records the cycles per iteration. Anything before the call isn't timed.
+    pub fn iter<T>(&mut self, mut routine: impl FnMut() -> T) {
+        let target = match clock::tsc_frequency() {
+            Some(frequency) => frequency * SAMPLE_TIME.as_micros() as u64 / 1_000_000,
+            None => DEFAULT_SAMPLE_CYCLES,
+        };
+
+        // Doubles the batch until it takes long enough, which also warms up the caches
+        let mut iterations = 1;
+        while iterations < MAX_ITERATIONS && measure(&mut routine, iterations) < target {
+            iterations *= 2;
+        }
+
+        let (mut min, mut max, mut total) = (u64::MAX, 0, 0);
+        for _ in 0..SAMPLES {
+            let cycles = measure(&mut routine, iterations) / iterations;
+            min = min.min(cycles);
+            max = max.max(cycles);
+            total += cycles;
+        }
+        self.stats = Some(Stats {
+            iterations,
+            min,
+            mean: total / SAMPLES,
+            max,
+        });
+    }
+}
+
+// Cycles it takes to run `routine` `iterations` times
+fn measure<T>(routine: &mut impl FnMut() -> T, iterations: u64) -> u64 {
+    let start = tsc::read_fenced();
+    for _ in 0..iterations {
+        black_box(routine());
+    }
+    tsc::read_fenced().wrapping_sub(start)
+}
+
+/// The outcome of a benchmark, printed as one line of `key=value` pairs
+pub struct BenchResult {
+    pub bench: &'static Bench,
+    /// `None` if the benchmark didn't call `iter`, e.g. for lack of a framebuffer
+    pub stats: Option<Stats>,
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bench {}::{}", self.bench.module, self.bench.name)?;
+        match self.stats {
+            Some(stats) => write!(
+                f,
+                " iterations={} min={} mean={} max={}",
+                stats.iterations, stats.min, stats.mean, stats.max
+            ),
+            None => write!(f, " skipped"),
+        }
+    }
+}
+
+impl Bench {
+    /// Runs the benchmark with interrupts disabled, so they don't end up in the timings
+    pub fn run(&'static self) -> BenchResult {
+        let mut bencher = Bencher { stats: None };
+        interrupts::without_interrupts(|| (self.run)(&mut bencher));
+        BenchResult {
+            bench: self,
+            stats: bencher.stats,
+        }
+    }
+}
+
+/// Runs the benchmarks whose path contains one of the comma separated
+/// patterns in `filter`, or all of them, and prints a header and one line per
+/// benchmark with `print`
+pub fn run(filter: Option<&str>, mut print: impl FnMut(fmt::Arguments)) {
+    print(format_args!(
+        "# cycles per iteration, tsc_hz={} samples={}",
+        clock::tsc_frequency().unwrap_or(0),
+        SAMPLES
+    ));
+    for bench in BENCHES.iter() {
+        let path = format!("{}::{}", bench.module, bench.name);
+        if filter.is_some_and(|filter| !filter.split(',').any(|pattern| path.contains(pattern))) {
+            continue;
+        }
+        print(format_args!("{}", bench.run()));
+    }
+}
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static BENCH_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "bench",
+    kind: crate::cmdline::Kind::Flag,
+    help: "run the benchmarks instead of the tests, in test builds",
+};
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static FILTER_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "bench.filter",
+    kind: crate::cmdline::Kind::Text,
+    help: "only run the benchmarks whose path contains one of these comma separated patterns",
+};
+
+/// Runs the benchmarks instead of the tests, if the command line contains
+/// `bench`, prints the results to the serial port and exits QEMU.
+/// `bench.filter=<patterns>` picks the benchmarks like `test.filter` does the tests.
+pub fn bench_runner() {
+    run(cmdline::get("bench.filter"), |line| {
+        serial_println!("{}", line);
+    });
+    exit_qemu(QemuExitCode::Success);
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static BENCH: crate::shell::Command = crate::shell::Command {
+    name: "bench",
+    help: "run the benchmarks: bench [pattern,...]",
+    run: bench,
+};
+
+fn bench(args: &[&str]) {
+    match args {
+        [] => run(None, |line| println!("{}", line)),
+        [filter] => run(Some(filter), |line| println!("{}", line)),
+        _ => println!("usage: bench [pattern,...]"),
+    }
+}
+
+#[macro_export]
+macro_rules! bench_cases {
+    () => {};
+
+    // Like `test_cases!`, each function gets a `Bencher` and passes the code
+    // to time to `Bencher::iter`
+    (
+        fn $bench_name:ident($bencher:ident: &mut Bencher) $body:block
+        $($rest:tt)*
+    ) => {
+        crate::bench_cases! { $($rest)* }
+
+        #[linkme::distributed_slice(crate::bench::BENCHES)]
+        #[allow(non_upper_case_globals)]
+        static $bench_name: crate::bench::Bench = crate::bench::Bench {
+            name: stringify!($bench_name),
+            module: module_path!(),
+            run: {
+                fn run($bencher: &mut crate::bench::Bencher) $body
+                run
+            },
+        };
+    };
+}
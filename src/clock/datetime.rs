@@ -0,0 +1,124 @@
+use core::fmt;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// A calendar date and time in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+// Days since 1970-01-01 for a date in the proleptic Gregorian calendar
+// (see http://howardhinnant.github.io/date_algorithms.html)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The inverse of days_from_civil, returns (year, month, day)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl DateTime {
+    pub fn from_unix_timestamp(seconds: u64, nanosecond: u32) -> Self {
+        let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+        let seconds_of_day = seconds % SECONDS_PER_DAY;
+        DateTime {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day / 60 % 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+            nanosecond,
+        }
+    }
+
+    /// Seconds since 1970-01-01 00:00:00 UTC, `None` for earlier or invalid
+    /// dates, like the garbage an RTC with a flat battery holds
+    pub fn unix_timestamp(&self) -> Option<u64> {
+        let date = (self.year as i64, self.month as i64, self.day as i64);
+        let days = days_from_civil(date.0, date.1, date.2);
+        // Out of range days and months end up on another date
+        if days < 0 || civil_from_days(days) != date {
+            return None;
+        }
+        if self.hour >= 24 || self.minute >= 60 || self.second >= 60 {
+            return None;
+        }
+        Some(
+            days as u64 * SECONDS_PER_DAY
+                + self.hour as u64 * 3600
+                + self.minute as u64 * 60
+                + self.second as u64,
+        )
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+crate::test_cases! {
+    fn unix_epoch() {
+        let epoch = DateTime::from_unix_timestamp(0, 0);
+        assert_eq!((epoch.year, epoch.month, epoch.day), (1970, 1, 1));
+        assert_eq!((epoch.hour, epoch.minute, epoch.second), (0, 0, 0));
+        assert_eq!(epoch.unix_timestamp(), Some(0));
+    }
+
+    fn unix_timestamp_round_trip() {
+        // 2000-02-29 is a leap day in a year divisible by 400
+        let leap_day = DateTime::from_unix_timestamp(951_782_400, 0);
+        assert_eq!((leap_day.year, leap_day.month, leap_day.day), (2000, 2, 29));
+
+        let date = DateTime::from_unix_timestamp(1_700_000_000, 0);
+        assert_eq!((date.year, date.month, date.day), (2023, 11, 14));
+        assert_eq!((date.hour, date.minute, date.second), (22, 13, 20));
+        assert_eq!(date.unix_timestamp(), Some(1_700_000_000));
+    }
+
+    fn invalid_dates_have_no_timestamp() {
+        let date = DateTime::from_unix_timestamp(1_700_000_000, 0);
+        // A century register reading 0
+        assert_eq!(DateTime { year: 19, ..date }.unix_timestamp(), None);
+        assert_eq!(DateTime { year: 1969, ..date }.unix_timestamp(), None);
+        assert_eq!(DateTime { month: 0, ..date }.unix_timestamp(), None);
+        assert_eq!(DateTime { month: 13, ..date }.unix_timestamp(), None);
+        assert_eq!(DateTime { day: 0, ..date }.unix_timestamp(), None);
+        assert_eq!(DateTime { month: 2, day: 30, ..date }.unix_timestamp(), None);
+        assert_eq!(DateTime { hour: 24, ..date }.unix_timestamp(), None);
+    }
+}
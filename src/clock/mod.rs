@@ -0,0 +1,122 @@
+pub mod datetime;
+pub mod instant;
+pub mod rtc;
+pub mod tsc;
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+pub use datetime::DateTime;
+pub use instant::Instant;
+
+use crate::{println, timer};
+
+/// TSC frequency in Hz, 0 until the TSC has been calibrated
+static TSC_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+/// TSC value at calibration time
+static TSC_START: AtomicU64 = AtomicU64::new(0);
+/// Monotonic time at calibration time, so the clock doesn't jump when switching sources
+static START_NANOS: AtomicU64 = AtomicU64::new(0);
+/// Nanoseconds per TSC cycle as a 32.32 fixed point number
+static NANOS_PER_CYCLE: AtomicU64 = AtomicU64::new(0);
+/// Unix time (in nanoseconds) at which the monotonic clock read zero
+static BOOT_UNIX_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Calibrates the TSC and reads the wall clock time from the RTC. Until this is
+/// called (or if the TSC isn't invariant) the clock falls back to counting timer ticks.
+pub fn init() {
+    init_monotonic();
+
+    let rtc_time = rtc::read();
+    let boot_nanos = rtc_time
+        .unix_timestamp()
+        .and_then(|seconds| seconds.checked_mul(1_000_000_000))
+        .and_then(|nanos| nanos.checked_sub(now().as_nanos()));
+    match boot_nanos {
+        Some(nanos) => {
+            BOOT_UNIX_NANOS.store(nanos, Ordering::Relaxed);
+            log::info!("wall clock time is {}", rtc_time);
+        }
+        // The wall clock then starts at 1970
+        None => log::warn!("the RTC holds an invalid time, {}", rtc_time),
+    }
+}
+
+fn init_monotonic() {
+    if !tsc::is_invariant() {
+        log::warn!("no invariant TSC, using the timer tick as clock source");
+        return;
+    }
+
+    let frequency = tsc::calibrate();
+    let nanos_per_cycle = ((1_000_000_000u128 << 32) / frequency as u128) as u64;
+
+    NANOS_PER_CYCLE.store(nanos_per_cycle, Ordering::Relaxed);
+    START_NANOS.store(now().as_nanos(), Ordering::Relaxed);
+    TSC_START.store(tsc::read(), Ordering::Relaxed);
+    TSC_FREQUENCY.store(frequency, Ordering::Release);
+
+    log::info!("TSC running at {} MHz", frequency / 1_000_000);
+}
+
+/// The calibrated TSC frequency in Hz
+pub fn tsc_frequency() -> Option<u64> {
+    match TSC_FREQUENCY.load(Ordering::Acquire) {
+        0 => None,
+        frequency => Some(frequency),
+    }
+}
+
+/// The current time of the monotonic clock
+pub fn now() -> Instant {
+    if tsc_frequency().is_none() {
+        return Instant::from_nanos(timer::uptime().as_nanos() as u64);
+    }
+
+    let cycles = tsc::read().wrapping_sub(TSC_START.load(Ordering::Relaxed));
+    let nanos = (cycles as u128 * NANOS_PER_CYCLE.load(Ordering::Relaxed) as u128) >> 32;
+    Instant::from_nanos(START_NANOS.load(Ordering::Relaxed) + nanos as u64)
+}
+
+/// Time since the Unix epoch, derived from the RTC at boot and the monotonic clock
+pub fn unix_time() -> Duration {
+    Duration::from_nanos(BOOT_UNIX_NANOS.load(Ordering::Relaxed) + now().as_nanos())
+}
+
+/// The current date and time in UTC
+pub fn utc_now() -> DateTime {
+    let time = unix_time();
+    DateTime::from_unix_timestamp(time.as_secs(), time.subsec_nanos())
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static DATE: crate::shell::Command = crate::shell::Command {
+    name: "date",
+    help: "print the current date and time (UTC)",
+    run: date,
+};
+
+fn date(_args: &[&str]) {
+    println!("{}", utc_now());
+}
+
+crate::test_cases! {
+    fn instant_arithmetic() {
+        let start = Instant::from_nanos(1_000);
+        let later = start + Duration::from_micros(2);
+        assert_eq!(later.as_nanos(), 3_000);
+        assert_eq!(later - start, Duration::from_nanos(2_000));
+        // Going backwards saturates instead of panicking
+        assert_eq!(start - later, Duration::ZERO);
+        assert_eq!(later - Duration::from_nanos(3_000), Instant::from_nanos(0));
+        assert_eq!(start.checked_sub(Duration::from_secs(1)), None);
+    }
+
+    fn clock_is_monotonic() {
+        let first = now();
+        let second = now();
+        assert!(second >= first);
+    }
+}
@@ -0,0 +1,115 @@
+use x86_64::instructions::{interrupts, port::Port};
+
+use super::datetime::DateTime;
+use crate::acpi;
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+const REGISTER_SECONDS: u8 = 0x00;
+const REGISTER_MINUTES: u8 = 0x02;
+const REGISTER_HOURS: u8 = 0x04;
+const REGISTER_DAY: u8 = 0x07;
+const REGISTER_MONTH: u8 = 0x08;
+const REGISTER_YEAR: u8 = 0x09;
+const REGISTER_STATUS_A: u8 = 0x0A;
+const REGISTER_STATUS_B: u8 = 0x0B;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+const HOUR_PM: u8 = 1 << 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RawTime {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+fn read_register(register: u8) -> u8 {
+    let mut address: Port<u8> = Port::new(CMOS_ADDRESS);
+    let mut data: Port<u8> = Port::new(CMOS_DATA);
+    unsafe {
+        address.write(register);
+        data.read()
+    }
+}
+
+fn update_in_progress() -> bool {
+    read_register(REGISTER_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0
+}
+
+fn read_raw(century_register: Option<u8>) -> RawTime {
+    while update_in_progress() {
+        core::hint::spin_loop();
+    }
+    RawTime {
+        second: read_register(REGISTER_SECONDS),
+        minute: read_register(REGISTER_MINUTES),
+        hour: read_register(REGISTER_HOURS),
+        day: read_register(REGISTER_DAY),
+        month: read_register(REGISTER_MONTH),
+        year: read_register(REGISTER_YEAR),
+        century: century_register.map(read_register).unwrap_or(0),
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+/// Reads the current date and time from the CMOS real time clock, which is
+/// assumed to run in UTC
+pub fn read() -> DateTime {
+    // The FADT tells us if (and where) the RTC keeps the century
+    let century_register = acpi::tables()
+        .and_then(|tables| tables.fadt.as_ref())
+        .map(|fadt| fadt.century)
+        .filter(|register| *register != 0);
+
+    let (raw, status_b) = interrupts::without_interrupts(|| {
+        // The registers may change between reads, read until two reads agree
+        let mut raw = read_raw(century_register);
+        loop {
+            let next = read_raw(century_register);
+            if next == raw {
+                break;
+            }
+            raw = next;
+        }
+        (raw, read_register(REGISTER_STATUS_B))
+    });
+
+    let binary = status_b & STATUS_B_BINARY != 0;
+    let convert = |value: u8| if binary { value } else { from_bcd(value) };
+
+    let pm = raw.hour & HOUR_PM != 0;
+    let mut hour = convert(raw.hour & !HOUR_PM);
+    if status_b & STATUS_B_24_HOUR == 0 {
+        // 12 hour clock, 12 AM is midnight and 12 PM is noon
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    let century = match century_register {
+        Some(_) => convert(raw.century) as u16,
+        None => 20,
+    };
+
+    DateTime {
+        year: century * 100 + convert(raw.year) as u16,
+        month: convert(raw.month),
+        day: convert(raw.day),
+        hour,
+        minute: convert(raw.minute),
+        second: convert(raw.second),
+        nanosecond: 0,
+    }
+}
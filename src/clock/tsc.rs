@@ -0,0 +1,101 @@
+use core::{
+    arch::{asm, x86_64::__cpuid},
+    time::Duration,
+};
+
+use x86_64::instructions::port::Port;
+
+use crate::timer::{hpet, pit::PIT_FREQUENCY};
+
+const CALIBRATION_TIME: Duration = Duration::from_millis(10);
+
+const PIT_CHANNEL2_DATA: u16 = 0x42;
+const PIT_COMMAND: u16 = 0x43;
+// Channel 2, access mode lobyte/hibyte, mode 0 (interrupt on terminal count)
+const PIT_CHANNEL2_ONE_SHOT: u8 = 0b1011_0000;
+// Port 0x61 controls the channel 2 gate (bit 0), the speaker (bit 1) and
+// reflects the channel 2 output (bit 5)
+const NMI_STATUS_AND_CONTROL: u16 = 0x61;
+
+pub fn read() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// Like `read`, but only once the instructions before it have finished, and
+/// before the ones after it start, for timing short pieces of code
+pub fn read_fenced() -> u64 {
+    unsafe {
+        asm!("lfence", options(nostack, preserves_flags));
+        let cycles = core::arch::x86_64::_rdtsc();
+        asm!("lfence", options(nostack, preserves_flags));
+        cycles
+    }
+}
+
+/// An invariant TSC runs at a constant rate in all P-, C- and T-states
+pub fn is_invariant() -> bool {
+    let max_extended_leaf = unsafe { __cpuid(0x8000_0000) }.eax;
+    if max_extended_leaf < 0x8000_0007 {
+        return false;
+    }
+    unsafe { __cpuid(0x8000_0007) }.edx & (1 << 8) != 0
+}
+
+/// The TSC frequency as reported by CPUID leaf 0x15, if the CPU enumerates it
+fn cpuid_frequency() -> Option<u64> {
+    if unsafe { __cpuid(0) }.eax < 0x15 {
+        return None;
+    }
+    let leaf = unsafe { __cpuid(0x15) };
+    let (denominator, numerator, crystal) = (leaf.eax, leaf.ebx, leaf.ecx);
+    if denominator == 0 || numerator == 0 || crystal == 0 {
+        return None;
+    }
+    Some(crystal as u64 * numerator as u64 / denominator as u64)
+}
+
+fn calibrate_with_hpet(hpet: &hpet::Hpet) -> u64 {
+    let start = read();
+    hpet.busy_wait(CALIBRATION_TIME);
+    let end = read();
+    (end - start) * 1_000_000_000 / CALIBRATION_TIME.as_nanos() as u64
+}
+
+// Lets PIT channel 2 count down once (it doesn't raise an interrupt) and
+// counts TSC cycles until its output goes high
+fn calibrate_with_pit() -> u64 {
+    let mut control: Port<u8> = Port::new(NMI_STATUS_AND_CONTROL);
+    let mut command: Port<u8> = Port::new(PIT_COMMAND);
+    let mut data: Port<u8> = Port::new(PIT_CHANNEL2_DATA);
+
+    let count = (PIT_FREQUENCY as u64 * CALIBRATION_TIME.as_nanos() as u64 / 1_000_000_000) as u16;
+
+    unsafe {
+        // Gate high, speaker off
+        let value = control.read();
+        control.write((value & !0b10) | 0b01);
+
+        command.write(PIT_CHANNEL2_ONE_SHOT);
+        data.write(count as u8);
+        data.write((count >> 8) as u8);
+
+        let start = read();
+        while control.read() & (1 << 5) == 0 {
+            core::hint::spin_loop();
+        }
+        let end = read();
+
+        (end - start) * PIT_FREQUENCY as u64 / count as u64
+    }
+}
+
+/// Measures the TSC frequency in Hz, preferring CPUID, then the HPET and then the PIT
+pub fn calibrate() -> u64 {
+    if let Some(frequency) = cpuid_frequency() {
+        return frequency;
+    }
+    match hpet::get() {
+        Some(hpet) => calibrate_with_hpet(hpet),
+        None => calibrate_with_pit(),
+    }
+}
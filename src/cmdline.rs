@@ -0,0 +1,211 @@
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use multiboot2::BootInformation;
+use spin::Once;
+
+use crate::println;
+
+static CMDLINE: Once<String> = Once::new();
+
+/// A command line option, registered by adding it to the `PARAMS` slice, next
+/// to the code that reads it. Options nobody registered are warned about.
+pub struct Param {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub help: &'static str,
+}
+
+#[linkme::distributed_slice]
+pub static PARAMS: [Param];
+
+/// What an option's value looks like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// No value, e.g. `bench`
+    Flag,
+    /// Any value, checked by whoever reads it
+    Text,
+    /// A decimal number
+    Number,
+    /// One of the given words
+    Choice(&'static [&'static str]),
+}
+
+impl Kind {
+    /// Checks a value given for an option of this kind, `None` meaning no `=`
+    pub fn check(self, value: Option<&str>) -> Result<(), &'static str> {
+        match (self, value) {
+            (Kind::Flag, None) => Ok(()),
+            (Kind::Flag, Some(_)) => Err("takes no value"),
+            (_, None) => Err("needs a value"),
+            (Kind::Text, Some(_)) => Ok(()),
+            (Kind::Number, Some(value)) if value.parse::<u64>().is_ok() => Ok(()),
+            (Kind::Number, Some(_)) => Err("expects a number"),
+            (Kind::Choice(choices), Some(value)) if choices.contains(&value) => Ok(()),
+            (Kind::Choice(_), Some(_)) => Err("unknown value"),
+        }
+    }
+}
+
+// The value part of the usage, e.g. `=<number>`
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::Flag => Ok(()),
+            Kind::Text => write!(f, "=<text>"),
+            Kind::Number => write!(f, "=<number>"),
+            Kind::Choice(choices) => {
+                for (index, choice) in choices.iter().enumerate() {
+                    let separator = if index == 0 { "=" } else { "|" };
+                    write!(f, "{}{}", separator, choice)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// One option as written on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arg<'a> {
+    pub key: &'a str,
+    /// `None` for a flag, `Some("")` for `key=`
+    pub value: Option<&'a str>,
+}
+
+/// Splits a command line into its options, separated by whitespace
+pub fn parse(cmdline: &str) -> impl Iterator<Item = Arg<'_>> {
+    cmdline
+        .split_whitespace()
+        .map(|option| match option.split_once('=') {
+            Some((key, value)) => Arg {
+                key,
+                value: Some(value),
+            },
+            None => Arg {
+                key: option,
+                value: None,
+            },
+        })
+}
+
+/// Copies the kernel command line out of the multiboot information, so it
+/// stays available after the boot information is gone, and warns about options
+/// that aren't registered or have the wrong kind of value. Needs the heap.
+pub fn init(boot_info: &BootInformation) {
+    let cmdline = boot_info
+        .command_line_tag()
+        .and_then(|tag| tag.cmdline().ok())
+        .unwrap_or("");
+    CMDLINE.call_once(|| String::from(cmdline));
+
+    for arg in args() {
+        match param(arg.key) {
+            Some(param) => {
+                if let Err(problem) = param.kind.check(arg.value) {
+                    log::warn!("command line option {}: {}", arg.key, problem);
+                }
+            }
+            None => log::warn!("unknown command line option {:?}", arg.key),
+        }
+    }
+}
+
+/// The whole command line, empty if `init` has not been called
+pub fn raw() -> &'static str {
+    CMDLINE.get().map(String::as_str).unwrap_or("")
+}
+
+/// The options on the command line, in order
+pub fn args() -> impl Iterator<Item = Arg<'static>> {
+    parse(raw())
+}
+
+/// The registered option called `name`
+pub fn param(name: &str) -> Option<&'static Param> {
+    PARAMS.iter().find(|param| param.name == name)
+}
+
+/// The value of the last `key=value` option named `key`
+pub fn get(key: &str) -> Option<&'static str> {
+    find(raw(), key)
+}
+
+/// The value of `key` converted to `T`, `None` if it is missing or doesn't
+/// convert. `init` has already warned about values of the wrong kind.
+pub fn get_parsed<T: FromStr>(key: &str) -> Option<T> {
+    get(key).and_then(|value| value.parse().ok())
+}
+
+/// Whether the command line contains `name` on its own, or as `name=...`
+pub fn flag(name: &str) -> bool {
+    args().any(|arg| arg.key == name)
+}
+
+fn find<'a>(cmdline: &'a str, key: &str) -> Option<&'a str> {
+    parse(cmdline)
+        .filter(|arg| arg.key == key)
+        .filter_map(|arg| arg.value)
+        .last()
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static CMDLINE_COMMAND: crate::shell::Command = crate::shell::Command {
+    name: "cmdline",
+    help: "print the kernel command line, or the known options: cmdline [help]",
+    run: cmdline,
+};
+
+fn cmdline(args: &[&str]) {
+    match args {
+        [] => println!("{}", raw()),
+        ["help"] => {
+            let mut params: Vec<&Param> = PARAMS.iter().collect();
+            params.sort_by_key(|param| param.name);
+            for param in params {
+                let usage = format!("{}{}", param.name, param.kind);
+                println!("  {:<32} {}", usage, param.help);
+            }
+        }
+        _ => println!("usage: cmdline [help]"),
+    }
+}
+
+crate::test_cases! {
+    fn cmdline_options() {
+        let cmdline = "quiet keyboard.layout=de keyboard.ctrl= keyboard.layout=uk";
+        // Later options override earlier ones
+        assert_eq!(find(cmdline, "keyboard.layout"), Some("uk"));
+        assert_eq!(find(cmdline, "keyboard.ctrl"), Some(""));
+        assert_eq!(find(cmdline, "quiet"), None);
+        assert_eq!(find(cmdline, "keyboard"), None);
+
+        let args: Vec<Arg> = parse(cmdline).collect();
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[0], Arg { key: "quiet", value: None });
+        assert_eq!(args[2], Arg { key: "keyboard.ctrl", value: Some("") });
+    }
+
+    fn cmdline_kinds() {
+        assert_eq!(Kind::Flag.check(None), Ok(()));
+        assert!(Kind::Flag.check(Some("1")).is_err());
+        assert!(Kind::Text.check(None).is_err());
+        assert_eq!(Kind::Number.check(Some("115200")), Ok(()));
+        assert!(Kind::Number.check(Some("fast")).is_err());
+        let choice = Kind::Choice(&["tap", "junit"]);
+        assert_eq!(choice.check(Some("junit")), Ok(()));
+        assert!(choice.check(Some("xml")).is_err());
+        assert_eq!(format!("{}", choice), "=tap|junit");
+    }
+
+    fn cmdline_params_are_unique() {
+        for (index, param) in PARAMS.iter().enumerate() {
+            assert!(
+                PARAMS[index + 1..].iter().all(|other| other.name != param.name),
+                "{} is registered twice",
+                param.name
+            );
+        }
+    }
+}
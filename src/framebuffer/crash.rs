@@ -0,0 +1,219 @@
+use core::{
+    fmt::{self, Write},
+    panic::Location,
+};
+
+use noto_sans_mono_bitmap::{get_raster, get_raster_width, RasterHeight};
+use x86_64::{
+    instructions::interrupts,
+    registers::control::{Cr0, Cr2, Cr3, Cr4},
+};
+
+use super::{
+    color::Color,
+    writer::{BACKUP_CHAR, FONT_WEIGHT},
+    FrameBuffer, RENDERER,
+};
+use crate::{backtrace::Backtrace, logger, logger::dmesg::DMESG};
+
+// White on blue, hard to mistake for the normal red background
+const BACKGROUND: u32 = 0x0000aa;
+const FOREGROUND: u32 = 0xffffff;
+const LOG_FOREGROUND: u32 = 0xaaaaff;
+
+// A smaller font than the writer's, to fit the whole report
+const FONT_HEIGHT: RasterHeight = RasterHeight::Size16;
+const CHAR_WIDTH: usize = get_raster_width(FONT_WEIGHT, FONT_HEIGHT);
+const LINE_SPACING: usize = 2;
+const PADDING: usize = 16;
+
+/// At most this many lines of the kernel log are shown, fewer if they don't fit
+const LOG_LINES: usize = 20;
+
+/// What is shown on the crash screen
+pub struct Crash<'a> {
+    /// e.g. `KERNEL PANIC` or the name of the exception
+    pub title: &'a str,
+    pub message: Option<&'a dyn fmt::Display>,
+    pub location: Option<&'a Location<'a>>,
+    /// The exception vector and error code
+    pub exception: Option<(u8, Option<u64>)>,
+    /// The interrupted code's registers, the control registers are added to them
+    pub registers: &'a [(&'static str, u64)],
+    pub backtrace: &'a Backtrace,
+}
+
+// The control registers at the time of the crash
+fn control_registers() -> [(&'static str, u64); 4] {
+    [
+        ("cr0", Cr0::read_raw()),
+        ("cr2", Cr2::read_raw()),
+        ("cr3", Cr3::read_raw().0.start_address().as_u64()),
+        ("cr4", Cr4::read_raw()),
+    ]
+}
+
+impl fmt::Display for Crash<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.title)?;
+        if let Some(message) = self.message {
+            writeln!(f, "{}", message)?;
+        }
+        if let Some(location) = self.location {
+            writeln!(f, "at {}", location)?;
+        }
+        if let Some((vector, error_code)) = self.exception {
+            write!(f, "vector {}", vector)?;
+            if let Some(error_code) = error_code {
+                write!(f, ", error code {:#x}", error_code)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        let control = control_registers();
+        let registers = self.registers.iter().chain(control.iter());
+        let count = self.registers.len() + control.len();
+        for (index, (name, value)) in registers.enumerate() {
+            // Four to a line
+            let separator = if index % 4 == 3 || index + 1 == count {
+                "\n"
+            } else {
+                "  "
+            };
+            write!(f, "{:>6} {:#018x}{}", name, value, separator)?;
+        }
+
+        writeln!(f)?;
+        write!(f, "{}", self.backtrace)
+    }
+}
+
+// Draws text straight to the front buffer, without scrolling or the heap
+struct CrashScreen<'a> {
+    buffer: &'a mut FrameBuffer,
+    x: usize,
+    y: usize,
+    foreground: Color,
+    // Long lines are wrapped, or cut off at the edge of the screen
+    wrap: bool,
+}
+
+impl<'a> CrashScreen<'a> {
+    fn new(buffer: &'a mut FrameBuffer) -> Self {
+        Self {
+            buffer,
+            x: PADDING,
+            y: PADDING,
+            foreground: Color::hex(FOREGROUND),
+            wrap: true,
+        }
+    }
+
+    fn line_height() -> usize {
+        FONT_HEIGHT.val() + LINE_SPACING
+    }
+
+    fn newline(&mut self) {
+        self.x = PADDING;
+        self.y += Self::line_height();
+    }
+
+    /// How many more lines fit on the screen
+    fn lines_left(&self) -> usize {
+        let bottom = self.buffer.height().saturating_sub(PADDING);
+        bottom.saturating_sub(self.y) / Self::line_height()
+    }
+
+    fn draw_char(&mut self, c: char) {
+        let Some(raster) = get_raster(c, FONT_WEIGHT, FONT_HEIGHT)
+            .or_else(|| get_raster(BACKUP_CHAR, FONT_WEIGHT, FONT_HEIGHT))
+        else {
+            return;
+        };
+        for (y, row) in raster.raster().iter().enumerate() {
+            for (x, intensity) in row.iter().enumerate() {
+                // The background is already filled in
+                if *intensity != 0 {
+                    self.buffer
+                        .draw_pixel(self.x + x, self.y + y, self.foreground);
+                }
+            }
+        }
+    }
+}
+
+impl Write for CrashScreen<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\n' => {
+                    self.newline();
+                    continue;
+                }
+                '\r' => continue,
+                _ => {}
+            }
+            if self.x + CHAR_WIDTH > self.buffer.width().saturating_sub(PADDING) {
+                if !self.wrap {
+                    continue;
+                }
+                self.newline();
+            }
+            // Whatever doesn't fit is left out
+            if self.lines_left() == 0 {
+                return Ok(());
+            }
+            self.draw_char(c);
+            self.x += CHAR_WIDTH;
+        }
+        Ok(())
+    }
+}
+
+/// Clears the screen and draws the crash report on it, followed by the end of
+/// the kernel log. Returns false if there is no framebuffer, or it is in use.
+pub fn show(crash: &Crash) -> bool {
+    let Some(mut renderer) = RENDERER.try_lock() else {
+        return false;
+    };
+    let Some(renderer) = renderer.as_mut() else {
+        return false;
+    };
+
+    // Not through the back buffer, a swap would copy over half drawn text
+    let front = renderer.front();
+    front.fill(Color::hex(BACKGROUND));
+    let mut screen = CrashScreen::new(front);
+    let _ = write!(screen, "{}", crash);
+
+    if let Some(dmesg) = DMESG.try_lock() {
+        screen.foreground = Color::hex(LOG_FOREGROUND);
+        let _ = writeln!(screen, "\nkernel log:");
+        screen.wrap = false;
+        let lines = screen.lines_left().min(LOG_LINES);
+        let _ = dmesg.dump_tail(lines, &mut screen);
+    }
+    true
+}
+
+/// Shows the crash screen, prints the report to the serial port and halts.
+/// Takes over the printing locks, since whatever holds them never runs again.
+pub fn report(crash: &Crash) -> ! {
+    interrupts::disable();
+    unsafe { logger::force_unlock() };
+
+    // Fails the running test, the runner carries on with the next one
+    #[cfg(testing)]
+    crate::tests::on_crash(crash);
+
+    #[cfg(not(testing))]
+    {
+        if show(crash) {
+            crate::serial_println!("{}", crash);
+        } else {
+            crate::eprintln!("{}", crash);
+        }
+        crate::hlt_loop();
+    }
+}
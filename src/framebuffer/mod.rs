@@ -1,8 +1,10 @@
 pub mod builder;
-pub mod color;
+pub mod crash;
 pub mod renderer;
 pub mod writer;
 
+pub use kernel_core::color;
+
 use crate::{
     memory::{
         frame::{Frame, FrameAllocator},
@@ -14,6 +16,7 @@ use crate::{
 use alloc::vec::Vec;
 use builder::FrameBufferBuilder;
 use color::Color;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use multiboot2::{
     BootInformation, BootInformationHeader, FramebufferField, FramebufferTag, TagTrait,
 };
@@ -87,11 +90,41 @@ lazy_static::lazy_static! {
     pub static ref RENDERER: Mutex<Option<FrameBufferRenderer>> = Mutex::new(None);
 }
 
-fn with_renderer(mut callback: impl FnMut(&mut FrameBufferRenderer)) {
-    let mut x = crate::framebuffer::RENDERER.lock();
-    let x = x.as_mut();
-    let x = x.unwrap();
-    callback(x);
+// Screen size, readable without taking the renderer lock
+static WIDTH: AtomicUsize = AtomicUsize::new(0);
+static HEIGHT: AtomicUsize = AtomicUsize::new(0);
+
+// Does nothing before the framebuffer is initialized
+fn with_renderer(callback: impl FnOnce(&mut FrameBufferRenderer)) {
+    if let Some(renderer) = RENDERER.lock().as_mut() {
+        callback(renderer);
+    }
+}
+
+/// Runs `callback` with the writer, unless the framebuffer isn't initialized or
+/// is already being drawn to, e.g. by the code this interrupted. Returns
+/// whether `callback` ran.
+pub fn try_with_writer(callback: impl FnOnce(&mut FrameBufferWriter)) -> bool {
+    let Some(mut writer) = WRITER.try_lock() else {
+        return false;
+    };
+    // The writer takes the renderer lock itself. Since the caller can't be
+    // interrupted, nobody else can take it in between.
+    match RENDERER.try_lock() {
+        Some(renderer) if renderer.is_some() => {}
+        _ => return false,
+    }
+    callback(&mut writer);
+    true
+}
+
+/// Unlocks the writer and the renderer.
+///
+/// # Safety
+/// Whoever holds the locks must never run again, e.g. because the kernel panicked.
+pub(crate) unsafe fn force_unlock() {
+    WRITER.force_unlock();
+    RENDERER.force_unlock();
 }
 
 pub fn init<'a>(boot_info: &BootInformation) {
@@ -105,12 +138,40 @@ pub fn init<'a>(boot_info: &BootInformation) {
         .build();
 
     let renderer = FrameBufferRenderer::new(front, back);
+    WIDTH.store(renderer.width(), Ordering::Relaxed);
+    HEIGHT.store(renderer.height(), Ordering::Relaxed);
 
     *RENDERER.lock() = Some(renderer);
 
     // *WRITER.lock() = Some(FrameBufferWriter::new(framebuffer));
 }
 
+/// Width and height of the screen in pixels, once the framebuffer is initialized
+pub fn dimensions() -> Option<(usize, usize)> {
+    match (
+        WIDTH.load(Ordering::Relaxed),
+        HEIGHT.load(Ordering::Relaxed),
+    ) {
+        (0, _) | (_, 0) => None,
+        dimensions => Some(dimensions),
+    }
+}
+
+/// Moves (or hides, with None) the mouse cursor. Safe to call from interrupt
+/// handlers: does nothing and returns false if the screen is being drawn to.
+pub fn set_mouse_cursor(position: Option<(usize, usize)>) -> bool {
+    match RENDERER.try_lock() {
+        Some(mut renderer) => match renderer.as_mut() {
+            Some(renderer) => {
+                renderer.set_cursor(position);
+                true
+            }
+            None => false,
+        },
+        None => false,
+    }
+}
+
 pub fn fill_bg() {
     let mut x = crate::framebuffer::RENDERER.lock();
     let mut c = x.as_mut().unwrap();
@@ -125,3 +186,10 @@ pub fn fill_bg() {
 //     let mut w = w.unwrap();
 //     w.load_font();
 // }
+
+crate::bench_cases! {
+    // Copying the back buffer to the screen, once per frame
+    fn swap_buffers(bencher: &mut Bencher) {
+        with_renderer(|renderer| bencher.iter(|| renderer.swap()));
+    }
+}
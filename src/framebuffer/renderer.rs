@@ -2,14 +2,41 @@ use multiboot2::FramebufferTag;
 
 use super::{builder::FrameBufferBuilder, color::Color, FrameBuffer};
 
+// Mouse cursor arrow, '#' is the outline and '.' the fill
+const CURSOR: [&[u8]; 16] = [
+    b"#",
+    b"##",
+    b"#.#",
+    b"#..#",
+    b"#...#",
+    b"#....#",
+    b"#.....#",
+    b"#......#",
+    b"#.......#",
+    b"#........#",
+    b"#.....#####",
+    b"#..#..#",
+    b"#.# #..#",
+    b"##  #..#",
+    b"#    #..#",
+    b"     ####",
+];
+const CURSOR_WIDTH: usize = 11;
+
 pub struct FrameBufferRenderer {
     front: FrameBuffer,
     back: FrameBuffer,
+    // The mouse cursor is only drawn to the front buffer, on top of the last swap
+    cursor: Option<(usize, usize)>,
 }
 
 impl FrameBufferRenderer {
     pub fn new(front: FrameBuffer, back: FrameBuffer) -> Self {
-        Self { front, back }
+        Self {
+            front,
+            back,
+            cursor: None,
+        }
     }
 
     pub fn draw_pixel(&mut self, x: usize, y: usize, color: Color) {
@@ -37,6 +64,55 @@ impl FrameBufferRenderer {
 
     pub fn swap(&mut self) {
         self.front.buffer().copy_from_slice(&self.back.buffer());
+        self.draw_cursor();
+    }
+
+    /// The buffer on screen, for drawing that must show up right away, like the
+    /// crash screen. Hides the mouse cursor.
+    pub fn front(&mut self) -> &mut FrameBuffer {
+        self.cursor = None;
+        &mut self.front
+    }
+
+    /// Moves the mouse cursor to `position`, or hides it
+    pub fn set_cursor(&mut self, position: Option<(usize, usize)>) {
+        if let Some((x, y)) = self.cursor {
+            self.restore_front(x, y, CURSOR_WIDTH, CURSOR.len());
+        }
+        self.cursor = position;
+        self.draw_cursor();
+    }
+
+    fn draw_cursor(&mut self) {
+        let Some((x_offset, y_offset)) = self.cursor else {
+            return;
+        };
+        for (y, row) in CURSOR.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                let color = match pixel {
+                    b'#' => Color::hex(0x000000),
+                    b'.' => Color::hex(0xffffff),
+                    _ => continue,
+                };
+                self.front.draw_pixel(x_offset + x, y_offset + y, color);
+            }
+        }
+    }
+
+    // Copies a rectangle from the back buffer to the front buffer, to erase the cursor
+    fn restore_front(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        if x >= self.back.width() {
+            return;
+        }
+        let bpp = self.back.bpp();
+        let pitch = self.back.pitch();
+        let end_x = (x + width).min(self.back.width());
+        let end_y = (y + height).min(self.back.height());
+        for row in y..end_y {
+            let start = row * pitch + x * bpp;
+            let end = row * pitch + end_x * bpp;
+            self.front.buffer()[start..end].copy_from_slice(&self.back.buffer()[start..end]);
+        }
     }
 }
 
@@ -44,6 +44,45 @@ impl FrameBufferWriter {
         todo!()
     }
 
+    /// Where the next character will be drawn, in pixels
+    pub fn position(&self) -> (usize, usize) {
+        (self.x_offset, self.y_offset)
+    }
+
+    pub fn set_position(&mut self, x: usize, y: usize) {
+        self.x_offset = x;
+        self.y_offset = y;
+    }
+
+    pub fn foreground(&self) -> Color {
+        self.foreground
+    }
+
+    pub fn set_foreground(&mut self, color: Color) {
+        self.foreground = color;
+    }
+
+    /// Horizontal distance between two characters, in pixels
+    pub fn char_width(&self) -> usize {
+        CHAR_RASTER_WIDTH + LETTER_SPACING
+    }
+
+    /// Draws an underline cursor at the current position. It is overdrawn by
+    /// the next character written at this position.
+    pub fn draw_cursor(&mut self) {
+        let x_offset = self.x_offset;
+        let y_offset = self.y_offset + CHAR_RASTER_HEIGHT.val() - 2;
+        let color = self.foreground;
+        with_renderer(|renderer| {
+            for y in y_offset..y_offset + 2 {
+                for x in x_offset..x_offset + CHAR_RASTER_WIDTH {
+                    renderer.draw_pixel(x, y, color);
+                }
+            }
+            renderer.swap();
+        })
+    }
+
     fn newline(&mut self) {
         self.y_offset += CHAR_RASTER_HEIGHT.val() + LINE_SPACING;
         self.carriage_return()
@@ -71,22 +110,19 @@ impl FrameBufferWriter {
     }
 
     pub fn write_char(&mut self, c: char) {
-        let x_offset = self.x_offset;
-        let y_offset = self.y_offset;
         match c {
             '\n' => self.newline(),
             '\r' => self.carriage_return(),
             c => {
-                let new_xpos = x_offset + CHAR_RASTER_WIDTH;
-                with_renderer(|renderer| {
-                    if new_xpos >= renderer.width() {
+                // Not inside `with_renderer`, since `clear` takes the renderer lock too
+                if let Some((width, height)) = super::dimensions() {
+                    if self.x_offset + CHAR_RASTER_WIDTH >= width {
                         self.newline();
                     }
-                    let new_ypos = y_offset + CHAR_RASTER_HEIGHT.val() + BORDER_PADDING;
-                    if new_ypos >= renderer.height() {
+                    if self.y_offset + CHAR_RASTER_HEIGHT.val() + BORDER_PADDING >= height {
                         self.clear();
                     }
-                });
+                }
                 self.write_rendered_char(get_char_raster(c));
             }
         }
@@ -0,0 +1,223 @@
+//! Shell commands for looking around the file systems
+
+use core::str;
+
+use super::{FileType, Metadata, VFS};
+use crate::println;
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static LS: crate::shell::Command = crate::shell::Command {
+    name: "ls",
+    help: "list a directory: ls [path]",
+    run: ls,
+};
+
+fn ls(args: &[&str]) {
+    let path = match args {
+        [] => ".",
+        [path] => *path,
+        _ => {
+            println!("usage: ls [path]");
+            return;
+        }
+    };
+    let metadata = match VFS.metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            println!("ls: {}: {}", path, error);
+            return;
+        }
+    };
+    if metadata.file_type != FileType::Directory {
+        print_metadata(&metadata, path);
+        return;
+    }
+    let entries = match VFS.read_dir(path) {
+        Ok(entries) => entries,
+        Err(error) => {
+            println!("ls: {}: {}", path, error);
+            return;
+        }
+    };
+    for entry in entries {
+        let full_path = format!("{}/{}", path.trim_end_matches('/'), entry.name);
+        match VFS.symlink_metadata(&full_path) {
+            Ok(metadata) => print_metadata(&metadata, &entry.name),
+            Err(error) => println!("ls: {}: {}", entry.name, error),
+        }
+    }
+}
+
+// Like `ls -l`, without the owners and times
+fn print_metadata(metadata: &Metadata, name: &str) {
+    let kind = match metadata.file_type {
+        FileType::File => '-',
+        FileType::Directory => 'd',
+        FileType::Symlink => 'l',
+    };
+    println!(
+        "{}{:04o} {:>10} {}",
+        kind, metadata.mode, metadata.size, name
+    );
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static CAT: crate::shell::Command = crate::shell::Command {
+    name: "cat",
+    help: "print files: cat <path>...",
+    run: cat,
+};
+
+fn cat(args: &[&str]) {
+    if args.is_empty() {
+        println!("usage: cat <path>...");
+    }
+    for path in args {
+        match VFS.read(path) {
+            Ok(data) => match str::from_utf8(&data) {
+                Ok(text) => println!("{}", text.trim_end()),
+                Err(_) => println!("{}: binary file, {} bytes", path, data.len()),
+            },
+            Err(error) => println!("cat: {}: {}", path, error),
+        }
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static WRITE: crate::shell::Command = crate::shell::Command {
+    name: "write",
+    help: "replace a file's contents with a line of text: write <path> <text>...",
+    run: write,
+};
+
+fn write(args: &[&str]) {
+    let Some((path, words)) = args.split_first() else {
+        println!("usage: write <path> <text>...");
+        return;
+    };
+    let mut text = words.join(" ");
+    text.push('\n');
+    if let Err(error) = VFS.write(path, text.as_bytes()) {
+        println!("write: {}: {}", path, error);
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static CD: crate::shell::Command = crate::shell::Command {
+    name: "cd",
+    help: "change the current directory: cd [path]",
+    run: cd,
+};
+
+fn cd(args: &[&str]) {
+    let path = match args {
+        [] => "/",
+        [path] => *path,
+        _ => {
+            println!("usage: cd [path]");
+            return;
+        }
+    };
+    if let Err(error) = VFS.set_cwd(path) {
+        println!("cd: {}: {}", path, error);
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static PWD: crate::shell::Command = crate::shell::Command {
+    name: "pwd",
+    help: "print the current directory",
+    run: |_| println!("{}", VFS.cwd()),
+};
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static MKDIR: crate::shell::Command = crate::shell::Command {
+    name: "mkdir",
+    help: "create directories: mkdir <path>...",
+    run: mkdir,
+};
+
+fn mkdir(args: &[&str]) {
+    for path in args {
+        if let Err(error) = VFS.create_dir(path) {
+            println!("mkdir: {}: {}", path, error);
+        }
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static RM: crate::shell::Command = crate::shell::Command {
+    name: "rm",
+    help: "remove files, symlinks or empty directories: rm <path>...",
+    run: rm,
+};
+
+fn rm(args: &[&str]) {
+    for path in args {
+        if let Err(error) = VFS.remove(path) {
+            println!("rm: {}: {}", path, error);
+        }
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static LN: crate::shell::Command = crate::shell::Command {
+    name: "ln",
+    help: "create a symlink: ln -s <target> <path>",
+    run: ln,
+};
+
+fn ln(args: &[&str]) {
+    match args {
+        ["-s", target, path] => {
+            if let Err(error) = VFS.symlink(target, path) {
+                println!("ln: {}: {}", path, error);
+            }
+        }
+        _ => println!("usage: ln -s <target> <path>"),
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static STAT: crate::shell::Command = crate::shell::Command {
+    name: "stat",
+    help: "print what is known about a file: stat <path>",
+    run: stat,
+};
+
+fn stat(args: &[&str]) {
+    let [path] = args else {
+        println!("usage: stat <path>");
+        return;
+    };
+    let metadata = match VFS.symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            println!("stat: {}: {}", path, error);
+            return;
+        }
+    };
+    println!("  type: {:?}", metadata.file_type);
+    println!("  size: {}", metadata.size);
+    println!("  mode: {:04o}", metadata.mode);
+    println!(" inode: {}", metadata.inode);
+    println!(" mtime: {}", metadata.mtime);
+    if metadata.file_type == FileType::Symlink {
+        if let Ok(target) = VFS.read_link(path) {
+            println!("target: {}", target);
+        }
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static MOUNT: crate::shell::Command = crate::shell::Command {
+    name: "mount",
+    help: "list the mounted file systems",
+    run: mount,
+};
+
+fn mount(_args: &[&str]) {
+    for (path, name) in VFS.mounts() {
+        println!("{:<8} on {}", name, path);
+    }
+}
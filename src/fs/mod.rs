@@ -0,0 +1,105 @@
+//! The kernel's directory tree: a tmpfs at `/`, with the initrd mounted read
+//! only at `/initrd` and each FAT formatted disk at `/mnt/ata<N>`. The file
+//! systems themselves live in `kernel_core::fs`.
+
+pub mod commands;
+
+pub use kernel_core::fs::*;
+
+use alloc::sync::Arc;
+
+use crate::{ata, initrd};
+
+pub static VFS: Vfs = Vfs::new();
+
+/// Mounts the root tmpfs, the initrd and the disks. Needs the heap,
+/// `initrd::init` and `ata::init`.
+pub fn init() {
+    VFS.mount("/", Arc::new(TmpFs::new()))
+        .expect("mounting the root file system failed");
+    mount_initrd();
+    mount_disks();
+}
+
+fn mount_initrd() {
+    let Some(archive) = initrd::archive() else {
+        return;
+    };
+    let mounted = ArchiveFs::new(*archive)
+        .map_err(|error| log::error!("initrd: {}", error))
+        .and_then(|fs| {
+            VFS.create_dir("/initrd")
+                .and_then(|_| VFS.mount("/initrd", Arc::new(fs)))
+                .map_err(|error| log::error!("mounting the initrd failed: {}", error))
+        });
+    if mounted.is_ok() {
+        log::info!("initrd mounted at /initrd");
+    }
+}
+
+// Disks without a FAT volume are left alone, there is nothing else to mount
+fn mount_disks() {
+    for disk in ata::disks() {
+        let fs = match FatFs::new(disk.clone()) {
+            Ok(fs) => fs,
+            Err(error) => {
+                log::info!("{}: no FAT volume ({})", disk.name(), error);
+                continue;
+            }
+        };
+        let path = format!("/mnt/{}", disk.name());
+        let name = fs.name();
+        let mounted = match VFS.create_dir("/mnt") {
+            Ok(()) | Err(Error::AlreadyExists) => VFS.create_dir(&path),
+            Err(error) => Err(error),
+        }
+        .and_then(|_| VFS.mount(&path, Arc::new(fs)));
+        match mounted {
+            Ok(()) => log::info!("{} mounted at {}", name, path),
+            Err(error) => log::error!("mounting {} failed: {}", path, error),
+        }
+    }
+}
+
+crate::test_cases! {
+    fn initrd_is_mounted() {
+        let expected = include_bytes!("../../initrd/tests/hello.txt");
+        assert_eq!(VFS.read("/initrd/tests/hello.txt"), Ok(expected.to_vec()));
+        assert_eq!(VFS.write("/initrd/tests/new", b""), Err(Error::ReadOnly));
+        assert!(VFS.mounts().iter().any(|(path, _)| path == "/initrd"));
+    }
+
+    fn root_is_writable() {
+        VFS.create_dir("/fs-test").unwrap();
+        VFS.write("/fs-test/file", b"data").unwrap();
+        VFS.symlink("/fs-test/file", "/fs-test/link").unwrap();
+        assert_eq!(VFS.read("/fs-test/link"), Ok(b"data".to_vec()));
+
+        VFS.remove("/fs-test/link").unwrap();
+        VFS.remove("/fs-test/file").unwrap();
+        VFS.remove("/fs-test").unwrap();
+        assert_eq!(VFS.metadata("/fs-test"), Err(Error::NotFound));
+    }
+
+    fn fat_disk_is_mounted() {
+        let expected = include_bytes!("../../disk/hello.txt");
+        assert_eq!(VFS.read("/mnt/ata0/hello.txt"), Ok(expected.to_vec()));
+        let expected = include_bytes!("../../disk/Documents/Long-File-Name.txt");
+        assert_eq!(
+            VFS.read("/mnt/ata0/Documents/Long-File-Name.txt"),
+            Ok(expected.to_vec())
+        );
+    }
+
+    fn fat_disk_is_writable() {
+        VFS.create_dir("/mnt/ata0/fat test").unwrap();
+        let data: alloc::vec::Vec<u8> = (0..5000).map(|byte| byte as u8).collect();
+        VFS.write("/mnt/ata0/fat test/Some Long File Name.bin", &data).unwrap();
+        assert_eq!(VFS.read("/mnt/ata0/FAT TEST/some long file name.bin"), Ok(data));
+
+        assert_eq!(VFS.remove("/mnt/ata0/fat test"), Err(Error::DirectoryNotEmpty));
+        VFS.remove("/mnt/ata0/fat test/Some Long File Name.bin").unwrap();
+        VFS.remove("/mnt/ata0/fat test").unwrap();
+        assert_eq!(VFS.metadata("/mnt/ata0/fat test"), Err(Error::NotFound));
+    }
+}
@@ -1,56 +0,0 @@
-use super::utils::{align_up, Locked};
-use alloc::alloc::{GlobalAlloc, Layout};
-use core::ptr::null_mut;
-
-pub struct BumpAllocator {
-    heap_start: usize,
-    heap_end: usize,
-    next: usize,
-    allocations: usize,
-}
-
-impl BumpAllocator {
-    pub const fn new() -> Self {
-        Self {
-            heap_start: 0,
-            heap_end: 0,
-            next: 0,
-            allocations: 0,
-        }
-    }
-
-    pub unsafe fn init(&mut self, heap_start: usize, heap_size: usize) {
-        self.heap_start = heap_start;
-        self.heap_end = heap_start + heap_size;
-        self.next = heap_start;
-    }
-}
-
-unsafe impl GlobalAlloc for Locked<BumpAllocator> {
-    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let mut allocator = self.lock();
-        let alloc_start = align_up(allocator.next, layout.align());
-        let alloc_end = match alloc_start.checked_add(layout.size()) {
-            Some(end) => end,
-            None => {
-                return null_mut();
-            }
-        };
-        if alloc_end > allocator.heap_end {
-            return null_mut();
-        } else {
-            allocator.next = alloc_end;
-            allocator.allocations += 1;
-            return alloc_start as *mut u8;
-        }
-    }
-
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
-        let mut allocator = self.lock();
-
-        allocator.allocations -= 1;
-        if allocator.allocations == 0 {
-            allocator.next = allocator.heap_start;
-        }
-    }
-}
@@ -1,6 +1,4 @@
-pub mod bump_allocator;
-pub mod linked_list_allocator;
-pub mod utils;
+pub use kernel_core::heap::{bump_allocator, linked_list_allocator, utils};
 
 use alloc::{boxed::Box, rc::Rc, vec::Vec};
 use bump_allocator::BumpAllocator;
@@ -19,6 +17,19 @@ pub const HEAP_SIZE: usize = 8000 * 1024; // 100 KiB
 // static ALLOCATOR: BumpAllocator = BumpAllocator;
 static ALLOCATOR: Locked<LinkedListAllocator> = Locked::new(LinkedListAllocator::new());
 
+/// Bytes currently allocated on the heap
+pub fn used() -> usize {
+    ALLOCATOR.lock().used()
+}
+
+/// Unlocks the allocator.
+///
+/// # Safety
+/// Whoever holds the lock must never run again, e.g. a test that panicked.
+pub(crate) unsafe fn force_unlock() {
+    ALLOCATOR.force_unlock();
+}
+
 pub fn init(mapper: &mut Mapper, allocator: &mut impl FrameAllocator) -> Result<(), MemoryError> {
     use x86_64::instructions::tlb;
 
@@ -81,3 +92,13 @@ crate::test_cases! {
         assert_eq!(Rc::strong_count(&cloned_rc), 1);
     }
 }
+
+crate::bench_cases! {
+    fn allocate_small_box(bencher: &mut Bencher) {
+        bencher.iter(|| Box::new(0u64));
+    }
+
+    fn allocate_page_sized_vec(bencher: &mut Bencher) {
+        bencher.iter(|| Vec::<u8>::with_capacity(4096));
+    }
+}
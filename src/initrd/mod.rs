@@ -0,0 +1,172 @@
+//! The boot modules GRUB loads with `module2`, and the initial ramdisk, a tar or
+//! cpio archive among them. `memory::init` keeps their frames out of use and
+//! `paging::remap_kernel` maps them, so they are read in place.
+
+pub use kernel_core::initrd::{Archive, Entry, Error, Format, Kind};
+
+use alloc::{string::String, vec::Vec};
+use core::{slice, str};
+
+use multiboot2::BootInformation;
+use spin::Once;
+
+use crate::{cmdline, println};
+
+/// A file loaded by GRUB
+pub struct Module {
+    /// What follows the path on the `module2` line, e.g. `initrd`
+    pub cmdline: String,
+    pub data: &'static [u8],
+}
+
+static MODULES: Once<Vec<Module>> = Once::new();
+static INITRD: Once<Archive<'static>> = Once::new();
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static INITRD_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "initrd",
+    kind: crate::cmdline::Kind::Text,
+    help: "the boot module to use as the initial ramdisk, by its module2 arguments",
+};
+
+/// Collects the boot modules and opens the initrd: the module named by the
+/// `initrd=` option, `initrd` by default, or else the first one that is an
+/// archive. Needs the heap and the command line.
+pub fn init(boot_info: &BootInformation) {
+    let modules = MODULES.call_once(|| {
+        boot_info
+            .module_tags()
+            .map(|tag| Module {
+                cmdline: String::from(tag.cmdline().unwrap_or("")),
+                // Reserved and mapped for good, see the top of the file
+                data: unsafe {
+                    slice::from_raw_parts(
+                        tag.start_address() as usize as *const u8,
+                        tag.module_size() as usize,
+                    )
+                },
+            })
+            .collect()
+    });
+    for module in modules {
+        log::info!(
+            "boot module {:?} at {:#x}, {} bytes",
+            module.cmdline,
+            module.data.as_ptr() as usize,
+            module.data.len()
+        );
+    }
+
+    let name = cmdline::get("initrd").unwrap_or("initrd");
+    let named = modules
+        .iter()
+        .find(|module| module.cmdline.split_whitespace().any(|word| word == name));
+    let initrd = match named {
+        Some(module) => Archive::new(module.data).map_err(|error| {
+            log::error!("initrd {:?}: {}", module.cmdline, error);
+        }),
+        None => modules
+            .iter()
+            .find_map(|module| Archive::new(module.data).ok())
+            .ok_or(()),
+    };
+    match initrd {
+        Ok(archive) => {
+            log::info!("initrd is a {:?} archive", archive.format());
+            INITRD.call_once(|| archive);
+        }
+        Err(()) => log::info!("no initrd"),
+    }
+}
+
+/// The boot modules, in the order of the `module2` lines
+pub fn modules() -> &'static [Module] {
+    MODULES.get().map(Vec::as_slice).unwrap_or(&[])
+}
+
+pub fn archive() -> Option<&'static Archive<'static>> {
+    INITRD.get()
+}
+
+/// The contents of the file at `path` in the initrd
+pub fn read(path: &str) -> Option<&'static [u8]> {
+    archive()?.read(path)
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static INITRD_COMMAND: crate::shell::Command = crate::shell::Command {
+    name: "initrd",
+    help: "list the initrd, print a file from it, or list the boot modules: initrd [cat <path> | modules]",
+    run: initrd,
+};
+
+fn initrd(args: &[&str]) {
+    match args {
+        [] => {
+            let Some(archive) = archive() else {
+                println!("no initrd");
+                return;
+            };
+            for entry in archive.entries() {
+                match entry {
+                    Ok(entry) => print_entry(&entry),
+                    Err(error) => println!("{}", error),
+                }
+            }
+        }
+        ["cat", path] => match read(path) {
+            Some(data) => match str::from_utf8(data) {
+                Ok(text) => println!("{}", text.trim_end()),
+                Err(_) => println!("{}: binary file, {} bytes", path, data.len()),
+            },
+            None => println!("{}: no such file", path),
+        },
+        ["modules"] => {
+            for module in modules() {
+                println!(
+                    "{:#010x} {:>10} {}",
+                    module.data.as_ptr() as usize,
+                    module.data.len(),
+                    module.cmdline
+                );
+            }
+        }
+        _ => println!("usage: initrd [cat <path> | modules]"),
+    }
+}
+
+// Like `ls -l`, without the owners
+fn print_entry(entry: &Entry) {
+    let kind = match entry.kind {
+        Kind::File => '-',
+        Kind::Directory => 'd',
+        Kind::Symlink => 'l',
+        Kind::Other => '?',
+    };
+    let link = entry
+        .link_target()
+        .map(|target| format!(" -> {}", target))
+        .unwrap_or_default();
+    let size = entry.data.len();
+    println!(
+        "{}{:04o} {:>10} {}{}",
+        kind,
+        entry.mode,
+        size,
+        entry.path(),
+        link
+    );
+}
+
+crate::test_cases! {
+    // The Makefile puts the `initrd` directory on every iso
+    fn initrd_has_the_fixtures() {
+        let archive = archive().expect("no initrd");
+        let expected = include_bytes!("../../initrd/tests/hello.txt");
+        assert_eq!(read("tests/hello.txt"), Some(&expected[..]));
+        assert_eq!(read("/tests/hello.txt"), Some(&expected[..]));
+        assert_eq!(archive.get("tests").map(|entry| entry.kind), Some(Kind::Directory));
+        assert!(archive.entries().all(|entry| entry.is_ok()));
+        assert!(modules().iter().any(|module| module.cmdline.contains("initrd")));
+    }
+}
@@ -0,0 +1,411 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use bit_field::BitField;
+use spin::Mutex;
+use x86_64::registers::model_specific::Msr;
+
+use crate::{
+    acpi::{
+        self,
+        madt::{Polarity, TriggerMode},
+    },
+    memory::frame::FrameAllocator,
+    paging::{self, mapper::Mapper},
+};
+
+const IA32_APIC_BASE: u32 = 0x1B;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+const APIC_BASE_ADDRESS_MASK: u64 = 0x000f_ffff_ffff_f000;
+
+pub const IO_APIC_DEFAULT_ADDRESS: usize = 0xFEC0_0000;
+pub const SPURIOUS_INTERRUPT_VECTOR: u8 = 0xFF;
+
+// Local APIC register offsets (relative to the APIC base address)
+const LAPIC_ID: usize = 0x20;
+const LAPIC_VERSION: usize = 0x30;
+const LAPIC_TASK_PRIORITY: usize = 0x80;
+const LAPIC_EOI: usize = 0xB0;
+const LAPIC_SPURIOUS: usize = 0xF0;
+const LAPIC_ERROR_STATUS: usize = 0x280;
+const LAPIC_ICR_LOW: usize = 0x300;
+const LAPIC_ICR_HIGH: usize = 0x310;
+const LAPIC_LVT_TIMER: usize = 0x320;
+const LAPIC_LVT_LINT0: usize = 0x350;
+const LAPIC_LVT_LINT1: usize = 0x360;
+const LAPIC_LVT_ERROR: usize = 0x370;
+const LAPIC_TIMER_INITIAL_COUNT: usize = 0x380;
+const LAPIC_TIMER_CURRENT_COUNT: usize = 0x390;
+const LAPIC_TIMER_DIVIDE: usize = 0x3E0;
+
+const LVT_MASKED: u32 = 1 << 16;
+
+// I/O APIC registers, accessed indirectly through IOREGSEL / IOWIN
+const IOAPIC_REGSEL: usize = 0x00;
+const IOAPIC_WINDOW: usize = 0x10;
+const IOAPIC_ID: u32 = 0x00;
+const IOAPIC_VERSION: u32 = 0x01;
+const IOAPIC_REDIRECTION_TABLE: u32 = 0x10;
+
+/// Set once the local APIC and I/O APIC have taken over from the 8259 PICs
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub static LOCAL_APIC: Mutex<Option<LocalApic>> = Mutex::new(None);
+pub static IO_APICS: Mutex<Vec<IoApic>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum TimerDivide {
+    By1 = 0b1011,
+    By2 = 0b0000,
+    By4 = 0b0001,
+    By8 = 0b0010,
+    By16 = 0b0011,
+    By32 = 0b1000,
+    By64 = 0b1001,
+    By128 = 0b1010,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    OneShot,
+    Periodic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DeliveryMode {
+    Fixed = 0b000,
+    LowestPriority = 0b001,
+    Smi = 0b010,
+    Nmi = 0b100,
+    Init = 0b101,
+    StartUp = 0b110,
+    ExtInt = 0b111,
+}
+
+pub struct LocalApic {
+    base: usize,
+}
+
+impl LocalApic {
+    /// # Safety
+    /// `base` must be the virtual address the local APIC's registers are mapped
+    /// at, e.g. by `paging::map_mmio`, and there must be only one `LocalApic` per
+    /// processor.
+    pub unsafe fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    fn read(&self, register: usize) -> u32 {
+        unsafe { core::ptr::read_volatile((self.base + register) as *const u32) }
+    }
+
+    fn write(&mut self, register: usize, value: u32) {
+        unsafe { core::ptr::write_volatile((self.base + register) as *mut u32, value) }
+    }
+
+    pub fn id(&self) -> u8 {
+        self.read(LAPIC_ID).get_bits(24..32) as u8
+    }
+
+    pub fn version(&self) -> u8 {
+        self.read(LAPIC_VERSION).get_bits(0..8) as u8
+    }
+
+    pub fn enable(&mut self) {
+        // Mask every local interrupt source until a driver asks for it
+        self.write(LAPIC_LVT_TIMER, LVT_MASKED);
+        self.write(LAPIC_LVT_LINT0, LVT_MASKED);
+        self.write(LAPIC_LVT_LINT1, LVT_MASKED);
+        self.write(LAPIC_LVT_ERROR, LVT_MASKED);
+
+        // Clear the error status register (needs back to back writes)
+        self.write(LAPIC_ERROR_STATUS, 0);
+        self.write(LAPIC_ERROR_STATUS, 0);
+
+        // Accept all interrupt priorities
+        self.write(LAPIC_TASK_PRIORITY, 0);
+
+        // Software enable the APIC and set the spurious interrupt vector
+        let mut spurious = self.read(LAPIC_SPURIOUS);
+        spurious.set_bits(0..8, SPURIOUS_INTERRUPT_VECTOR as u32);
+        spurious.set_bit(8, true);
+        self.write(LAPIC_SPURIOUS, spurious);
+    }
+
+    pub fn end_of_interrupt(&mut self) {
+        self.write(LAPIC_EOI, 0);
+    }
+
+    pub fn start_timer(&mut self, vector: u8, divide: TimerDivide, count: u32, mode: TimerMode) {
+        let mut lvt = vector as u32;
+        lvt.set_bit(17, mode == TimerMode::Periodic);
+        self.write(LAPIC_TIMER_DIVIDE, divide as u32);
+        self.write(LAPIC_LVT_TIMER, lvt);
+        // Writing the initial count starts the timer
+        self.write(LAPIC_TIMER_INITIAL_COUNT, count);
+    }
+
+    pub fn stop_timer(&mut self) {
+        self.write(LAPIC_LVT_TIMER, LVT_MASKED);
+        self.write(LAPIC_TIMER_INITIAL_COUNT, 0);
+    }
+
+    pub fn timer_current_count(&self) -> u32 {
+        self.read(LAPIC_TIMER_CURRENT_COUNT)
+    }
+
+    fn send_command(&mut self, destination: u8, low: u32) {
+        self.write(LAPIC_ICR_HIGH, (destination as u32) << 24);
+        // Writing the low dword sends the interrupt
+        self.write(LAPIC_ICR_LOW, low);
+        while self.read(LAPIC_ICR_LOW).get_bit(12) {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Send a fixed inter processor interrupt to the APIC with id `destination`
+    pub fn send_ipi(&mut self, destination: u8, vector: u8) {
+        let mut low = vector as u32;
+        low.set_bits(8..11, DeliveryMode::Fixed as u32);
+        low.set_bit(14, true);
+        self.send_command(destination, low);
+    }
+
+    pub fn send_init_ipi(&mut self, destination: u8) {
+        let mut low = 0;
+        low.set_bits(8..11, DeliveryMode::Init as u32);
+        low.set_bit(14, true);
+        self.send_command(destination, low);
+    }
+
+    /// `page` is the physical page number (address >> 12) the AP starts executing at
+    pub fn send_startup_ipi(&mut self, destination: u8, page: u8) {
+        let mut low = page as u32;
+        low.set_bits(8..11, DeliveryMode::StartUp as u32);
+        low.set_bit(14, true);
+        self.send_command(destination, low);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectionEntry {
+    pub vector: u8,
+    pub delivery_mode: DeliveryMode,
+    pub active_low: bool,
+    pub level_triggered: bool,
+    pub masked: bool,
+    pub destination: u8,
+}
+
+impl RedirectionEntry {
+    pub fn new(vector: u8, destination: u8) -> Self {
+        Self {
+            vector,
+            delivery_mode: DeliveryMode::Fixed,
+            active_low: false,
+            level_triggered: false,
+            masked: false,
+            destination,
+        }
+    }
+
+    fn low(&self) -> u32 {
+        let mut low = self.vector as u32;
+        low.set_bits(8..11, self.delivery_mode as u32);
+        low.set_bit(13, self.active_low);
+        low.set_bit(15, self.level_triggered);
+        low.set_bit(16, self.masked);
+        low
+    }
+
+    fn high(&self) -> u32 {
+        (self.destination as u32) << 24
+    }
+}
+
+pub struct IoApic {
+    base: usize,
+    gsi_base: u32,
+}
+
+impl IoApic {
+    /// # Safety
+    /// `base` must be the virtual address the I/O APIC's registers are mapped
+    /// at, e.g. by `paging::map_mmio`, and there must be only one `IoApic` per
+    /// controller, as every register access is a write of the index followed by
+    /// a read or write.
+    pub unsafe fn new(base: usize, gsi_base: u32) -> Self {
+        Self { base, gsi_base }
+    }
+
+    fn read(&self, register: u32) -> u32 {
+        unsafe {
+            core::ptr::write_volatile((self.base + IOAPIC_REGSEL) as *mut u32, register);
+            core::ptr::read_volatile((self.base + IOAPIC_WINDOW) as *const u32)
+        }
+    }
+
+    fn write(&mut self, register: u32, value: u32) {
+        unsafe {
+            core::ptr::write_volatile((self.base + IOAPIC_REGSEL) as *mut u32, register);
+            core::ptr::write_volatile((self.base + IOAPIC_WINDOW) as *mut u32, value);
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        self.read(IOAPIC_ID).get_bits(24..28) as u8
+    }
+
+    pub fn version(&self) -> u8 {
+        self.read(IOAPIC_VERSION).get_bits(0..8) as u8
+    }
+
+    pub fn gsi_base(&self) -> u32 {
+        self.gsi_base
+    }
+
+    /// Number of redirection entries, i.e. the number of GSIs this I/O APIC handles
+    pub fn redirection_entries(&self) -> u32 {
+        self.read(IOAPIC_VERSION).get_bits(16..24) + 1
+    }
+
+    pub fn handles(&self, gsi: u32) -> bool {
+        gsi >= self.gsi_base && gsi < self.gsi_base + self.redirection_entries()
+    }
+
+    pub fn set_redirection(&mut self, gsi: u32, entry: RedirectionEntry) {
+        let register = IOAPIC_REDIRECTION_TABLE + (gsi - self.gsi_base) * 2;
+        // Mask the entry while it is being rewritten
+        self.write(register, self.read(register) | LVT_MASKED);
+        self.write(register + 1, entry.high());
+        self.write(register, entry.low());
+    }
+
+    pub fn set_masked(&mut self, gsi: u32, masked: bool) {
+        let register = IOAPIC_REDIRECTION_TABLE + (gsi - self.gsi_base) * 2;
+        let mut low = self.read(register);
+        low.set_bit(16, masked);
+        self.write(register, low);
+    }
+
+    pub fn mask_all(&mut self) {
+        for index in 0..self.redirection_entries() {
+            self.set_masked(self.gsi_base + index, true);
+        }
+    }
+}
+
+/// Checks CPUID.01h:EDX[9] for an on-chip local APIC
+pub fn is_supported() -> bool {
+    let cpuid = unsafe { core::arch::x86_64::__cpuid(1) };
+    cpuid.edx.get_bit(9)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+pub fn end_of_interrupt() {
+    if let Some(lapic) = LOCAL_APIC.lock().as_mut() {
+        lapic.end_of_interrupt();
+    }
+}
+
+fn local_apic_base() -> usize {
+    let mut msr = Msr::new(IA32_APIC_BASE);
+    unsafe {
+        let value = msr.read();
+        // Make sure the APIC is globally enabled
+        msr.write(value | APIC_BASE_ENABLE);
+        (value & APIC_BASE_ADDRESS_MASK) as usize
+    }
+}
+
+/// Routes the legacy ISA `irq` to `vector` on the current CPU, honouring the
+/// interrupt source overrides from the MADT
+pub fn route_isa_irq(irq: u8, vector: u8) {
+    let destination = match LOCAL_APIC.lock().as_ref() {
+        Some(lapic) => lapic.id(),
+        None => return,
+    };
+
+    let mut entry = RedirectionEntry::new(vector, destination);
+    // Without an ACPI override ISA interrupts are active high and edge triggered.
+    // QEMU and most chipsets wire the PIT to GSI 2, which the MADT reports.
+    let mut gsi = irq as u32;
+    if let Some(madt) = acpi::tables().and_then(|tables| tables.madt.as_ref()) {
+        if let Some(source_override) = madt.isa_override(irq) {
+            gsi = source_override.gsi;
+            entry.active_low = source_override.polarity == Polarity::ActiveLow;
+            entry.level_triggered = source_override.trigger == TriggerMode::Level;
+        }
+    } else if irq == 0 {
+        gsi = 2;
+    }
+
+    let mut io_apics = IO_APICS.lock();
+    if let Some(ioapic) = io_apics.iter_mut().find(|ioapic| ioapic.handles(gsi)) {
+        ioapic.set_redirection(gsi, entry);
+    }
+}
+
+/// Maps and enables the local APIC and the I/O APICs, routes the legacy ISA
+/// interrupts through the I/O APIC and masks the 8259 PICs.
+///
+/// Returns false (and leaves the PICs in charge) if the CPU has no APIC.
+pub fn init(mapper: &mut Mapper, allocator: &mut impl FrameAllocator) -> bool {
+    use super::pic::{self, InterruptIndex};
+
+    if !is_supported() {
+        return false;
+    }
+
+    let madt = acpi::tables().and_then(|tables| tables.madt.as_ref());
+
+    let lapic_base = match madt {
+        Some(madt) => {
+            // The MSR must still be written to globally enable the APIC
+            local_apic_base();
+            madt.local_apic_address as usize
+        }
+        None => local_apic_base(),
+    };
+    paging::map_mmio(lapic_base, mapper, allocator);
+    let mut lapic = unsafe { LocalApic::new(lapic_base) };
+
+    let mut io_apics = Vec::new();
+    match madt {
+        Some(madt) => {
+            for info in &madt.io_apics {
+                paging::map_mmio(info.address as usize, mapper, allocator);
+                io_apics.push(unsafe { IoApic::new(info.address as usize, info.gsi_base) });
+            }
+        }
+        None => {
+            paging::map_mmio(IO_APIC_DEFAULT_ADDRESS, mapper, allocator);
+            io_apics.push(unsafe { IoApic::new(IO_APIC_DEFAULT_ADDRESS, 0) });
+        }
+    }
+
+    // Stop the PICs from delivering anything, all IRQs now go through the I/O APIC
+    unsafe { pic::PICS.lock().disable() };
+
+    lapic.enable();
+    for ioapic in io_apics.iter_mut() {
+        ioapic.mask_all();
+    }
+
+    *LOCAL_APIC.lock() = Some(lapic);
+    *IO_APICS.lock() = io_apics;
+    ENABLED.store(true, Ordering::Release);
+
+    route_isa_irq(0, InterruptIndex::Timer.into());
+    route_isa_irq(1, InterruptIndex::Keyboard.into());
+    route_isa_irq(3, InterruptIndex::Com2.into());
+    route_isa_irq(4, InterruptIndex::Com1.into());
+    route_isa_irq(12, InterruptIndex::Mouse.into());
+
+    true
+}
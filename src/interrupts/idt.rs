@@ -8,6 +8,7 @@ pub type HandlerFunc = extern "C" fn();
 pub struct Idt([Entry; 256]);
 
 #[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InterruptType {
     DivideError,
     Debug,
@@ -67,7 +68,7 @@ impl Idt {
 
     pub fn set_handler(&mut self, entry: impl Into<u8>, handler: HandlerFunc) {
         let x = entry.into();
-        crate::println!("Setting handler for entry {}", x);
+        log::trace!("setting handler for entry {}", x);
         self.0[x as usize] = Entry::new(segmentation::CS::get_reg(), handler);
     }
 
@@ -1,11 +1,20 @@
+pub mod apic;
 mod idt;
 mod pic;
 
-use crate::{hlt_loop, println};
+use crate::{
+    backtrace::Backtrace,
+    framebuffer::crash::{self, Crash},
+    hlt_loop,
+    memory::frame::FrameAllocator,
+    paging::mapper::Mapper,
+    println,
+};
 use core::arch::asm;
-use idt::InterruptType;
 use pic::InterruptIndex;
 
+pub use idt::InterruptType;
+
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
 struct ExceptionStackFrame {
@@ -57,15 +66,15 @@ macro_rules! handler {
                     add rdi, 9*8 // align the stack pointer
                     call {}
                     
-                    pop rax
-                    pop rcx
-                    pop rdx
-                    pop rsi
-                    pop rdi
-                    pop r8
-                    pop r9
-                    pop r10
                     pop r11
+                    pop r10
+                    pop r9
+                    pop r8
+                    pop rdi
+                    pop rsi
+                    pop rdx
+                    pop rcx
+                    pop rax
 
                     iretq
                     ",
@@ -103,18 +112,24 @@ macro_rules! handler_with_error_code {
                     // handler
                     mov rdi, rsp 
                     add rdi, 10*8 // align the stack pointer
+
+                    // the error code leaves the stack misaligned by 8 bytes
+                    sub rsp, 8
                     call {}
                     
-                    pop rax
-                    pop rcx
-                    pop rdx
-                    pop rsi
-                    pop rdi
-                    pop r8
-                    pop r9
-                    pop r10
+                    add rsp, 8 // undo the stack alignment
+
                     pop r11
+                    pop r10
+                    pop r9
+                    pop r8
+                    pop rdi
+                    pop rsi
+                    pop rdx
+                    pop rcx
+                    pop rax
 
+                    add rsp, 8 // pop the error code
                     iretq
                     ",
                     sym $name,
@@ -136,93 +151,202 @@ lazy_static::lazy_static! {
         idt.set_handler(InterruptType::DoubleFault, handler_with_error_code!(double_fault_handler));
         idt.set_handler(InterruptIndex::Timer, handler!(timer_handler));
         idt.set_handler(InterruptIndex::Keyboard, handler!(keyboard_handler));
+        idt.set_handler(InterruptIndex::Com2, handler!(com2_handler));
+        idt.set_handler(InterruptIndex::Com1, handler!(com1_handler));
+        idt.set_handler(InterruptIndex::Mouse, handler!(mouse_handler));
+        idt.set_handler(apic::SPURIOUS_INTERRUPT_VECTOR, handler!(spurious_handler));
         idt
     };
 }
 
+// The scratch registers pushed by the `handler!` wrappers, in memory order
+#[repr(C)]
+struct ScratchRegisters {
+    r11: u64,
+    r10: u64,
+    r9: u64,
+    r8: u64,
+    rdi: u64,
+    rsi: u64,
+    rdx: u64,
+    rcx: u64,
+    rax: u64,
+}
+
+// The interrupted code's registers, for the crash screen. The scratch registers
+// are right below the stack frame, or below the error code if there is one.
+// Only valid in handlers called by the wrappers.
+unsafe fn registers(
+    stack_frame: &ExceptionStackFrame,
+    error_code: bool,
+) -> [(&'static str, u64); 14] {
+    let below = if error_code { 10 } else { 9 };
+    let scratch = &*((stack_frame as *const ExceptionStackFrame as *const u64).sub(below)
+        as *const ScratchRegisters);
+    [
+        ("rip", stack_frame.instruction_pointer),
+        ("rsp", stack_frame.stack_pointer),
+        ("rflags", stack_frame.cpu_flags),
+        ("cs", stack_frame.code_segment),
+        ("ss", stack_frame.stack_segment),
+        ("rax", scratch.rax),
+        ("rcx", scratch.rcx),
+        ("rdx", scratch.rdx),
+        ("rsi", scratch.rsi),
+        ("rdi", scratch.rdi),
+        ("r8", scratch.r8),
+        ("r9", scratch.r9),
+        ("r10", scratch.r10),
+        ("r11", scratch.r11),
+    ]
+}
+
 extern "C" fn divide_error_handler(stack_frame: &ExceptionStackFrame) {
-    println!("EXCEPTION: DIVIDE BY ZERO\n{:#?}", stack_frame);
-    hlt_loop();
+    let backtrace = Backtrace::from_exception(stack_frame.instruction_pointer);
+    crash::report(&Crash {
+        title: "EXCEPTION: DIVIDE BY ZERO",
+        message: None,
+        location: None,
+        exception: Some((InterruptType::DivideError.into(), None)),
+        registers: &unsafe { registers(stack_frame, false) },
+        backtrace: &backtrace,
+    });
 }
+
 extern "C" fn breakpoint_handler(stack_frame: &ExceptionStackFrame) {
     println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
     hlt_loop();
 }
 
 extern "C" fn invalid_opcode_handler(stack_frame: &ExceptionStackFrame) {
-    println!(
-        "\nEXCEPTION: INVALID OPCODE at {:#x}\n{:#?}",
-        stack_frame.instruction_pointer, stack_frame
-    );
-    hlt_loop();
+    let backtrace = Backtrace::from_exception(stack_frame.instruction_pointer);
+    crash::report(&Crash {
+        title: "EXCEPTION: INVALID OPCODE",
+        message: None,
+        location: None,
+        exception: Some((InterruptType::InvalidOpcode.into(), None)),
+        registers: &unsafe { registers(stack_frame, false) },
+        backtrace: &backtrace,
+    });
 }
 
 extern "C" fn double_fault_handler(stack_frame: &ExceptionStackFrame, error_code: u64) {
-    println!(
-        "\nEXCEPTION: DOUBLE FAULT\nerror code: {:?}\n{:#?}",
-        error_code, stack_frame
-    );
-    hlt_loop();
+    let backtrace = Backtrace::from_exception(stack_frame.instruction_pointer);
+    crash::report(&Crash {
+        title: "EXCEPTION: DOUBLE FAULT",
+        message: None,
+        location: None,
+        exception: Some((InterruptType::DoubleFault.into(), Some(error_code))),
+        registers: &unsafe { registers(stack_frame, true) },
+        backtrace: &backtrace,
+    });
 }
 
 extern "C" fn page_fault_handler(stack_frame: &ExceptionStackFrame, error_code: u64) -> ! {
     use x86_64::registers::control::Cr2;
-    println!(
-        "\nEXCEPTION: PAGE FAULT while accessing {:#x}\
-        \nerror code: {:?}\n{:#?}",
-        Cr2::read_raw(),
-        PageFaultErrorCode::from_bits(error_code).unwrap(),
-        stack_frame
-    );
-    hlt_loop();
+    let backtrace = Backtrace::from_exception(stack_frame.instruction_pointer);
+    crash::report(&Crash {
+        title: "EXCEPTION: PAGE FAULT",
+        message: Some(&format_args!(
+            "while accessing {:#x}: {:?}",
+            Cr2::read_raw(),
+            PageFaultErrorCode::from_bits_truncate(error_code)
+        )),
+        location: None,
+        exception: Some((InterruptType::PageFault.into(), Some(error_code))),
+        registers: &unsafe { registers(stack_frame, true) },
+        backtrace: &backtrace,
+    });
 }
 
 extern "C" fn timer_handler(_stack_frame: &ExceptionStackFrame) {
-    // crate::print!(".");
+    crate::timer::on_tick();
 
-    unsafe {
-        pic::PICS
-            .lock()
-            .notify_end_of_interrupt(InterruptIndex::Timer.into());
-    }
+    end_of_interrupt(InterruptIndex::Timer);
+}
+
+extern "C" fn spurious_handler(_stack_frame: &ExceptionStackFrame) {
+    // Spurious interrupts must not be acknowledged
 }
 
 extern "C" fn keyboard_handler(_stack_frame: &ExceptionStackFrame) {
-    use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, ScancodeSet1};
-    use spin::Mutex;
-    use x86_64::instructions::port::Port;
-
-    lazy_static::lazy_static! {
-        static ref KEYBOARD: Mutex<Keyboard<layouts::Us104Key, ScancodeSet1>> =
-            Mutex::new(Keyboard::new(
-                ScancodeSet1::new(),
-                layouts::Us104Key,
-                HandleControl::Ignore
-            ));
-    }
+    crate::keyboard::on_interrupt();
 
-    let mut keyboard = KEYBOARD.lock();
-    let mut port = Port::new(0x60);
+    end_of_interrupt(InterruptIndex::Keyboard);
+}
 
-    let scancode: u8 = unsafe { port.read() };
-    if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-        if let Some(key) = keyboard.process_keyevent(key_event) {
-            match key {
-                DecodedKey::Unicode(character) => crate::print!("{}", character),
-                DecodedKey::RawKey(key) => crate::print!("{:?}", key),
-            }
-        }
-    }
+// COM1 and COM3 share IRQ 4, COM2 and COM4 share IRQ 3
+extern "C" fn com1_handler(_stack_frame: &ExceptionStackFrame) {
+    crate::serial::on_interrupt(4);
+
+    end_of_interrupt(InterruptIndex::Com1);
+}
+
+extern "C" fn com2_handler(_stack_frame: &ExceptionStackFrame) {
+    crate::serial::on_interrupt(3);
 
-    unsafe {
-        pic::PICS
-            .lock()
-            .notify_end_of_interrupt(InterruptIndex::Keyboard.into());
+    end_of_interrupt(InterruptIndex::Com2);
+}
+
+extern "C" fn mouse_handler(_stack_frame: &ExceptionStackFrame) {
+    crate::ps2::mouse::on_interrupt();
+
+    end_of_interrupt(InterruptIndex::Mouse);
+}
+
+fn end_of_interrupt(index: InterruptIndex) {
+    if apic::is_enabled() {
+        apic::end_of_interrupt();
+    } else {
+        unsafe { pic::PICS.lock().notify_end_of_interrupt(index.into()) }
     }
 }
 
 pub fn init() {
     IDT.load();
-    unsafe { pic::PICS.lock().initialize() }
+    pic::init();
     x86_64::instructions::interrupts::enable();
 }
+
+// Switches interrupt delivery over to the local APIC and I/O APIC if the CPU
+// has one. Needs paging, since the APIC registers are memory mapped.
+// The 8259 PICs initialized in init() stay in charge otherwise.
+pub fn init_apic(mapper: &mut Mapper, allocator: &mut impl FrameAllocator) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        if !apic::init(mapper, allocator) {
+            log::warn!("no APIC found, using the 8259 PIC");
+        }
+    });
+}
+
+crate::test_cases! {
+    fn page_fault_is_caught() {
+        use x86_64::registers::control::Cr2;
+
+        const UNMAPPED: u64 = 0xdead_b000;
+        let error_code = crate::tests::expect_exception(InterruptType::PageFault, || unsafe {
+            core::ptr::write_volatile(UNMAPPED as *mut u64, 1);
+        });
+        let error_code = PageFaultErrorCode::from_bits_truncate(error_code.unwrap());
+        assert!(error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE));
+        assert!(!error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION));
+        assert_eq!(Cr2::read_raw(), UNMAPPED);
+    }
+
+    fn invalid_opcode_is_caught() {
+        let error_code = crate::tests::expect_exception(InterruptType::InvalidOpcode, || unsafe {
+            asm!("ud2");
+        });
+        assert_eq!(error_code, None);
+    }
+
+    #[should_panic]
+    fn missing_exception_fails() {
+        crate::tests::expect_exception(InterruptType::PageFault, || {});
+    }
+
+    #[interrupts]
+    fn timer_interrupts_arrive() {
+        crate::tests::expect_ticks(10, core::time::Duration::from_secs(1));
+    }
+}
@@ -7,11 +7,27 @@ pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
 pub static PICS: Mutex<ChainedPics> =
     Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
 
+// Interrupt lines the kernel has drivers for, every other one stays masked
+const PIC_1_MASK: u8 = !(1 << 0 | 1 << 1 | 1 << 2 | 1 << 3 | 1 << 4);
+const PIC_2_MASK: u8 = !(1 << 4);
+
+pub fn init() {
+    let mut pics = PICS.lock();
+    unsafe {
+        pics.initialize();
+        // IRQ 2 is the cascade from the second PIC, needed for the mouse on IRQ 12
+        pics.write_masks(PIC_1_MASK, PIC_2_MASK);
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 #[repr(u8)]
 pub enum InterruptIndex {
     Timer = PIC_1_OFFSET,
     Keyboard,
+    Com2 = PIC_1_OFFSET + 3,
+    Com1 = PIC_1_OFFSET + 4,
+    Mouse = PIC_1_OFFSET + 12,
 }
 
 impl Into<u8> for InterruptIndex {
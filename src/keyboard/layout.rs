@@ -0,0 +1,160 @@
+use pc_keyboard::{
+    layouts, DecodedKey, Error, HandleControl, KeyCode, KeyEvent, KeyboardLayout, Modifiers,
+    ScancodeSet1, ScancodeSet2,
+};
+
+/// The keyboard layouts from `pc_keyboard`, selectable at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Us,
+    Uk,
+    De,
+    Fr,
+    Dvorak,
+    ProgrammerDvorak,
+    Colemak,
+    Jp,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 8] = [
+        Layout::Us,
+        Layout::Uk,
+        Layout::De,
+        Layout::Fr,
+        Layout::Dvorak,
+        Layout::ProgrammerDvorak,
+        Layout::Colemak,
+        Layout::Jp,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Us => "us",
+            Layout::Uk => "uk",
+            Layout::De => "de",
+            Layout::Fr => "fr",
+            Layout::Dvorak => "dvorak",
+            Layout::ProgrammerDvorak => "dvp",
+            Layout::Colemak => "colemak",
+            Layout::Jp => "jp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layout| layout.name() == name)
+    }
+}
+
+impl KeyboardLayout for Layout {
+    fn map_keycode(
+        &self,
+        keycode: KeyCode,
+        modifiers: &Modifiers,
+        handle_ctrl: HandleControl,
+    ) -> DecodedKey {
+        match self {
+            Layout::Us => layouts::Us104Key.map_keycode(keycode, modifiers, handle_ctrl),
+            Layout::Uk => layouts::Uk105Key.map_keycode(keycode, modifiers, handle_ctrl),
+            Layout::De => layouts::De105Key.map_keycode(keycode, modifiers, handle_ctrl),
+            Layout::Fr => layouts::Azerty.map_keycode(keycode, modifiers, handle_ctrl),
+            Layout::Dvorak => layouts::Dvorak104Key.map_keycode(keycode, modifiers, handle_ctrl),
+            Layout::ProgrammerDvorak => {
+                layouts::DVP104Key.map_keycode(keycode, modifiers, handle_ctrl)
+            }
+            Layout::Colemak => layouts::Colemak.map_keycode(keycode, modifiers, handle_ctrl),
+            Layout::Jp => layouts::Jis109Key.map_keycode(keycode, modifiers, handle_ctrl),
+        }
+    }
+}
+
+/// The scancode set the decoder expects from the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScancodeSet {
+    /// Set 2 translated to set 1 by the controller, the usual setup
+    One,
+    /// Untranslated set 2, as sent by the keyboard itself
+    Two,
+}
+
+impl ScancodeSet {
+    pub fn name(self) -> &'static str {
+        match self {
+            ScancodeSet::One => "1",
+            ScancodeSet::Two => "2",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "1" => Some(ScancodeSet::One),
+            "2" => Some(ScancodeSet::Two),
+            _ => None,
+        }
+    }
+}
+
+pub fn control_name(control: HandleControl) -> &'static str {
+    match control {
+        HandleControl::Ignore => "ignore",
+        HandleControl::MapLettersToUnicode => "map",
+    }
+}
+
+pub fn control_from_name(name: &str) -> Option<HandleControl> {
+    match name {
+        "ignore" => Some(HandleControl::Ignore),
+        "map" => Some(HandleControl::MapLettersToUnicode),
+        _ => None,
+    }
+}
+
+// Decoder state for whichever scancode set is selected
+pub(super) enum AnyScancodeSet {
+    One(ScancodeSet1),
+    Two(ScancodeSet2),
+}
+
+impl AnyScancodeSet {
+    pub(super) fn new(set: ScancodeSet) -> Self {
+        match set {
+            ScancodeSet::One => AnyScancodeSet::One(ScancodeSet1::new()),
+            ScancodeSet::Two => AnyScancodeSet::Two(ScancodeSet2::new()),
+        }
+    }
+}
+
+impl pc_keyboard::ScancodeSet for AnyScancodeSet {
+    fn advance_state(&mut self, code: u8) -> Result<Option<KeyEvent>, Error> {
+        match self {
+            AnyScancodeSet::One(set) => set.advance_state(code),
+            AnyScancodeSet::Two(set) => set.advance_state(code),
+        }
+    }
+}
+
+crate::test_cases! {
+    fn keyboard_layout_names() {
+        for layout in Layout::ALL {
+            assert_eq!(Layout::from_name(layout.name()), Some(layout));
+        }
+        assert_eq!(Layout::from_name("klingon"), None);
+        assert_eq!(ScancodeSet::from_name("2"), Some(ScancodeSet::Two));
+        assert_eq!(control_from_name("map"), Some(HandleControl::MapLettersToUnicode));
+    }
+
+    fn keyboard_layout_decodes() {
+        use pc_keyboard::Keyboard;
+
+        // The key labelled Y on a US keyboard (set 1 make code 0x15) is Z on a German one
+        for (layout, expected) in [(Layout::Us, 'y'), (Layout::De, 'z')] {
+            let mut keyboard = Keyboard::new(
+                AnyScancodeSet::new(ScancodeSet::One),
+                layout,
+                HandleControl::Ignore,
+            );
+            let event = keyboard.add_byte(0x15).unwrap().unwrap();
+            assert_eq!(keyboard.process_keyevent(event), Some(DecodedKey::Unicode(expected)));
+        }
+    }
+}
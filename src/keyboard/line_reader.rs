@@ -0,0 +1,263 @@
+use alloc::{string::String, vec::Vec};
+
+use pc_keyboard::{DecodedKey, KeyCode};
+use x86_64::instructions::interrupts;
+
+use crate::framebuffer::WRITER;
+
+const MAX_HISTORY: usize = 32;
+
+const BACKSPACE: char = '\u{8}';
+const DELETE: char = '\u{7f}';
+
+/// Displays the line while it is being edited
+pub trait LineEcho {
+    /// Called once when reading starts
+    fn start(&mut self, prompt: &str);
+    /// Redraws `line` with the cursor before the `cursor`-th character.
+    /// `previous_len` is the length of the line that is currently displayed.
+    fn redraw(&mut self, line: &[char], cursor: usize, previous_len: usize);
+    /// Called once the line is complete
+    fn finish(&mut self, line: &[char]);
+}
+
+// Echoes to two places at once, e.g. the screen and a serial terminal
+impl<A: LineEcho, B: LineEcho> LineEcho for (A, B) {
+    fn start(&mut self, prompt: &str) {
+        self.0.start(prompt);
+        self.1.start(prompt);
+    }
+
+    fn redraw(&mut self, line: &[char], cursor: usize, previous_len: usize) {
+        self.0.redraw(line, cursor, previous_len);
+        self.1.redraw(line, cursor, previous_len);
+    }
+
+    fn finish(&mut self, line: &[char]) {
+        self.0.finish(line);
+        self.1.finish(line);
+    }
+}
+
+/// Echoes the line through the framebuffer writer
+pub struct FrameBufferEcho {
+    start: (usize, usize),
+}
+
+impl FrameBufferEcho {
+    pub fn new() -> Self {
+        Self { start: (0, 0) }
+    }
+}
+
+impl Default for FrameBufferEcho {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineEcho for FrameBufferEcho {
+    fn start(&mut self, prompt: &str) {
+        interrupts::without_interrupts(|| {
+            let mut writer = WRITER.lock();
+            writer.write(prompt);
+            self.start = writer.position();
+            writer.draw_cursor();
+        });
+    }
+
+    fn redraw(&mut self, line: &[char], cursor: usize, previous_len: usize) {
+        interrupts::without_interrupts(|| {
+            let mut writer = WRITER.lock();
+            writer.set_position(self.start.0, self.start.1);
+
+            for c in &line[..cursor] {
+                writer.write_char(*c);
+            }
+            let (cursor_x, cursor_y) = writer.position();
+            for c in &line[cursor..] {
+                writer.write_char(*c);
+            }
+            // Blank out what is left of a longer previous line, and the old cursor
+            for _ in line.len()..previous_len + 1 {
+                writer.write_char(' ');
+            }
+
+            writer.set_position(cursor_x, cursor_y);
+            writer.draw_cursor();
+        });
+    }
+
+    fn finish(&mut self, line: &[char]) {
+        interrupts::without_interrupts(|| {
+            let mut writer = WRITER.lock();
+            writer.set_position(self.start.0, self.start.1);
+            for c in line {
+                writer.write_char(*c);
+            }
+            // Overdraw the cursor, in case it was at the end of the line
+            writer.write_char(' ');
+            writer.write_char('\n');
+        });
+    }
+}
+
+/// Reads lines of input with basic editing (backspace, delete, cursor
+/// movement with the arrow keys, home and end) and a history of previous
+/// lines on the up and down keys
+pub struct LineReader {
+    history: Vec<String>,
+}
+
+impl LineReader {
+    pub fn new() -> Self {
+        Self {
+            history: Vec::new(),
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Reads a line from the keyboard, echoing it to the framebuffer
+    pub fn read_line(&mut self, prompt: &str) -> String {
+        self.read_line_with(prompt, &mut FrameBufferEcho::new(), super::read_key)
+    }
+
+    pub fn read_line_with(
+        &mut self,
+        prompt: &str,
+        echo: &mut impl LineEcho,
+        mut next_key: impl FnMut() -> DecodedKey,
+    ) -> String {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // Position while browsing the history, and the line that was being
+        // typed before browsing started
+        let mut history_index = self.history.len();
+        let mut draft: Vec<char> = Vec::new();
+
+        echo.start(prompt);
+
+        loop {
+            let previous_len = line.len();
+            match next_key() {
+                DecodedKey::Unicode('\n') | DecodedKey::Unicode('\r') => break,
+                DecodedKey::Unicode(BACKSPACE) => {
+                    if cursor > 0 {
+                        cursor -= 1;
+                        line.remove(cursor);
+                    }
+                }
+                DecodedKey::Unicode(DELETE) | DecodedKey::RawKey(KeyCode::Delete) => {
+                    if cursor < line.len() {
+                        line.remove(cursor);
+                    }
+                }
+                DecodedKey::Unicode(c) if !c.is_control() => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                DecodedKey::RawKey(KeyCode::ArrowLeft) => cursor = cursor.saturating_sub(1),
+                DecodedKey::RawKey(KeyCode::ArrowRight) => cursor = (cursor + 1).min(line.len()),
+                DecodedKey::RawKey(KeyCode::Home) => cursor = 0,
+                DecodedKey::RawKey(KeyCode::End) => cursor = line.len(),
+                DecodedKey::RawKey(KeyCode::ArrowUp) => {
+                    if history_index == 0 {
+                        continue;
+                    }
+                    if history_index == self.history.len() {
+                        draft = line.clone();
+                    }
+                    history_index -= 1;
+                    line = self.history[history_index].chars().collect();
+                    cursor = line.len();
+                }
+                DecodedKey::RawKey(KeyCode::ArrowDown) => {
+                    if history_index == self.history.len() {
+                        continue;
+                    }
+                    history_index += 1;
+                    line = match self.history.get(history_index) {
+                        Some(entry) => entry.chars().collect(),
+                        None => draft.clone(),
+                    };
+                    cursor = line.len();
+                }
+                _ => continue,
+            }
+            echo.redraw(&line, cursor, previous_len);
+        }
+
+        echo.finish(&line);
+
+        let line: String = line.into_iter().collect();
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            if self.history.len() == MAX_HISTORY {
+                self.history.remove(0);
+            }
+            self.history.push(line.clone());
+        }
+        line
+    }
+}
+
+impl Default for LineReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+crate::test_cases! {
+    fn line_reader_editing() {
+        struct NoEcho;
+        impl LineEcho for NoEcho {
+            fn start(&mut self, _prompt: &str) {}
+            fn redraw(&mut self, _line: &[char], _cursor: usize, _previous_len: usize) {}
+            fn finish(&mut self, _line: &[char]) {}
+        }
+
+        fn read(reader: &mut LineReader, keys: &[DecodedKey]) -> String {
+            let mut keys = keys.iter().copied();
+            reader.read_line_with("> ", &mut NoEcho, || keys.next().unwrap())
+        }
+
+        let mut reader = LineReader::new();
+
+        let keys = [
+            DecodedKey::Unicode('a'),
+            DecodedKey::Unicode('b'),
+            DecodedKey::RawKey(KeyCode::ArrowLeft),
+            DecodedKey::Unicode('c'),
+            DecodedKey::RawKey(KeyCode::Home),
+            DecodedKey::Unicode(DELETE),
+            DecodedKey::RawKey(KeyCode::End),
+            DecodedKey::Unicode('d'),
+            DecodedKey::Unicode(BACKSPACE),
+            DecodedKey::Unicode('\n'),
+        ];
+        assert_eq!(read(&mut reader, &keys), "cb");
+
+        // The previous line comes back from the history, and can be edited
+        let keys = [
+            DecodedKey::Unicode('x'),
+            DecodedKey::RawKey(KeyCode::ArrowUp),
+            DecodedKey::Unicode('e'),
+            DecodedKey::Unicode('\n'),
+        ];
+        assert_eq!(read(&mut reader, &keys), "cbe");
+
+        // Going down past the newest entry restores the draft
+        let keys = [
+            DecodedKey::Unicode('y'),
+            DecodedKey::RawKey(KeyCode::ArrowUp),
+            DecodedKey::RawKey(KeyCode::ArrowUp),
+            DecodedKey::RawKey(KeyCode::ArrowDown),
+            DecodedKey::RawKey(KeyCode::ArrowDown),
+            DecodedKey::Unicode('\n'),
+        ];
+        assert_eq!(read(&mut reader, &keys), "y");
+        assert_eq!(reader.history().len(), 3);
+    }
+}
@@ -0,0 +1,401 @@
+pub mod layout;
+pub mod line_reader;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use layout::{AnyScancodeSet, Layout, ScancodeSet};
+use pc_keyboard::{DecodedKey, HandleControl, KeyCode, KeyEvent, KeyState, Keyboard};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::{
+    cmdline, println,
+    ps2::{self, Channel},
+    ring_buffer::RingBuffer,
+};
+
+const SET_LEDS: u8 = 0xED;
+
+// Bits of the SET_LEDS argument
+const LED_SCROLL_LOCK: u8 = 1 << 0;
+const LED_NUM_LOCK: u8 = 1 << 1;
+const LED_CAPS_LOCK: u8 = 1 << 2;
+
+/// Raw scancodes, pushed by the keyboard interrupt handler
+static SCANCODES: RingBuffer<u8, 256> = RingBuffer::new();
+static WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+
+/// How scancodes are turned into keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub layout: Layout,
+    pub scancode_set: ScancodeSet,
+    pub control: HandleControl,
+}
+
+impl Config {
+    const DEFAULT: Config = Config {
+        layout: Layout::Us,
+        scancode_set: ScancodeSet::One,
+        control: HandleControl::Ignore,
+    };
+}
+
+/// State of the modifier and lock keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub alt_gr: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl Modifiers {
+    fn leds(&self) -> u8 {
+        let mut leds = 0;
+        if self.scroll_lock {
+            leds |= LED_SCROLL_LOCK;
+        }
+        if self.num_lock {
+            leds |= LED_NUM_LOCK;
+        }
+        if self.caps_lock {
+            leds |= LED_CAPS_LOCK;
+        }
+        leds
+    }
+}
+
+struct State {
+    decoder: Keyboard<Layout, AnyScancodeSet>,
+    config: Config,
+    // pc_keyboard keeps its modifier state private, so it is tracked here from
+    // the same key events
+    modifiers: Modifiers,
+    // The left and right keys, so releasing one doesn't clear the other
+    shift: [bool; 2],
+    ctrl: [bool; 2],
+    // The hidden control key Pause sends before Num Lock, which then doesn't
+    // toggle num lock
+    pause: bool,
+    // What the LEDs currently show, so they are only updated on changes
+    leds: Option<u8>,
+}
+
+impl State {
+    fn new(config: Config) -> Self {
+        Self {
+            decoder: Keyboard::new(
+                AnyScancodeSet::new(config.scancode_set),
+                config.layout,
+                config.control,
+            ),
+            config,
+            // Num lock starts on, as in pc_keyboard
+            modifiers: Modifiers {
+                num_lock: true,
+                ..Modifiers::default()
+            },
+            shift: [false; 2],
+            ctrl: [false; 2],
+            pause: false,
+            leds: None,
+        }
+    }
+
+    // Updates the modifiers the way the decoder does, before it sees `event`
+    fn track(&mut self, event: &KeyEvent) {
+        let down = event.state == KeyState::Down;
+        let modifiers = &mut self.modifiers;
+        match event.code {
+            KeyCode::LShift => self.shift[0] = down,
+            KeyCode::RShift => self.shift[1] = down,
+            KeyCode::LControl => self.ctrl[0] = down,
+            KeyCode::RControl => self.ctrl[1] = down,
+            KeyCode::RControl2 => self.pause = down,
+            KeyCode::LAlt => modifiers.alt = down,
+            KeyCode::RAltGr => modifiers.alt_gr = down,
+            KeyCode::CapsLock if down => modifiers.caps_lock = !modifiers.caps_lock,
+            KeyCode::NumpadLock if down && !self.pause => modifiers.num_lock = !modifiers.num_lock,
+            KeyCode::ScrollLock if down => modifiers.scroll_lock = !modifiers.scroll_lock,
+            _ => {}
+        }
+        modifiers.shift = self.shift[0] || self.shift[1];
+        modifiers.ctrl = self.ctrl[0] || self.ctrl[1];
+    }
+
+    fn update_leds(&mut self) {
+        let leds = self.modifiers.leds();
+        if self.leds == Some(leds) || !ps2::is_present(Channel::First) {
+            return;
+        }
+        if ps2::command(Channel::First, SET_LEDS, Some(leds)).is_ok() {
+            self.leds = Some(leds);
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref KEYBOARD: Mutex<State> = Mutex::new(State::new(Config::DEFAULT));
+}
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static LAYOUT_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "keyboard.layout",
+    kind: crate::cmdline::Kind::Text,
+    help: "keyboard layout: us, uk, de, fr, dvorak, dvp, colemak or jp",
+};
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static SCANCODES_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "keyboard.scancodes",
+    kind: crate::cmdline::Kind::Choice(&["1", "2"]),
+    help: "scancode set the keyboard sends",
+};
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static CTRL_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "keyboard.ctrl",
+    kind: crate::cmdline::Kind::Choice(&["ignore", "map"]),
+    help: "whether ctrl+letter gives control characters (map) or letters (ignore)",
+};
+
+/// Applies the `keyboard.layout`, `keyboard.scancodes` and `keyboard.ctrl`
+/// command line options and syncs the LEDs with the lock key state
+pub fn init() {
+    let mut config = Config::DEFAULT;
+
+    if let Some(name) = cmdline::get("keyboard.layout") {
+        match Layout::from_name(name) {
+            Some(layout) => config.layout = layout,
+            None => log::warn!("unknown layout {:?}", name),
+        }
+    }
+    // `cmdline::init` has warned about unknown values already
+    if let Some(set) = cmdline::get("keyboard.scancodes").and_then(ScancodeSet::from_name) {
+        config.scancode_set = set;
+    }
+    if let Some(control) = cmdline::get("keyboard.ctrl").and_then(layout::control_from_name) {
+        config.control = control;
+    }
+
+    configure(config);
+}
+
+pub fn config() -> Config {
+    interrupts::without_interrupts(|| KEYBOARD.lock().config)
+}
+
+/// Switches layout, scancode set and control handling. Modifier and lock
+/// state is reset, since keys held down can't be tracked across the switch.
+pub fn configure(config: Config) {
+    interrupts::without_interrupts(|| {
+        let mut keyboard = KEYBOARD.lock();
+        if keyboard.config.scancode_set != config.scancode_set || keyboard.leds.is_none() {
+            // A missing controller has already been reported by ps2::init
+            let _ = ps2::set_translation(config.scancode_set == ScancodeSet::One);
+        }
+        // Drop anything that was queued in the old scancode set
+        while SCANCODES.pop().is_some() {}
+
+        *keyboard = State::new(config);
+        keyboard.update_leds();
+    });
+}
+
+pub fn set_layout(layout: Layout) {
+    configure(Config { layout, ..config() });
+}
+
+pub fn set_scancode_set(scancode_set: ScancodeSet) {
+    configure(Config {
+        scancode_set,
+        ..config()
+    });
+}
+
+pub fn set_control_handling(control: HandleControl) {
+    configure(Config {
+        control,
+        ..config()
+    });
+}
+
+pub fn modifiers() -> Modifiers {
+    interrupts::without_interrupts(|| KEYBOARD.lock().modifiers)
+}
+
+// Called from the keyboard interrupt handler, only queues the scancode so
+// decoding and everything else happens outside of interrupt context
+pub(crate) fn on_interrupt() {
+    let Some(scancode) = ps2::read_interrupt_byte() else {
+        return;
+    };
+    // Late command responses aren't key presses
+    if scancode == ps2::ACK || scancode == ps2::RESEND {
+        return;
+    }
+    push_scancode(scancode);
+}
+
+/// Queues a scancode as if the keyboard had sent it, e.g. to test the input
+/// path with synthetic key presses
+pub fn push_scancode(scancode: u8) {
+    // The queue takes one producer at a time, so the interrupt handler must not
+    // push in between
+    let pushed = interrupts::without_interrupts(|| SCANCODES.push(scancode));
+    if pushed.is_err() {
+        // Nobody is reading the keyboard, drop the key press
+        return;
+    }
+    // The consumer may hold the lock, it will see the scancode on its next poll
+    if let Some(mut waker) = WAKER.try_lock() {
+        if let Some(waker) = waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Returns the next key press or release, if one is queued
+pub fn poll_event() -> Option<KeyEvent> {
+    interrupts::without_interrupts(|| {
+        let mut keyboard = KEYBOARD.lock();
+        while let Some(scancode) = SCANCODES.pop() {
+            if let Ok(Some(event)) = keyboard.decoder.add_byte(scancode) {
+                return Some(event);
+            }
+        }
+        None
+    })
+}
+
+/// Returns the next decoded key, if one is queued. Modifier keys are consumed
+/// and only update the keyboard state (and LEDs).
+pub fn poll_key() -> Option<DecodedKey> {
+    while let Some(event) = poll_event() {
+        let key = interrupts::without_interrupts(|| {
+            let mut keyboard = KEYBOARD.lock();
+            keyboard.track(&event);
+            let key = keyboard.decoder.process_keyevent(event);
+            keyboard.update_leds();
+            key
+        });
+        if key.is_some() {
+            return key;
+        }
+    }
+    None
+}
+
+/// Halts until a key is pressed. Needs interrupts to be enabled.
+pub fn read_key() -> DecodedKey {
+    loop {
+        // Check with interrupts disabled, so a key arriving between the check
+        // and the hlt wakes us up instead of being missed
+        interrupts::disable();
+        if let Some(key) = poll_key() {
+            interrupts::enable();
+            return key;
+        }
+        interrupts::enable_and_hlt();
+    }
+}
+
+/// A future resolving to the next decoded key, for use by an async executor
+pub fn next_key() -> NextKey {
+    NextKey
+}
+
+pub struct NextKey;
+
+impl Future for NextKey {
+    type Output = DecodedKey;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<DecodedKey> {
+        if let Some(key) = poll_key() {
+            return Poll::Ready(key);
+        }
+
+        interrupts::without_interrupts(|| {
+            *WAKER.lock() = Some(context.waker().clone());
+        });
+
+        // A key may have arrived before the waker was registered
+        match poll_key() {
+            Some(key) => {
+                interrupts::without_interrupts(|| WAKER.lock().take());
+                Poll::Ready(key)
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static KEYBOARD_COMMAND: crate::shell::Command = crate::shell::Command {
+    name: "keyboard",
+    help: "show or change the keyboard settings: keyboard [layout|scancodes|ctrl <value>]",
+    run: keyboard,
+};
+
+fn keyboard(args: &[&str]) {
+    match args {
+        [] => {
+            let config = config();
+            let modifiers = modifiers();
+            println!(
+                "layout {}, scancode set {}, ctrl {}",
+                config.layout.name(),
+                config.scancode_set.name(),
+                layout::control_name(config.control)
+            );
+            println!(
+                "caps lock {}, num lock {}, scroll lock {}",
+                modifiers.caps_lock, modifiers.num_lock, modifiers.scroll_lock
+            );
+        }
+        ["layout", name] => match Layout::from_name(name) {
+            Some(layout) => set_layout(layout),
+            None => {
+                println!("unknown layout {}, available layouts:", name);
+                for layout in Layout::ALL {
+                    println!("  {}", layout.name());
+                }
+            }
+        },
+        ["scancodes", name] => match ScancodeSet::from_name(name) {
+            Some(set) => set_scancode_set(set),
+            None => println!("unknown scancode set {}, expected 1 or 2", name),
+        },
+        ["ctrl", name] => match layout::control_from_name(name) {
+            Some(control) => set_control_handling(control),
+            None => println!("unknown control handling {}, expected ignore or map", name),
+        },
+        _ => println!("usage: keyboard [layout|scancodes|ctrl <value>]"),
+    }
+}
+
+crate::test_cases! {
+    #[interrupts]
+    fn synthetic_key_press() {
+        let previous = config();
+        configure(Config::DEFAULT);
+
+        // Typed from the timer interrupt, while `read_key` halts
+        crate::timer::schedule_once(core::time::Duration::from_millis(5), || {
+            // `A` pressed and released, in scancode set 1
+            push_scancode(0x1e);
+            push_scancode(0x9e);
+        });
+        assert_eq!(read_key(), DecodedKey::Unicode('a'));
+
+        configure(previous);
+    }
+}
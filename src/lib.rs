@@ -13,12 +13,27 @@
 #[macro_use]
 extern crate alloc;
 
+pub mod acpi;
+pub mod ata;
+pub mod backtrace;
+pub mod bench;
+pub mod clock;
+pub mod cmdline;
 pub mod framebuffer;
+pub mod fs;
 pub mod heap;
+pub mod initrd;
 pub mod interrupts;
+pub mod keyboard;
 pub mod memory;
 pub mod paging;
 pub mod panic;
+pub mod power;
+pub mod ps2;
+pub mod ring_buffer;
+pub mod serial;
+pub mod shell;
+pub mod timer;
 
 #[macro_use]
 pub mod logger;
@@ -33,6 +48,9 @@ use x86_64::instructions::hlt;
 
 #[no_mangle]
 pub extern "C" fn rust_main(multiboot_info_ptr: usize) {
+    // Route log messages to the serial port until the framebuffer is up
+    logger::init();
+
     // Parse the multiboot information header passed by grub
     let boot_info = unsafe {
         multiboot2::BootInformation::load(multiboot_info_ptr as *const BootInformationHeader)
@@ -47,16 +65,61 @@ pub extern "C" fn rust_main(multiboot_info_ptr: usize) {
     let mut active_page_table = paging::init(&mut frame_allocator, &boot_info);
     heap::init(&mut active_page_table, &mut frame_allocator);
 
+    // Find the kernel's symbols, to name the functions in backtraces
+    backtrace::symbols::init(&boot_info);
+
+    // Keep a copy of the kernel command line for drivers to read their options from
+    cmdline::init(&boot_info);
+    logger::init_filter();
+
+    // Find the boot modules and the initrd, which `memory::init` reserved
+    initrd::init(&boot_info);
+
+    // Find the hard disks on the IDE ports, for fs::init to mount
+    ata::init();
+
+    // Mount the root tmpfs, the initrd at /initrd and the FAT disks under /mnt
+    fs::init();
+
+    // Apply the serial port settings and start receiving
+    serial::init();
+
+    // Parse the ACPI tables GRUB found for us
+    if let Err(error) = acpi::init(&boot_info, &mut active_page_table, &mut frame_allocator) {
+        log::error!("ACPI initialization failed: {:?}", error);
+    }
+
+    // Hand interrupt delivery over to the APIC, if there is one
+    interrupts::init_apic(&mut active_page_table, &mut frame_allocator);
+
+    // Program the timer interrupt to a known frequency
+    timer::init();
+    timer::hpet::init(&mut active_page_table, &mut frame_allocator);
+
+    // Calibrate the TSC for high resolution timestamps
+    clock::init();
+
     // Initialize frame buffer
     framebuffer::init(&boot_info);
     framebuffer::fill_bg();
+    // Show what was logged before the screen was available
+    logger::replay_to_framebuffer();
+
+    // Reset the PS/2 devices, then apply the keyboard layout from the command line.
+    // After the framebuffer, since the mouse cursor is drawn on it.
+    ps2::init();
+    keyboard::init();
 
     #[cfg(testing)]
-    tests::test_runner();
+    if cmdline::flag("bench") {
+        bench::bench_runner();
+    } else {
+        tests::test_runner();
+    }
 
     println!("It did not crash");
 
-    hlt_loop();
+    shell::run();
 }
 
 fn hlt_loop() -> ! {
@@ -0,0 +1,212 @@
+use core::fmt;
+
+use spin::Mutex;
+
+/// Size of the kernel log, older messages are overwritten
+pub const DMESG_SIZE: usize = 64 * 1024;
+
+/// Everything printed or logged since boot, kept in a static so it works
+/// before the heap and the framebuffer exist
+pub static DMESG: Mutex<LogBuffer<DMESG_SIZE>> = Mutex::new(LogBuffer::new());
+
+/// A fixed size text buffer that overwrites the oldest bytes when full
+pub struct LogBuffer<const N: usize> {
+    data: [u8; N],
+    // Index of the oldest byte
+    start: usize,
+    len: usize,
+    // Whether the oldest line was partially overwritten
+    partial_first_line: bool,
+}
+
+impl<const N: usize> LogBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            data: [0; N],
+            start: 0,
+            len: 0,
+            partial_first_line: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+        self.partial_first_line = false;
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.len == N {
+                // Drop the oldest byte to make room
+                self.partial_first_line = self.data[self.start] != b'\n';
+                self.data[self.start] = byte;
+                self.start = (self.start + 1) % N;
+            } else {
+                self.data[(self.start + self.len) % N] = byte;
+                self.len += 1;
+            }
+        }
+    }
+
+    /// The contents in order, as two slices since they may wrap around
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        let end = self.start + self.len;
+        if end <= N {
+            (&self.data[self.start..end], &[])
+        } else {
+            (&self.data[self.start..], &self.data[..end - N])
+        }
+    }
+
+    fn byte(&self, index: usize) -> u8 {
+        self.data[(self.start + index) % N]
+    }
+
+    // Skips what is left of a partially overwritten line
+    fn first_line_start(&self) -> usize {
+        if !self.partial_first_line {
+            return 0;
+        }
+        (0..self.len)
+            .find(|&index| self.byte(index) == b'\n')
+            .map_or(self.len, |index| index + 1)
+    }
+
+    /// Writes the contents to `writer`, starting at the `lines`-th line from the end
+    pub fn dump_tail(&self, lines: usize, writer: &mut impl fmt::Write) -> fmt::Result {
+        if lines == 0 {
+            return Ok(());
+        }
+        let mut start = self.first_line_start();
+        // A trailing newline doesn't start another line
+        let mut remaining = lines + usize::from(self.len > 0 && self.byte(self.len - 1) == b'\n');
+        for index in (start..self.len).rev() {
+            if self.byte(index) == b'\n' {
+                remaining -= 1;
+                if remaining == 0 {
+                    start = index + 1;
+                    break;
+                }
+            }
+        }
+        self.dump_from(start, writer)
+    }
+
+    /// Writes all complete lines to `writer`
+    pub fn dump(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        self.dump_from(self.first_line_start(), writer)
+    }
+
+    fn dump_from(&self, offset: usize, writer: &mut impl fmt::Write) -> fmt::Result {
+        let (first, second) = self.as_slices();
+        let (first, second) = if offset < first.len() {
+            (&first[offset..], second)
+        } else {
+            (&[][..], &second[offset - first.len()..])
+        };
+        for slice in [first, second] {
+            // A character may be split between the two halves, or cut off by wrapping
+            for chunk in slice.utf8_chunks() {
+                writer.write_str(chunk.valid())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for LogBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Write for LogBuffer<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Appends to the kernel log. Dropped if the log is locked by the code this
+/// interrupted, which would otherwise deadlock.
+pub fn record(args: fmt::Arguments) {
+    use fmt::Write;
+    if let Some(mut dmesg) = DMESG.try_lock() {
+        let _ = dmesg.write_fmt(args);
+    }
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static DMESG_COMMAND: crate::shell::Command = crate::shell::Command {
+    name: "dmesg",
+    help: "print the kernel log: dmesg [lines] | dmesg clear",
+    run: dmesg,
+};
+
+fn dmesg(args: &[&str]) {
+    use alloc::string::String;
+
+    // Copied out first, printing appends to the log itself
+    let mut text = String::new();
+    let result = match args {
+        [] => DMESG.lock().dump(&mut text),
+        ["clear"] => {
+            DMESG.lock().clear();
+            return;
+        }
+        [lines] => match lines.parse() {
+            Ok(lines) => DMESG.lock().dump_tail(lines, &mut text),
+            Err(_) => {
+                crate::println!("usage: dmesg [lines] | dmesg clear");
+                return;
+            }
+        },
+        _ => {
+            crate::println!("usage: dmesg [lines] | dmesg clear");
+            return;
+        }
+    };
+    if result.is_ok() {
+        crate::print!("{}", text);
+    }
+}
+
+crate::test_cases! {
+    fn log_buffer_wraps_around() {
+        use alloc::string::String;
+
+        let mut buffer: LogBuffer<16> = LogBuffer::new();
+        let dump = |buffer: &LogBuffer<16>, lines: Option<usize>| {
+            let mut text = String::new();
+            match lines {
+                Some(lines) => buffer.dump_tail(lines, &mut text).unwrap(),
+                None => buffer.dump(&mut text).unwrap(),
+            }
+            text
+        };
+
+        buffer.write(b"one\ntwo\n");
+        assert_eq!(dump(&buffer, None), "one\ntwo\n");
+
+        // Drops "one", only its newline is left
+        buffer.write(b"three\nfour\n");
+        assert_eq!(buffer.len(), 16);
+        assert_eq!(dump(&buffer, None), "two\nthree\nfour\n");
+        assert_eq!(dump(&buffer, Some(1)), "four\n");
+        assert_eq!(dump(&buffer, Some(2)), "three\nfour\n");
+
+        // Cuts "three" in half, the rest of it is skipped
+        buffer.write(b"five\nsix");
+        assert_eq!(dump(&buffer, None), "four\nfive\nsix");
+        assert_eq!(dump(&buffer, Some(1)), "six");
+    }
+}
@@ -0,0 +1,84 @@
+use alloc::{string::String, vec::Vec};
+use core::str::FromStr;
+
+use log::{LevelFilter, ParseLevelError};
+
+/// Per-module log levels, parsed from directives like
+/// `info,paging=debug,os::interrupts::idt=trace`
+pub struct Filter {
+    default: LevelFilter,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    pub const fn new(default: LevelFilter) -> Self {
+        Self {
+            default,
+            directives: Vec::new(),
+        }
+    }
+
+    /// Comma separated directives, either a level on its own to set the
+    /// default, or `module=level`. Module paths may leave out the crate name.
+    pub fn parse(spec: &str) -> Result<Self, ParseLevelError> {
+        let mut filter = Filter::new(LevelFilter::Info);
+        for directive in spec.split(',').filter(|directive| !directive.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let level = LevelFilter::from_str(level)?;
+                    filter.directives.push((String::from(module), level));
+                }
+                None => filter.default = LevelFilter::from_str(directive)?,
+            }
+        }
+        Ok(filter)
+    }
+
+    /// The level for `target`, from the most specific matching directive
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        // Module paths start with the crate name, directives don't have to
+        let short_target = target.split_once("::").map(|(_, rest)| rest);
+        self.directives
+            .iter()
+            .filter(|(module, _)| {
+                matches(module, target) || short_target.is_some_and(|short| matches(module, short))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// The most verbose level any module is logged at
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+// Whether `target` is `module` or one of its submodules
+fn matches(module: &str, target: &str) -> bool {
+    match target.strip_prefix(module) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+crate::test_cases! {
+    fn log_filter_directives() {
+        let filter = Filter::parse("warn,paging=debug,os::paging::mapper=trace,acpi=off").unwrap();
+        assert_eq!(filter.level_for("os::clock"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("os::paging"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("os::paging::entry"), LevelFilter::Debug);
+        // The longest match wins
+        assert_eq!(filter.level_for("os::paging::mapper"), LevelFilter::Trace);
+        // Only whole path components match
+        assert_eq!(filter.level_for("os::pagingx"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("os::acpi::madt"), LevelFilter::Off);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+
+        assert!(Filter::parse("paging=loud").is_err());
+        assert_eq!(Filter::parse("").unwrap().level_for("os"), LevelFilter::Info);
+    }
+}
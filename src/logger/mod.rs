@@ -0,0 +1,322 @@
+pub mod dmesg;
+pub mod filter;
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use filter::Filter;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::{
+    clock, cmdline,
+    framebuffer::{self, color::Color},
+    println,
+    serial::{self, console, ComPort},
+};
+
+static LOGGER: KernelLogger = KernelLogger;
+static FILTER: Mutex<Filter> = Mutex::new(Filter::new(LevelFilter::Info));
+
+// Set while printing or logging. Printing again before it's done, e.g. from an
+// exception handler or a `Display` impl, goes straight to the serial port. Only
+// the boot CPU runs kernel code, so one flag is enough for now.
+static IN_LOGGER: AtomicBool = AtomicBool::new(false);
+
+// Runs `print` with interrupts disabled and the re-entrancy flag set, or
+// `fallback` if the flag is already set
+fn guarded(print: impl FnOnce(), fallback: impl FnOnce()) {
+    interrupts::without_interrupts(|| {
+        if IN_LOGGER.swap(true, Ordering::Acquire) {
+            fallback();
+            return;
+        }
+        print();
+        IN_LOGGER.store(false, Ordering::Release);
+    });
+}
+
+/// Sends `log` records to the serial port and, once it is up, the framebuffer,
+/// prefixed with the time since boot and the level
+struct KernelLogger;
+
+impl Log for KernelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // A record logged while the filter is being replaced only gets the global check
+        let level = match FILTER.try_lock() {
+            Some(filter) => filter.level_for(metadata.target()),
+            None => log::max_level(),
+        };
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let nanos = clock::now().as_nanos();
+        let (seconds, micros) = (nanos / 1_000_000_000, nanos % 1_000_000_000 / 1000);
+        let level = record.level();
+
+        let line = |color| Line {
+            seconds,
+            micros,
+            color,
+            record,
+        };
+        guarded(
+            || {
+                dmesg::record(format_args!("{}", line("")));
+                serial::write_fmt_nonblocking(
+                    ComPort::Com1,
+                    format_args!("{}", line(ansi_color(level))),
+                );
+                framebuffer::try_with_writer(|writer| {
+                    let foreground = writer.foreground();
+                    let _ = write!(writer, "[{:>5}.{:06}] ", seconds, micros);
+                    writer.set_foreground(framebuffer_color(level));
+                    let _ = write!(writer, "{:<5}", level);
+                    writer.set_foreground(foreground);
+                    let _ = writeln!(writer, " {}: {}", record.target(), record.args());
+                });
+            },
+            || serial::write_fmt_raw(ComPort::Com1, format_args!("{}", line(""))),
+        );
+    }
+
+    fn flush(&self) {}
+}
+
+// A record as printed to the serial port and kept in the kernel log, with the
+// level in the given ANSI color, if any
+struct Line<'a> {
+    seconds: u64,
+    micros: u64,
+    color: &'static str,
+    record: &'a Record<'a>,
+}
+
+impl fmt::Display for Line<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reset = if self.color.is_empty() { "" } else { "\x1b[0m" };
+        writeln!(
+            f,
+            "[{:>5}.{:06}] {}{:<5}{} {}: {}",
+            self.seconds,
+            self.micros,
+            self.color,
+            self.record.level(),
+            reset,
+            self.record.target(),
+            self.record.args()
+        )
+    }
+}
+
+fn ansi_color(level: Level) -> &'static str {
+    match level {
+        Level::Error => "\x1b[31m",
+        Level::Warn => "\x1b[33m",
+        Level::Info => "\x1b[32m",
+        Level::Debug => "\x1b[36m",
+        Level::Trace => "\x1b[90m",
+    }
+}
+
+// Picked to be readable on the red background
+fn framebuffer_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::hex(0xffff55),
+        Level::Warn => Color::hex(0xffaa00),
+        Level::Info => Color::hex(0x55ff55),
+        Level::Debug => Color::hex(0x55ffff),
+        Level::Trace => Color::hex(0xaaaaaa),
+    }
+}
+
+/// Prints everything logged so far to the framebuffer, which missed whatever
+/// happened before it was initialized
+pub fn replay_to_framebuffer() {
+    interrupts::without_interrupts(|| {
+        let dmesg = dmesg::DMESG.lock();
+        let mut writer = framebuffer::WRITER.lock();
+        let _ = dmesg.dump(&mut *writer);
+    });
+}
+
+/// Installs the kernel logger, logging at `info` until `init_filter` reads the
+/// command line. Works without the heap, so it can run first thing.
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Applies the `log=` command line option, e.g. `log=warn,paging=debug`
+pub fn init_filter() {
+    if let Some(spec) = cmdline::get("log") {
+        if let Err(error) = set_filter(spec) {
+            log::warn!("invalid log filter {:?}: {}", spec, error);
+        }
+    }
+}
+
+/// Replaces the log filter, see `Filter::parse` for the syntax
+pub fn set_filter(spec: &str) -> Result<(), log::ParseLevelError> {
+    let filter = Filter::parse(spec)?;
+    log::set_max_level(filter.max_level());
+    interrupts::without_interrupts(|| *FILTER.lock() = filter);
+    Ok(())
+}
+
+pub fn _print_framebuffer(args: fmt::Arguments) {
+    crate::framebuffer::WRITER
+        .lock()
+        .write_fmt(args)
+        .expect("Writing to framebuffer failed");
+}
+
+pub fn _print_serial(args: fmt::Arguments) {
+    serial::write_fmt_nonblocking(ComPort::Com1, args);
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    guarded(
+        || {
+            dmesg::record(args);
+
+            let printed = framebuffer::try_with_writer(|writer| {
+                let _ = writer.write_fmt(args);
+            });
+            if !printed {
+                // Before the framebuffer is initialized, or while it is busy
+                _print_serial(args);
+            } else if console::is_enabled() {
+                // Mirror the output to the terminal driving the kernel
+                console::write_fmt(args);
+            }
+        },
+        || serial::write_fmt_raw(ComPort::Com1, args),
+    );
+}
+
+/// Prints to both the serial port and the framebuffer, whether or not the
+/// serial console is on. For crash reports, which must reach whoever is watching.
+#[doc(hidden)]
+pub fn _eprint(args: fmt::Arguments) {
+    guarded(
+        || {
+            dmesg::record(args);
+            serial::write_fmt_nonblocking(ComPort::Com1, args);
+            framebuffer::try_with_writer(|writer| {
+                let _ = writer.write_fmt(args);
+            });
+        },
+        || serial::write_fmt_raw(ComPort::Com1, args),
+    );
+}
+
+/// Releases every lock on the printing path and clears the re-entrancy flag,
+/// so the panic handler can print even if it interrupted a print.
+///
+/// # Safety
+/// Whoever holds the locks must never run again.
+pub unsafe fn force_unlock() {
+    IN_LOGGER.store(false, Ordering::Relaxed);
+    dmesg::DMESG.force_unlock();
+    serial::force_unlock();
+    framebuffer::force_unlock();
+}
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static LOG_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "log",
+    kind: crate::cmdline::Kind::Text,
+    help: "log filter, e.g. log=warn,paging=debug",
+};
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static LOG: crate::shell::Command = crate::shell::Command {
+    name: "log",
+    help: "set the log filter, e.g. log info,paging=debug",
+    run: log_command,
+};
+
+fn log_command(args: &[&str]) {
+    match args {
+        [spec] => {
+            if let Err(error) = set_filter(spec) {
+                println!("invalid log filter {}: {}", spec, error);
+            }
+        }
+        _ => println!("usage: log <level>[,<module>=<level>...]"),
+    }
+}
+
+// Prints to the frame buffer, if it is availabe annd mapped
+// Else, falls back to serial output
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::logger::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
+
+// Prints to the serial port and the frame buffer, for errors
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ($crate::logger::_eprint(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    () => ($crate::eprint!("\n"));
+    ($($arg:tt)*) => ($crate::eprint!("{}\n", format_args!($($arg)*)));
+}
+
+// Prints to the host through the serial interface.
+#[macro_export]
+macro_rules! serial_print {
+    ($($arg:tt)*) => {
+        $crate::logger::_print_serial(format_args!($($arg)*));
+    };
+}
+
+// Prints to the host through the serial interface, appending a newline.
+#[macro_export]
+macro_rules! serial_println {
+    () => ($crate::serial_print!("\n"));
+    ($fmt:expr) => ($crate::serial_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::serial_print!(
+        concat!($fmt, "\n"), $($arg)*));
+}
+
+// crate::test_cases! {
+//     fn printing_to_vga() {
+//         println!("test_println_simple output");
+//     }
+//
+// fn chars_appearing_on_vga() {
+//     use core::fmt::Write;
+//     use ::x86_64::instructions::interrupts;
+//
+//     let s = "Some test string that fits on a single line";
+//     interrupts::without_interrupts(|| {
+//         let mut writer = WRITER.lock();
+//         writeln!(writer, "\n{}", s).expect("writeln failed");
+//         for (i, c) in s.chars().enumerate() {
+//             let screen_char = writer.buffer.chars[BUFFER_HEIGHT - 2][i].read();
+//             assert_eq!(char::from(screen_char.ascii_character), c);
+//         }
+//     });
+// }
+// }
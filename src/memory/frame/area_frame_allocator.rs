@@ -1,5 +1,9 @@
 use crate::memory::frame::{Frame, FrameAllocator};
-use multiboot2::MemoryArea;
+use multiboot2::{MemoryArea, MemoryAreaType, MemoryAreaTypeId};
+
+/// How many ranges besides the kernel and the multiboot information can be kept
+/// out of use, e.g. one per boot module
+pub const MAX_RESERVED: usize = 16;
 
 pub struct AreaFrameAllocator<'a> {
     next_free_frame: Frame,
@@ -9,6 +13,8 @@ pub struct AreaFrameAllocator<'a> {
     kernel_end: Frame,
     multiboot_start: Frame,
     multiboot_end: Frame,
+    // First and last frames of the ranges added with `reserve`
+    reserved: [Option<(Frame, Frame)>; MAX_RESERVED],
 }
 
 impl<'a> FrameAllocator for AreaFrameAllocator<'a> {
@@ -33,6 +39,16 @@ impl<'a> FrameAllocator for AreaFrameAllocator<'a> {
                 self.next_free_frame = Frame {
                     number: self.multiboot_end.number + 1,
                 };
+            } else if let Some((_, end)) = self
+                .reserved
+                .iter()
+                .flatten()
+                .find(|(start, end)| frame >= *start && frame <= *end)
+            {
+                // `frame` is reserved, e.g. holds a boot module
+                self.next_free_frame = Frame {
+                    number: end.number + 1,
+                };
             } else {
                 // frame is unused, increment `next_free_frame` and return it
                 self.next_free_frame.number += 1;
@@ -66,15 +82,34 @@ impl<'a> AreaFrameAllocator<'a> {
             kernel_end: Frame::containing_address(kernel_end),
             multiboot_start: Frame::containing_address(multiboot_start),
             multiboot_end: Frame::containing_address(multiboot_end),
+            reserved: Default::default(),
         };
         allocator.choose_next_area();
         allocator
     }
 
+    /// Keeps the frames from `start` to `end`, inclusive, from being allocated.
+    /// Call it before allocating any frames. Returns false if there are already
+    /// `MAX_RESERVED` ranges.
+    pub fn reserve(&mut self, start: u64, end: u64) -> bool {
+        match self.reserved.iter_mut().find(|range| range.is_none()) {
+            Some(range) => {
+                *range = Some((
+                    Frame::containing_address(start),
+                    Frame::containing_address(end),
+                ));
+                true
+            }
+            None => false,
+        }
+    }
+
     fn choose_next_area(&mut self) {
         self.current_area = self
             .areas
             .into_iter()
+            // Skip reserved areas, they hold things like the ACPI tables
+            .filter(|area| area.typ() == MemoryAreaTypeId::from(MemoryAreaType::Available))
             .filter(|area| {
                 let address = area.start_address() + area.size() - 1;
                 Frame::containing_address(address) >= self.next_free_frame
@@ -32,16 +32,19 @@ pub fn init<'a>(boot_info: &'a BootInformation) -> AreaFrameAllocator<'a> {
     let multiboot_start = boot_info.start_address() as u64;
     let multiboot_end = (multiboot_start + boot_info.total_size() as u64);
 
+    // Includes the symbol table, which GRUB loads after the kernel
     let kernel_start = boot_info
         .elf_sections()
         .unwrap()
+        .filter(|s| s.size() > 0)
         .map(|s| s.start_address())
         .min()
         .unwrap();
     let kernel_end = boot_info
         .elf_sections()
         .unwrap()
-        .map(|s| s.start_address())
+        .filter(|s| s.size() > 0)
+        .map(|s| s.end_address() - 1)
         .max()
         .unwrap();
 
@@ -55,6 +58,24 @@ pub fn init<'a>(boot_info: &'a BootInformation) -> AreaFrameAllocator<'a> {
         memory_areas,
     );
 
+    // Keep the boot modules, the initrd among them, out of use for good
+    for module in boot_info.module_tags() {
+        if module.module_size() == 0 {
+            continue;
+        }
+        let (start, end) = (
+            module.start_address() as u64,
+            module.end_address() as u64 - 1,
+        );
+        if !frame_allocator.reserve(start, end) {
+            log::warn!(
+                "too many boot modules, {:#x}..{:#x} may be overwritten",
+                start,
+                end
+            );
+        }
+    }
+
     enable_bits();
 
     // Initialzie 4-level paging
@@ -138,3 +138,29 @@ impl Mapper {
         // allocator.deallocate_frame(frame);
     }
 }
+
+crate::bench_cases! {
+    fn translate_address(bencher: &mut Bencher) {
+        let mapper = unsafe { Mapper::new() };
+        bencher.iter(|| mapper.translate(core::hint::black_box(crate::heap::HEAP_START)));
+    }
+
+    fn map_and_unmap_page(bencher: &mut Bencher) {
+        // The page below the heap shares its page tables with the start of the
+        // heap, so no frames are needed for new tables
+        struct NoFrames;
+        impl FrameAllocator for NoFrames {
+            fn allocate_frame(&mut self) -> Option<Frame> {
+                None
+            }
+            fn deallocate_frame(&mut self, _frame: Frame) {}
+        }
+
+        let mut mapper = unsafe { Mapper::new() };
+        let page = Page::containing_address(crate::heap::HEAP_START - PAGE_SIZE as usize);
+        bencher.iter(|| {
+            mapper.map_to(page, Frame::new(0), EntryFlags::WRITABLE, &mut NoFrames);
+            mapper.unmap(page, &mut NoFrames);
+        });
+    }
+}
@@ -1,23 +1,19 @@
 pub mod active_page_table;
-pub mod entry;
 pub mod inactive_page_table;
 pub mod mapper;
 pub mod page;
 pub mod table;
 
 use crate::memory::frame::{Frame, FrameAllocator};
-use crate::println;
 use active_page_table::ActivePageTable;
 use entry::EntryFlags;
 use inactive_page_table::InactivePageTable;
 use multiboot2::BootInformation;
 use page::{Page, TemporaryPage};
 
-pub const PAGE_SIZE: u64 = 4096; // 4KB
-const PAGE_TABLE_ENTRY_COUNT: usize = 512; // 512 * 8 bytes = 4KB
+pub use kernel_core::paging::{entry, PhysAddr, VirtAddr, PAGE_SIZE};
 
-pub type PhysAddr = usize;
-pub type VirtAddr = usize;
+const PAGE_TABLE_ENTRY_COUNT: usize = 512; // 512 * 8 bytes = 4KB
 
 pub fn remap_kernel<A: FrameAllocator>(
     allocator: &mut A,
@@ -47,7 +43,7 @@ pub fn remap_kernel<A: FrameAllocator>(
                 "sections need to be page aligned"
             );
 
-            println!(
+            log::debug!(
                 "mapping section at addr: {:#x}, size: {:#x}",
                 section.start_address(),
                 section.size()
@@ -71,6 +67,41 @@ pub fn remap_kernel<A: FrameAllocator>(
         for frame in Frame::range_inclusive(multiboot_start, multiboot_end) {
             mapper.identity_map(frame, EntryFlags::PRESENT, allocator);
         }
+
+        // Identity map the symbol table read only, for backtraces. GRUB loads it,
+        // but it isn't an allocated section. The section names are needed to find it.
+        for section in boot_info.elf_sections().unwrap() {
+            let symbols = matches!(section.name(), Ok(".symtab" | ".strtab" | ".shstrtab"));
+            if section.is_allocated() || !symbols || section.size() == 0 {
+                continue;
+            }
+
+            let start_frame = Frame::containing_address(section.start_address());
+            let end_frame = Frame::containing_address(section.end_address() - 1);
+            for frame in Frame::range_inclusive(start_frame, end_frame) {
+                // The sections aren't page aligned, and may share pages
+                let page = Page::containing_address(frame.start_address() as usize);
+                if mapper.translate_page(page).is_none() {
+                    mapper.identity_map(frame, EntryFlags::NO_EXECUTE, allocator);
+                }
+            }
+        }
+
+        // Identity map the boot modules read only, `initrd` reads them in place
+        for module in boot_info.module_tags() {
+            if module.module_size() == 0 {
+                continue;
+            }
+            let start_frame = Frame::containing_address(module.start_address() as u64);
+            let end_frame = Frame::containing_address(module.end_address() as u64 - 1);
+            for frame in Frame::range_inclusive(start_frame, end_frame) {
+                // Modules needn't be page aligned either
+                let page = Page::containing_address(frame.start_address() as usize);
+                if mapper.translate_page(page).is_none() {
+                    mapper.identity_map(frame, EntryFlags::NO_EXECUTE, allocator);
+                }
+            }
+        }
     });
 
     let old_table = active_table.switch(new_table);
@@ -78,12 +109,26 @@ pub fn remap_kernel<A: FrameAllocator>(
     // Turn the old P4 table into a guard page
     let old_p4_page = Page::containing_address(old_table.p4_frame.start_address() as usize);
     active_table.unmap(old_p4_page, allocator);
-    println!("Guard page at {:#x}", old_p4_page.start_address());
-    println!("Switched to new page table!");
+    log::debug!("guard page at {:#x}", old_p4_page.start_address());
+    log::info!("switched to the new page table");
 
     active_table
 }
 
+/// Identity maps the memory mapped device registers at `address`, uncached and
+/// writable. Pages that are already mapped are left alone.
+pub fn map_mmio(address: usize, mapper: &mut mapper::Mapper, allocator: &mut impl FrameAllocator) {
+    let page = Page::containing_address(address);
+    if mapper.translate_page(page).is_some() {
+        return;
+    }
+    let flags = EntryFlags::WRITABLE
+        | EntryFlags::NO_CACHE
+        | EntryFlags::WRITE_THROUGH
+        | EntryFlags::NO_EXECUTE;
+    mapper.identity_map(Frame::containing_address(address as u64), flags, allocator);
+}
+
 pub fn init(allocator: &mut impl FrameAllocator, boot_info: &BootInformation) -> ActivePageTable {
     // Remap the kernel
     let mut mapper = remap_kernel(allocator, boot_info);
@@ -1,6 +1,15 @@
-use core::panic::PanicInfo;
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use crate::{println, serial_println};
+use x86_64::instructions::interrupts;
+
+use crate::{
+    backtrace::Backtrace,
+    logger,
+    serial::{self, ComPort},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
@@ -17,34 +26,49 @@ pub fn exit_qemu(exit_code: QemuExitCode) {
     }
 }
 
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Lets the next panic be handled normally again, for code that carries on
+/// after a panic, like the test runner after an expected one
+pub(crate) fn recovered() {
+    PANICKING.store(false, Ordering::Relaxed);
+}
+
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
-    #[cfg(not(testing))]
-    println!("{}", info);
+    interrupts::disable();
+    if PANICKING.swap(true, Ordering::Relaxed) {
+        // Panicked while printing the panic, only the bare serial port is left
+        serial::write_fmt_raw(
+            ComPort::Com1,
+            format_args!("\npanicked while panicking: {}\n", info),
+        );
+        #[cfg(testing)]
+        exit_qemu(QemuExitCode::Failed);
+        crate::hlt_loop();
+    }
+    // Nothing that the panic interrupted runs again, so the locks it held can
+    // be taken over
+    unsafe { logger::force_unlock() };
 
+    let backtrace = Backtrace::capture();
+
+    // The test runner decides whether to carry on
     #[cfg(testing)]
+    crate::tests::on_panic(info, &backtrace);
+
+    #[cfg(not(testing))]
     {
-        serial_println!("[failed]\n{}", info);
-        exit_qemu(QemuExitCode::Failed);
-    }
+        use crate::framebuffer::crash::{self, Crash};
 
-    crate::hlt_loop();
+        let message = info.message();
+        crash::report(&Crash {
+            title: "KERNEL PANIC",
+            message: Some(&message),
+            location: info.location(),
+            exception: None,
+            registers: &[],
+            backtrace: &backtrace,
+        });
+    }
 }
-
-// TODO: Implement panic_print from https://github.com/thepowersgang/rust-barebones-kernel/blob/master/Kernel/unwind.rs
-
-// fn panic_print(fmt: core::fmt::Arguments, file: &'static str, line: u32) {
-//     #[cfg(testing)]
-//     {
-//         use crate::serial_print;
-//         serial_print!("\n\n PANIC in {} a line {}:", file, line);
-//         serial_println!("    {}", fmt);
-//     }
-//
-//     #[cfg(not(testing))]
-//     {
-//         use crate::println;
-//         println!("\n\n PANIC in {} a line {}:", file, line);
-//         println!("    {}", fmt);
-//     }
-// }
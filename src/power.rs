@@ -0,0 +1,236 @@
+use x86_64::instructions::{interrupts, port::Port};
+
+use crate::{
+    acpi::{self, fadt::Fadt, GenericAddress},
+    println,
+};
+
+// PM1 control register bits
+const SCI_EN: u16 = 1 << 0;
+const SLP_EN: u16 = 1 << 13;
+
+// AML opcodes needed to decode the \_S5 package
+const AML_NAME_OP: u8 = 0x08;
+const AML_PACKAGE_OP: u8 = 0x12;
+const AML_BYTE_PREFIX: u8 = 0x0A;
+const AML_ZERO_OP: u8 = 0x00;
+const AML_ONE_OP: u8 = 0x01;
+
+const KEYBOARD_CONTROLLER_STATUS: u16 = 0x64;
+const KEYBOARD_CONTROLLER_RESET: u8 = 0xFE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SleepType {
+    pm1a: u16,
+    pm1b: u16,
+}
+
+// Decodes a single integer element of an AML package
+fn aml_integer(aml: &[u8], offset: &mut usize) -> Option<u16> {
+    let value = match *aml.get(*offset)? {
+        AML_BYTE_PREFIX => {
+            *offset += 1;
+            *aml.get(*offset)? as u16
+        }
+        AML_ZERO_OP => 0,
+        AML_ONE_OP => 1,
+        byte => byte as u16,
+    };
+    *offset += 1;
+    Some(value)
+}
+
+/// Finds the `Name (\_S5, Package () { SLP_TYPa, SLP_TYPb, ... })` object in
+/// the AML byte code of the DSDT
+fn parse_s5(aml: &[u8]) -> Option<SleepType> {
+    let position = aml.windows(4).enumerate().position(|(index, window)| {
+        if window != b"_S5_" || index < 1 {
+            return false;
+        }
+        // Either `NameOp _S5_` or `NameOp \_S5_`
+        aml[index - 1] == AML_NAME_OP
+            || (index >= 2 && aml[index - 1] == b'\\' && aml[index - 2] == AML_NAME_OP)
+    })?;
+
+    let mut offset = position + 4;
+    if *aml.get(offset)? != AML_PACKAGE_OP {
+        return None;
+    }
+    offset += 1;
+
+    // The top two bits of the PkgLength lead byte give the number of extra bytes
+    let pkg_length_bytes = (*aml.get(offset)? >> 6) as usize;
+    offset += 1 + pkg_length_bytes;
+
+    // NumElements
+    offset += 1;
+
+    let pm1a = aml_integer(aml, &mut offset)?;
+    let pm1b = aml_integer(aml, &mut offset)?;
+    Some(SleepType { pm1a, pm1b })
+}
+
+// Switches the chipset from legacy (SMM) mode to ACPI mode, if needed
+fn enable_acpi(fadt: &Fadt) {
+    let mut pm1a_control: Port<u16> = Port::new(fadt.pm1a_control_block as u16);
+    if unsafe { pm1a_control.read() } & SCI_EN != 0 {
+        return;
+    }
+    if fadt.smi_command_port == 0 || fadt.acpi_enable == 0 {
+        // ACPI mode can't be changed, assume it's always on
+        return;
+    }
+
+    let mut smi_command: Port<u8> = Port::new(fadt.smi_command_port as u16);
+    unsafe { smi_command.write(fadt.acpi_enable) };
+
+    for _ in 0..1_000_000 {
+        if unsafe { pm1a_control.read() } & SCI_EN != 0 {
+            return;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+fn acpi_shutdown() {
+    let Some(tables) = acpi::tables() else {
+        return;
+    };
+    let (Some(fadt), Some(dsdt)) = (tables.fadt.as_ref(), tables.dsdt.as_ref()) else {
+        return;
+    };
+    let Some(sleep_type) = parse_s5(dsdt.data()) else {
+        log::error!("no \\_S5 object in the DSDT");
+        return;
+    };
+
+    enable_acpi(fadt);
+
+    unsafe {
+        let mut pm1a_control: Port<u16> = Port::new(fadt.pm1a_control_block as u16);
+        pm1a_control.write((sleep_type.pm1a << 10) | SLP_EN);
+
+        if fadt.pm1b_control_block != 0 {
+            let mut pm1b_control: Port<u16> = Port::new(fadt.pm1b_control_block as u16);
+            pm1b_control.write((sleep_type.pm1b << 10) | SLP_EN);
+        }
+    }
+}
+
+fn acpi_reset() {
+    let Some(fadt) = acpi::tables().and_then(|tables| tables.fadt.as_ref()) else {
+        return;
+    };
+    let Some(register) = fadt.reset_register else {
+        return;
+    };
+    let address = register.address;
+    let address_space = register.address_space;
+
+    match address_space {
+        GenericAddress::SYSTEM_IO => unsafe {
+            Port::<u8>::new(address as u16).write(fadt.reset_value);
+        },
+        GenericAddress::PCI_CONFIG => unsafe {
+            // Bus 0, device / function / offset are packed into the address
+            let device = (address >> 32) & 0xFFFF;
+            let function = (address >> 16) & 0xFFFF;
+            let offset = address & 0xFFFF;
+            let config_address = 0x8000_0000
+                | (device << 11) as u32
+                | (function << 8) as u32
+                | (offset & 0xFC) as u32;
+            Port::<u32>::new(0xCF8).write(config_address);
+            Port::<u8>::new(0xCFC + (offset & 3) as u16).write(fadt.reset_value);
+        },
+        // A memory mapped reset register would need to be mapped first
+        _ => {}
+    }
+}
+
+fn keyboard_controller_reset() {
+    let mut status: Port<u8> = Port::new(KEYBOARD_CONTROLLER_STATUS);
+    unsafe {
+        // Wait for the input buffer to be empty
+        for _ in 0..100_000 {
+            if status.read() & 0b10 == 0 {
+                break;
+            }
+            core::hint::spin_loop();
+        }
+        status.write(KEYBOARD_CONTROLLER_RESET);
+    }
+}
+
+fn triple_fault() -> ! {
+    use x86_64::instructions::tables::{lidt, DescriptorTablePointer};
+    use x86_64::VirtAddr;
+
+    // With an empty IDT every exception escalates to a triple fault
+    let idt = DescriptorTablePointer {
+        limit: 0,
+        base: VirtAddr::new(0),
+    };
+    unsafe {
+        lidt(&idt);
+        core::arch::asm!("int3", options(noreturn));
+    }
+}
+
+fn spin_wait() {
+    for _ in 0..10_000_000 {
+        core::hint::spin_loop();
+    }
+}
+
+/// Powers the machine off using the ACPI S5 sleep state
+pub fn shutdown() -> ! {
+    interrupts::disable();
+
+    acpi_shutdown();
+    spin_wait();
+
+    println!("Shutdown failed, it is now safe to turn off your computer");
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+/// Resets the machine, trying the ACPI reset register, the keyboard controller
+/// and finally a triple fault
+pub fn reboot() -> ! {
+    interrupts::disable();
+
+    acpi_reset();
+    spin_wait();
+
+    keyboard_controller_reset();
+    spin_wait();
+
+    triple_fault();
+}
+
+crate::test_cases! {
+    fn parse_s5_with_byte_prefix() {
+        // Name (_S5, Package (0x04) { 0x05, 0x05, Zero, Zero })
+        let aml = [
+            0x10, 0x08, AML_NAME_OP, b'_', b'S', b'5', b'_', AML_PACKAGE_OP, 0x0A, 0x04,
+            AML_BYTE_PREFIX, 0x05, AML_BYTE_PREFIX, 0x05, AML_ZERO_OP, AML_ZERO_OP,
+        ];
+        assert_eq!(parse_s5(&aml), Some(SleepType { pm1a: 5, pm1b: 5 }));
+    }
+
+    fn parse_s5_rooted_name() {
+        // Name (\_S5, Package (0x02) { Zero, One })
+        let aml = [
+            AML_NAME_OP, b'\\', b'_', b'S', b'5', b'_', AML_PACKAGE_OP, 0x04, 0x02,
+            AML_ZERO_OP, AML_ONE_OP,
+        ];
+        assert_eq!(parse_s5(&aml), Some(SleepType { pm1a: 0, pm1b: 1 }));
+    }
+
+    fn parse_s5_missing() {
+        let aml = [AML_NAME_OP, b'_', b'S', b'4', b'_', AML_PACKAGE_OP, 0x04, 0x02, 0x00, 0x00];
+        assert_eq!(parse_s5(&aml), None);
+    }
+}
@@ -0,0 +1,295 @@
+pub mod mouse;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::instructions::{interrupts, port::Port};
+
+const DATA_PORT: u16 = 0x60;
+// Reads give the status register, writes send controller commands
+const COMMAND_PORT: u16 = 0x64;
+
+// Status register bits
+const OUTPUT_FULL: u8 = 1 << 0;
+const INPUT_FULL: u8 = 1 << 1;
+
+// Controller commands
+const READ_CONFIG: u8 = 0x20;
+const WRITE_CONFIG: u8 = 0x60;
+const DISABLE_SECOND_PORT: u8 = 0xA7;
+const ENABLE_SECOND_PORT: u8 = 0xA8;
+const TEST_SECOND_PORT: u8 = 0xA9;
+const SELF_TEST: u8 = 0xAA;
+const TEST_FIRST_PORT: u8 = 0xAB;
+const DISABLE_FIRST_PORT: u8 = 0xAD;
+const ENABLE_FIRST_PORT: u8 = 0xAE;
+const WRITE_SECOND_PORT: u8 = 0xD4;
+
+const SELF_TEST_PASSED: u8 = 0x55;
+const PORT_TEST_PASSED: u8 = 0x00;
+
+// Configuration byte bits
+const CONFIG_FIRST_IRQ: u8 = 1 << 0;
+const CONFIG_SECOND_IRQ: u8 = 1 << 1;
+const CONFIG_SECOND_CLOCK_DISABLED: u8 = 1 << 5;
+const CONFIG_TRANSLATION: u8 = 1 << 6;
+
+// Device commands and responses
+const RESET: u8 = 0xFF;
+pub const ACK: u8 = 0xFA;
+pub const RESEND: u8 = 0xFE;
+const RESET_PASSED: u8 = 0xAA;
+
+// How often the status register is polled before giving up, roughly a
+// microsecond per read
+const TIMEOUT: usize = 100_000;
+// Devices can take up to half a second to finish their reset
+const RESET_TIMEOUT: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Usually the keyboard, IRQ 1
+    First,
+    /// Usually the mouse, IRQ 12
+    Second,
+}
+
+#[derive(Debug)]
+pub enum Ps2Error {
+    Timeout,
+    SelfTestFailed(u8),
+    PortTestFailed(Channel, u8),
+    NoDevice(Channel),
+    UnexpectedResponse(u8),
+}
+
+static FIRST_PRESENT: AtomicBool = AtomicBool::new(false);
+static SECOND_PRESENT: AtomicBool = AtomicBool::new(false);
+
+// Polls the status register until `mask` reads as `expected`, so a missing or
+// broken controller can't hang the kernel
+fn wait_status(mask: u8, expected: u8, timeout: usize) -> Result<(), Ps2Error> {
+    let mut status: Port<u8> = Port::new(COMMAND_PORT);
+    for _ in 0..timeout {
+        if unsafe { status.read() } & mask == expected {
+            return Ok(());
+        }
+        core::hint::spin_loop();
+    }
+    Err(Ps2Error::Timeout)
+}
+
+fn write_port(port: u16, value: u8) -> Result<(), Ps2Error> {
+    wait_status(INPUT_FULL, 0, TIMEOUT)?;
+    unsafe { Port::new(port).write(value) };
+    Ok(())
+}
+
+fn read_timeout(timeout: usize) -> Result<u8, Ps2Error> {
+    wait_status(OUTPUT_FULL, OUTPUT_FULL, timeout)?;
+    Ok(unsafe { Port::new(DATA_PORT).read() })
+}
+
+/// Waits for a byte from the controller or one of the devices. Interrupts must
+/// be disabled, or the interrupt handlers will take the byte first.
+pub fn read() -> Result<u8, Ps2Error> {
+    read_timeout(TIMEOUT)
+}
+
+// Discards whatever is left in the output buffer
+fn flush() {
+    let mut status: Port<u8> = Port::new(COMMAND_PORT);
+    let mut data: Port<u8> = Port::new(DATA_PORT);
+    for _ in 0..16 {
+        if unsafe { status.read() } & OUTPUT_FULL == 0 {
+            return;
+        }
+        unsafe { data.read() };
+    }
+}
+
+fn controller_command(command: u8) -> Result<(), Ps2Error> {
+    write_port(COMMAND_PORT, command)
+}
+
+pub fn read_config() -> Result<u8, Ps2Error> {
+    controller_command(READ_CONFIG)?;
+    read()
+}
+
+pub fn write_config(config: u8) -> Result<(), Ps2Error> {
+    controller_command(WRITE_CONFIG)?;
+    write_port(DATA_PORT, config)
+}
+
+/// Sends a byte to the device on `channel`
+pub fn write(channel: Channel, byte: u8) -> Result<(), Ps2Error> {
+    if channel == Channel::Second {
+        controller_command(WRITE_SECOND_PORT)?;
+    }
+    write_port(DATA_PORT, byte)
+}
+
+/// Sends a command (with an optional argument byte) to the device on `channel`
+/// and waits for each byte to be acknowledged, resending it if asked to.
+pub fn command(channel: Channel, command: u8, argument: Option<u8>) -> Result<(), Ps2Error> {
+    interrupts::without_interrupts(|| {
+        for byte in core::iter::once(command).chain(argument) {
+            send_acknowledged(channel, byte)?;
+        }
+        Ok(())
+    })
+}
+
+fn send_acknowledged(channel: Channel, byte: u8) -> Result<(), Ps2Error> {
+    let mut response = RESEND;
+    for _ in 0..3 {
+        write(channel, byte)?;
+        response = read()?;
+        if response != RESEND {
+            break;
+        }
+    }
+    match response {
+        ACK => Ok(()),
+        response => Err(Ps2Error::UnexpectedResponse(response)),
+    }
+}
+
+/// The controller translates set 2 scancodes from the first port to set 1 when
+/// enabled. Turning it off passes on what the keyboard sends natively.
+pub fn set_translation(enabled: bool) -> Result<(), Ps2Error> {
+    interrupts::without_interrupts(|| {
+        let config = read_config()?;
+        let config = if enabled {
+            config | CONFIG_TRANSLATION
+        } else {
+            config & !CONFIG_TRANSLATION
+        };
+        write_config(config)
+    })
+}
+
+/// Whether a working device was found on `channel`
+pub fn is_present(channel: Channel) -> bool {
+    match channel {
+        Channel::First => FIRST_PRESENT.load(Ordering::Relaxed),
+        Channel::Second => SECOND_PRESENT.load(Ordering::Relaxed),
+    }
+}
+
+// Called from the interrupt handlers. The byte may already have been consumed
+// by a command waiting for its response, in which case the buffer is empty.
+pub(crate) fn read_interrupt_byte() -> Option<u8> {
+    let mut status: Port<u8> = Port::new(COMMAND_PORT);
+    if unsafe { status.read() } & OUTPUT_FULL == 0 {
+        return None;
+    }
+    Some(unsafe { Port::new(DATA_PORT).read() })
+}
+
+fn reset_device(channel: Channel) -> Result<(), Ps2Error> {
+    send_acknowledged(channel, RESET)?;
+    match read_timeout(RESET_TIMEOUT)? {
+        RESET_PASSED => {}
+        response => return Err(Ps2Error::UnexpectedResponse(response)),
+    }
+    // Mice follow up with their device id, keyboards send nothing more
+    let _ = read_timeout(TIMEOUT / 10);
+    Ok(())
+}
+
+fn init_controller() -> Result<(), Ps2Error> {
+    // Keep the devices quiet while the controller is set up
+    controller_command(DISABLE_FIRST_PORT)?;
+    controller_command(DISABLE_SECOND_PORT)?;
+    flush();
+
+    let mut config = read_config()?;
+    config &= !(CONFIG_FIRST_IRQ | CONFIG_SECOND_IRQ | CONFIG_TRANSLATION);
+    write_config(config)?;
+
+    controller_command(SELF_TEST)?;
+    match read()? {
+        SELF_TEST_PASSED => {}
+        response => return Err(Ps2Error::SelfTestFailed(response)),
+    }
+    // The self test resets the controller on some hardware
+    write_config(config)?;
+
+    // The second clock is only turned on by enabling the second port if there is one
+    let mut dual_channel = false;
+    if config & CONFIG_SECOND_CLOCK_DISABLED != 0 {
+        controller_command(ENABLE_SECOND_PORT)?;
+        dual_channel = read_config()? & CONFIG_SECOND_CLOCK_DISABLED == 0;
+        controller_command(DISABLE_SECOND_PORT)?;
+    }
+
+    let tests = [
+        (Channel::First, TEST_FIRST_PORT),
+        (Channel::Second, TEST_SECOND_PORT),
+    ];
+    let mut working = [false; 2];
+    for (channel, test) in tests.into_iter().take(if dual_channel { 2 } else { 1 }) {
+        controller_command(test)?;
+        match read()? {
+            PORT_TEST_PASSED => working[channel as usize] = true,
+            response => log::warn!("{:?}", Ps2Error::PortTestFailed(channel, response)),
+        }
+    }
+
+    if working[Channel::First as usize] {
+        controller_command(ENABLE_FIRST_PORT)?;
+        config |= CONFIG_FIRST_IRQ | CONFIG_TRANSLATION;
+    }
+    if working[Channel::Second as usize] {
+        controller_command(ENABLE_SECOND_PORT)?;
+        config |= CONFIG_SECOND_IRQ;
+        config &= !CONFIG_SECOND_CLOCK_DISABLED;
+    }
+
+    for channel in [Channel::First, Channel::Second] {
+        if !working[channel as usize] {
+            continue;
+        }
+        match reset_device(channel) {
+            Ok(()) => match channel {
+                Channel::First => FIRST_PRESENT.store(true, Ordering::Relaxed),
+                Channel::Second => SECOND_PRESENT.store(true, Ordering::Relaxed),
+            },
+            Err(_) => log::info!("{:?}", Ps2Error::NoDevice(channel)),
+        }
+    }
+
+    // Only turn on the interrupts once the devices are done answering
+    flush();
+    write_config(config)
+}
+
+/// Initializes the 8042 PS/2 controller and resets the attached devices, then
+/// sets up the mouse if there is one on the second port
+pub fn init() {
+    if let Err(error) = interrupts::without_interrupts(init_controller) {
+        log::error!("controller initialization failed: {:?}", error);
+        return;
+    }
+
+    log::info!(
+        "keyboard {}, second port {}",
+        if is_present(Channel::First) {
+            "found"
+        } else {
+            "missing"
+        },
+        if is_present(Channel::Second) {
+            "found"
+        } else {
+            "missing"
+        }
+    );
+
+    if is_present(Channel::Second) {
+        if let Err(error) = mouse::init() {
+            log::warn!("mouse initialization failed: {:?}", error);
+        }
+    }
+}
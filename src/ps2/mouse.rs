@@ -0,0 +1,290 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use super::{Channel, Ps2Error};
+use crate::{framebuffer, ring_buffer::RingBuffer};
+
+// Mouse commands
+const SET_DEFAULTS: u8 = 0xF6;
+const ENABLE_REPORTING: u8 = 0xF4;
+const SET_SAMPLE_RATE: u8 = 0xF3;
+const GET_DEVICE_ID: u8 = 0xF2;
+
+// Device ids, the wheel and extra buttons are unlocked by magic sample rate sequences
+const ID_WHEEL: u8 = 0x03;
+const ID_FIVE_BUTTONS: u8 = 0x04;
+
+// Bits of the first packet byte
+const LEFT_BUTTON: u8 = 1 << 0;
+const RIGHT_BUTTON: u8 = 1 << 1;
+const MIDDLE_BUTTON: u8 = 1 << 2;
+const ALWAYS_ONE: u8 = 1 << 3;
+const X_SIGN: u8 = 1 << 4;
+const Y_SIGN: u8 = 1 << 5;
+const X_OVERFLOW: u8 = 1 << 6;
+const Y_OVERFLOW: u8 = 1 << 7;
+
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct MouseButtons: u8 {
+        const LEFT = 1 << 0;
+        const RIGHT = 1 << 1;
+        const MIDDLE = 1 << 2;
+        const BUTTON_4 = 1 << 3;
+        const BUTTON_5 = 1 << 4;
+    }
+}
+
+/// One decoded mouse packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// Movement to the right
+    pub dx: i16,
+    /// Movement downwards, i.e. in screen coordinates
+    pub dy: i16,
+    /// Wheel movement, positive when scrolling down
+    pub wheel: i8,
+    /// Buttons held down after this event
+    pub buttons: MouseButtons,
+    /// Buttons that were pressed or released by this event
+    pub changed: MouseButtons,
+}
+
+// Assembles the bytes coming from the mouse into packets
+struct PacketDecoder {
+    packet: [u8; 4],
+    received: usize,
+    packet_size: usize,
+    // Whether the fourth byte also holds buttons 4 and 5
+    five_buttons: bool,
+    buttons: MouseButtons,
+}
+
+impl PacketDecoder {
+    const fn new() -> Self {
+        Self {
+            packet: [0; 4],
+            received: 0,
+            packet_size: 3,
+            five_buttons: false,
+            buttons: MouseButtons::empty(),
+        }
+    }
+
+    fn set_device_id(&mut self, id: u8) {
+        self.packet_size = if id == ID_WHEEL || id == ID_FIVE_BUTTONS {
+            4
+        } else {
+            3
+        };
+        self.five_buttons = id == ID_FIVE_BUTTONS;
+        self.received = 0;
+    }
+
+    fn add_byte(&mut self, byte: u8) -> Option<MouseEvent> {
+        // The first byte always has bit 3 set, skip bytes until we are back in sync
+        if self.received == 0 && byte & ALWAYS_ONE == 0 {
+            return None;
+        }
+        self.packet[self.received] = byte;
+        self.received += 1;
+        if self.received < self.packet_size {
+            return None;
+        }
+        self.received = 0;
+        Some(self.decode())
+    }
+
+    fn decode(&mut self) -> MouseEvent {
+        let [flags, x, y, extra] = self.packet;
+
+        // Movement is a 9 bit two's complement number, with the sign in the flags
+        let mut dx = x as i16 - (((flags & X_SIGN) as i16) << 4);
+        let mut dy = y as i16 - (((flags & Y_SIGN) as i16) << 3);
+        if flags & (X_OVERFLOW | Y_OVERFLOW) != 0 {
+            dx = 0;
+            dy = 0;
+        }
+
+        let mut buttons =
+            MouseButtons::from_bits_truncate(flags & (LEFT_BUTTON | RIGHT_BUTTON | MIDDLE_BUTTON));
+        let wheel = match self.packet_size {
+            3 => 0,
+            _ if self.five_buttons => {
+                buttons.set(MouseButtons::BUTTON_4, extra & (1 << 4) != 0);
+                buttons.set(MouseButtons::BUTTON_5, extra & (1 << 5) != 0);
+                // Sign extend the 4 bit wheel movement
+                ((extra << 4) as i8) >> 4
+            }
+            _ => extra as i8,
+        };
+
+        let changed = buttons ^ self.buttons;
+        self.buttons = buttons;
+
+        MouseEvent {
+            dx,
+            // The mouse counts upwards movement as positive
+            dy: -dy,
+            wheel,
+            buttons,
+            changed,
+        }
+    }
+}
+
+static DECODER: Mutex<PacketDecoder> = Mutex::new(PacketDecoder::new());
+static EVENTS: RingBuffer<MouseEvent, 64> = RingBuffer::new();
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CURSOR_VISIBLE: AtomicBool = AtomicBool::new(true);
+static X: AtomicUsize = AtomicUsize::new(0);
+static Y: AtomicUsize = AtomicUsize::new(0);
+
+fn set_sample_rate(rate: u8) -> Result<(), Ps2Error> {
+    super::command(Channel::Second, SET_SAMPLE_RATE, Some(rate))
+}
+
+fn device_id() -> Result<u8, Ps2Error> {
+    interrupts::without_interrupts(|| {
+        super::command(Channel::Second, GET_DEVICE_ID, None)?;
+        super::read()
+    })
+}
+
+pub(super) fn init() -> Result<(), Ps2Error> {
+    super::command(Channel::Second, SET_DEFAULTS, None)?;
+
+    // Magic sequence enabling the scroll wheel
+    for rate in [200, 100, 80] {
+        set_sample_rate(rate)?;
+    }
+    let mut id = device_id()?;
+    // And the one for the fourth and fifth button, only works with a wheel
+    if id == ID_WHEEL {
+        for rate in [200, 200, 80] {
+            set_sample_rate(rate)?;
+        }
+        id = device_id()?;
+    }
+    set_sample_rate(100)?;
+
+    interrupts::without_interrupts(|| DECODER.lock().set_device_id(id));
+
+    // Start in the middle of the screen
+    if let Some((width, height)) = framebuffer::dimensions() {
+        X.store(width / 2, Ordering::Relaxed);
+        Y.store(height / 2, Ordering::Relaxed);
+    }
+
+    super::command(Channel::Second, ENABLE_REPORTING, None)?;
+    ENABLED.store(true, Ordering::Relaxed);
+    update_cursor();
+
+    log::info!(
+        "mouse with {}",
+        match id {
+            ID_WHEEL => "a scroll wheel",
+            ID_FIVE_BUTTONS => "a scroll wheel and five buttons",
+            _ => "three buttons",
+        }
+    );
+    Ok(())
+}
+
+// Called from the mouse interrupt handler
+pub(crate) fn on_interrupt() {
+    let Some(byte) = super::read_interrupt_byte() else {
+        return;
+    };
+    // Only the interrupt handler decodes packets, so this never spins
+    let Some(event) = DECODER.lock().add_byte(byte) else {
+        return;
+    };
+
+    if event.dx != 0 || event.dy != 0 {
+        if let Some((width, height)) = framebuffer::dimensions() {
+            let move_by = |position: &AtomicUsize, delta: i16, limit: usize| {
+                let current = position.load(Ordering::Relaxed) as isize;
+                let new = (current + delta as isize).clamp(0, limit as isize - 1);
+                position.store(new as usize, Ordering::Relaxed);
+            };
+            move_by(&X, event.dx, width);
+            move_by(&Y, event.dy, height);
+        }
+        update_cursor();
+    }
+
+    // Events are dropped if nobody reads them
+    let _ = EVENTS.push(event);
+}
+
+fn update_cursor() {
+    let position = CURSOR_VISIBLE.load(Ordering::Relaxed).then(position);
+    // Skipped if the screen is being drawn to, the next packet moves it again
+    framebuffer::set_mouse_cursor(position);
+}
+
+/// Whether a mouse was found and is sending packets
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns the next mouse event, if one is queued
+pub fn poll_event() -> Option<MouseEvent> {
+    EVENTS.pop()
+}
+
+/// The cursor position in pixels
+pub fn position() -> (usize, usize) {
+    (X.load(Ordering::Relaxed), Y.load(Ordering::Relaxed))
+}
+
+pub fn buttons() -> MouseButtons {
+    interrupts::without_interrupts(|| DECODER.lock().buttons)
+}
+
+pub fn set_cursor_visible(visible: bool) {
+    CURSOR_VISIBLE.store(visible, Ordering::Relaxed);
+    if is_enabled() {
+        interrupts::without_interrupts(update_cursor);
+    }
+}
+
+crate::test_cases! {
+    fn mouse_packet_decoding() {
+        let mut decoder = PacketDecoder::new();
+
+        // Left button down, moving right by 5 and down by 3 (negative in mouse terms)
+        assert_eq!(decoder.add_byte(ALWAYS_ONE | LEFT_BUTTON | Y_SIGN), None);
+        assert_eq!(decoder.add_byte(5), None);
+        let event = decoder.add_byte(0xFD).unwrap();
+        assert_eq!((event.dx, event.dy, event.wheel), (5, 3, 0));
+        assert_eq!(event.buttons, MouseButtons::LEFT);
+        assert_eq!(event.changed, MouseButtons::LEFT);
+
+        // A stray byte without bit 3 is skipped to get back in sync
+        assert_eq!(decoder.add_byte(0x00), None);
+
+        // Wheel mice send a fourth byte
+        decoder.set_device_id(ID_WHEEL);
+        for byte in [ALWAYS_ONE | X_SIGN, 0xFF, 0x00] {
+            assert_eq!(decoder.add_byte(byte), None);
+        }
+        let event = decoder.add_byte(0xFF).unwrap();
+        assert_eq!((event.dx, event.dy, event.wheel), (-1, 0, -1));
+        assert_eq!(event.buttons, MouseButtons::empty());
+        assert_eq!(event.changed, MouseButtons::LEFT);
+
+        // Five button mice share the fourth byte between the wheel and buttons
+        decoder.set_device_id(ID_FIVE_BUTTONS);
+        for byte in [ALWAYS_ONE, 0x00, 0x00] {
+            assert_eq!(decoder.add_byte(byte), None);
+        }
+        let event = decoder.add_byte((1 << 4) | 0x0F).unwrap();
+        assert_eq!(event.wheel, -1);
+        assert_eq!(event.buttons, MouseButtons::BUTTON_4);
+    }
+}
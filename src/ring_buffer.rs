@@ -0,0 +1,93 @@
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A fixed size lock-free queue for a single producer and a single consumer,
+/// e.g. an interrupt handler pushing and kernel code popping. Needs no heap,
+/// so it can live in a static and be used from the earliest boot stage.
+pub struct RingBuffer<T: Copy, const N: usize> {
+    buffer: UnsafeCell<[MaybeUninit<T>; N]>,
+    // Index of the next slot to read, only written by the consumer
+    head: AtomicUsize,
+    // Index of the next slot to write, only written by the producer
+    tail: AtomicUsize,
+}
+
+// The producer and consumer never access the same slot at the same time
+unsafe impl<T: Copy + Send, const N: usize> Sync for RingBuffer<T, N> {}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            // An array of MaybeUninit doesn't need initialization
+            buffer: UnsafeCell::new(unsafe { MaybeUninit::uninit().assume_init() }),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        // One slot stays empty to tell a full buffer from an empty one
+        N - 1
+    }
+
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        (tail + N - head) % N
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+
+    /// Appends `value`, or hands it back if the buffer is full
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let next = (tail + 1) % N;
+        if next == self.head.load(Ordering::Acquire) {
+            return Err(value);
+        }
+        unsafe { (*self.buffer.get())[tail].write(value) };
+        self.tail.store(next, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let value = unsafe { (*self.buffer.get())[head].assume_init() };
+        self.head.store((head + 1) % N, Ordering::Release);
+        Some(value)
+    }
+}
+
+impl<T: Copy, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+crate::test_cases! {
+    fn ring_buffer_fifo_order() {
+        let buffer: RingBuffer<u8, 4> = RingBuffer::new();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.push(1), Ok(()));
+        assert_eq!(buffer.push(2), Ok(()));
+        assert_eq!(buffer.push(3), Ok(()));
+        // Full, one slot is always kept free
+        assert_eq!(buffer.push(4), Err(4));
+        assert_eq!(buffer.len(), 3);
+
+        assert_eq!(buffer.pop(), Some(1));
+        assert_eq!(buffer.push(4), Ok(()));
+        assert_eq!(buffer.pop(), Some(2));
+        assert_eq!(buffer.pop(), Some(3));
+        assert_eq!(buffer.pop(), Some(4));
+        assert_eq!(buffer.pop(), None);
+    }
+}
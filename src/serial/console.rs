@@ -0,0 +1,228 @@
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use pc_keyboard::{DecodedKey, KeyCode};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use super::{port, read_byte, try_port, uart::RawPort, ComPort};
+use crate::{cmdline, keyboard, keyboard::line_reader::LineEcho};
+
+/// The port the console runs on
+pub const CONSOLE_PORT: ComPort = ComPort::Com1;
+
+const ESCAPE: u8 = 0x1B;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7F;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DECODER: Mutex<EscapeDecoder> = Mutex::new(EscapeDecoder::new());
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static CONSOLE_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "console",
+    kind: crate::cmdline::Kind::Choice(&["serial"]),
+    help: "also run the shell on the serial port",
+};
+
+/// Turns on the serial console if the command line contains `console=serial`
+pub(super) fn init() {
+    if cmdline::get("console") == Some("serial") {
+        set_enabled(true);
+    }
+}
+
+/// Whether the shell also reads from and echoes to the serial port, and
+/// `println!` output is mirrored there
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    Normal,
+    Escape,
+    // Control sequence, with the numeric parameter read so far
+    Csi(u8),
+    // ESC O, sent for home and end by some terminals
+    Ss3,
+}
+
+/// Decodes the bytes a terminal sends into keys, including the escape
+/// sequences for the arrow, home, end and delete keys
+struct EscapeDecoder {
+    state: EscapeState,
+    // Terminals send \r for enter, some follow it with \n
+    last_was_cr: bool,
+}
+
+impl EscapeDecoder {
+    const fn new() -> Self {
+        Self {
+            state: EscapeState::Normal,
+            last_was_cr: false,
+        }
+    }
+
+    fn add_byte(&mut self, byte: u8) -> Option<DecodedKey> {
+        let last_was_cr = core::mem::replace(&mut self.last_was_cr, byte == b'\r');
+
+        match self.state {
+            EscapeState::Normal => match byte {
+                ESCAPE => {
+                    self.state = EscapeState::Escape;
+                    None
+                }
+                b'\n' if last_was_cr => None,
+                b'\r' | b'\n' => Some(DecodedKey::Unicode('\n')),
+                BACKSPACE | DELETE => Some(DecodedKey::Unicode('\u{8}')),
+                // Multi byte UTF-8 sequences aren't supported
+                byte if byte.is_ascii() => Some(DecodedKey::Unicode(byte as char)),
+                _ => None,
+            },
+            EscapeState::Escape => {
+                self.state = match byte {
+                    b'[' => EscapeState::Csi(0),
+                    b'O' => EscapeState::Ss3,
+                    _ => EscapeState::Normal,
+                };
+                None
+            }
+            EscapeState::Csi(parameter) => {
+                if byte.is_ascii_digit() {
+                    self.state =
+                        EscapeState::Csi(parameter.saturating_mul(10).saturating_add(byte - b'0'));
+                    return None;
+                }
+                self.state = EscapeState::Normal;
+                let key = match (byte, parameter) {
+                    (b'~', 1 | 7) => KeyCode::Home,
+                    (b'~', 3) => KeyCode::Delete,
+                    (b'~', 4 | 8) => KeyCode::End,
+                    (b'~', _) => return None,
+                    (byte, _) => Self::final_byte_key(byte)?,
+                };
+                Some(DecodedKey::RawKey(key))
+            }
+            EscapeState::Ss3 => {
+                self.state = EscapeState::Normal;
+                Self::final_byte_key(byte).map(DecodedKey::RawKey)
+            }
+        }
+    }
+
+    fn final_byte_key(byte: u8) -> Option<KeyCode> {
+        match byte {
+            b'A' => Some(KeyCode::ArrowUp),
+            b'B' => Some(KeyCode::ArrowDown),
+            b'C' => Some(KeyCode::ArrowRight),
+            b'D' => Some(KeyCode::ArrowLeft),
+            b'H' => Some(KeyCode::Home),
+            b'F' => Some(KeyCode::End),
+            _ => None,
+        }
+    }
+}
+
+fn poll_serial_key() -> Option<DecodedKey> {
+    let mut decoder = DECODER.lock();
+    while let Some(byte) = read_byte(CONSOLE_PORT) {
+        if let Some(key) = decoder.add_byte(byte) {
+            return Some(key);
+        }
+    }
+    None
+}
+
+/// Halts until a key is pressed on the keyboard or received over the console port
+pub fn read_key() -> DecodedKey {
+    loop {
+        interrupts::disable();
+        if let Some(key) = keyboard::poll_key().or_else(poll_serial_key) {
+            interrupts::enable();
+            return key;
+        }
+        interrupts::enable_and_hlt();
+    }
+}
+
+// Terminals need \r\n to start a new line
+struct CrLf<W: Write>(W);
+
+impl<W: Write> Write for CrLf<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (index, line) in s.split('\n').enumerate() {
+            if index > 0 {
+                self.0.write_str("\r\n")?;
+            }
+            self.0.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes to the console port, translating line endings for the terminal.
+/// Doesn't wait for the port lock, so it can be used from interrupt handlers.
+pub fn write_fmt(args: fmt::Arguments) {
+    let _ = match try_port(CONSOLE_PORT) {
+        Some(mut uart) => CrLf(&mut *uart).write_fmt(args),
+        None => CrLf(RawPort::new(CONSOLE_PORT.base())).write_fmt(args),
+    };
+}
+
+/// Echoes the line being edited to a terminal on the console port, using
+/// ANSI escape sequences to redraw it
+pub struct SerialEcho;
+
+impl LineEcho for SerialEcho {
+    fn start(&mut self, prompt: &str) {
+        // Save the cursor position after the prompt
+        write_fmt(format_args!("{}\x1b7", prompt));
+    }
+
+    fn redraw(&mut self, line: &[char], cursor: usize, _previous_len: usize) {
+        let mut uart = port(CONSOLE_PORT);
+        // Back to the saved position, then clear the rest of the line
+        let _ = write!(uart, "\x1b8");
+        for c in line {
+            let _ = uart.write_char(*c);
+        }
+        let _ = write!(uart, "\x1b[K");
+        if cursor < line.len() {
+            let _ = write!(uart, "\x1b[{}D", line.len() - cursor);
+        }
+    }
+
+    fn finish(&mut self, _line: &[char]) {
+        write_fmt(format_args!("\n"));
+    }
+}
+
+crate::test_cases! {
+    fn serial_console_escape_sequences() {
+        let mut decoder = EscapeDecoder::new();
+        let mut decode = |bytes: &[u8]| {
+            let mut keys = bytes.iter().filter_map(|byte| decoder.add_byte(*byte));
+            let key = keys.next();
+            assert_eq!(keys.next(), None);
+            key
+        };
+
+        assert_eq!(decode(b"a"), Some(DecodedKey::Unicode('a')));
+        assert_eq!(decode(b"\x1b[A"), Some(DecodedKey::RawKey(KeyCode::ArrowUp)));
+        assert_eq!(decode(b"\x1bOH"), Some(DecodedKey::RawKey(KeyCode::Home)));
+        assert_eq!(decode(b"\x1b[3~"), Some(DecodedKey::RawKey(KeyCode::Delete)));
+        assert_eq!(decode(b"\x7f"), Some(DecodedKey::Unicode('\u{8}')));
+        // A single enter for both \r and \r\n
+        assert_eq!(decode(b"\r\n"), Some(DecodedKey::Unicode('\n')));
+        assert_eq!(decode(b"\r"), Some(DecodedKey::Unicode('\n')));
+        // Unknown sequences are swallowed
+        assert_eq!(decode(b"\x1b[5~"), None);
+    }
+}
@@ -0,0 +1,285 @@
+pub mod console;
+pub mod uart;
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use spin::Mutex;
+use uart::{LineConfig, RawPort, Uart};
+use x86_64::instructions::interrupts;
+
+use crate::{cmdline, println, ring_buffer::RingBuffer};
+
+/// The four legacy serial ports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComPort {
+    Com1,
+    Com2,
+    Com3,
+    Com4,
+}
+
+impl ComPort {
+    pub const ALL: [ComPort; 4] = [ComPort::Com1, ComPort::Com2, ComPort::Com3, ComPort::Com4];
+
+    pub fn base(self) -> u16 {
+        match self {
+            ComPort::Com1 => 0x3F8,
+            ComPort::Com2 => 0x2F8,
+            ComPort::Com3 => 0x3E8,
+            ComPort::Com4 => 0x2E8,
+        }
+    }
+
+    /// COM1 and COM3 share IRQ 4, COM2 and COM4 share IRQ 3
+    pub fn irq(self) -> u8 {
+        match self {
+            ComPort::Com1 | ComPort::Com3 => 4,
+            ComPort::Com2 | ComPort::Com4 => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ComPort::Com1 => "com1",
+            ComPort::Com2 => "com2",
+            ComPort::Com3 => "com3",
+            ComPort::Com4 => "com4",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|port| port.name() == name)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// COM1 is initialized lazily with the default settings by the first write, so
+// it works for debug output long before `init` runs
+static PORTS: [Mutex<Uart>; 4] = [
+    Mutex::new(Uart::new(0x3F8)),
+    Mutex::new(Uart::new(0x2F8)),
+    Mutex::new(Uart::new(0x3E8)),
+    Mutex::new(Uart::new(0x2E8)),
+];
+
+/// Bytes received by the interrupt handlers
+static RECEIVED: [RingBuffer<u8, 1024>; 4] = [
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+];
+
+/// Whether the port exists and its receive interrupt is enabled
+static RECEIVING: [AtomicBool; 4] = [
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+
+/// Locks `port`, e.g. to write to it
+pub fn port(port: ComPort) -> spin::MutexGuard<'static, Uart> {
+    PORTS[port.index()].lock()
+}
+
+/// Locks `port` unless it is already locked
+pub fn try_port(port: ComPort) -> Option<spin::MutexGuard<'static, Uart>> {
+    PORTS[port.index()].try_lock()
+}
+
+/// Unlocks all ports.
+///
+/// # Safety
+/// Whoever holds the locks must never run again, e.g. because the kernel panicked.
+pub(crate) unsafe fn force_unlock() {
+    for port in &PORTS {
+        port.force_unlock();
+    }
+}
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static BAUD_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "serial.baud",
+    kind: crate::cmdline::Kind::Number,
+    help: "baud rate of COM1, 115200 by default",
+};
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static FORMAT_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "serial.format",
+    kind: crate::cmdline::Kind::Text,
+    help: "data bits, parity and stop bits of COM1, e.g. 8N1",
+};
+
+/// Probes all four ports and enables their receive interrupts. COM1 is set up
+/// with `serial.baud` and `serial.format` (e.g. `8N1`) from the command line.
+pub fn init() {
+    let mut com1 = LineConfig::DEFAULT;
+    match cmdline::get_parsed("serial.baud") {
+        Some(0) => log::warn!("invalid baud rate 0"),
+        Some(baud) => com1.baud = baud,
+        None => {}
+    }
+    if let Some(format) = cmdline::get("serial.format") {
+        match com1.with_format(format) {
+            Some(config) => com1 = config,
+            None => log::warn!("invalid line format {:?}", format),
+        }
+    }
+
+    for com in ComPort::ALL {
+        let config = match com {
+            ComPort::Com1 => com1,
+            _ => LineConfig::DEFAULT,
+        };
+        if !configure(com, config) {
+            continue;
+        }
+        log::info!("{} at {}", com.name(), config);
+    }
+
+    console::init();
+}
+
+/// Reprograms `com` and enables its receive interrupt. Returns false if the
+/// port doesn't exist.
+pub fn configure(com: ComPort, config: LineConfig) -> bool {
+    interrupts::without_interrupts(|| {
+        let mut uart = port(com);
+        let present = uart.init(config);
+        uart.set_receive_interrupt(present);
+        RECEIVING[com.index()].store(present, Ordering::Relaxed);
+        present
+    })
+}
+
+pub fn is_present(com: ComPort) -> bool {
+    RECEIVING[com.index()].load(Ordering::Relaxed)
+}
+
+// Called from the interrupt handler of IRQ 3 or 4, drains the ports sharing it
+pub(crate) fn on_interrupt(irq: u8) {
+    for com in ComPort::ALL {
+        if com.irq() != irq || !is_present(com) {
+            continue;
+        }
+        while let Some(byte) = uart::receive(com.base()) {
+            // Dropped if nobody is reading the port
+            let _ = RECEIVED[com.index()].push(byte);
+        }
+    }
+}
+
+/// Returns the next received byte, if there is one
+pub fn read_byte(com: ComPort) -> Option<u8> {
+    RECEIVED[com.index()].pop()
+}
+
+/// Copies the bytes received so far into `buffer`, without blocking.
+/// Returns the number of bytes read.
+pub fn read(com: ComPort, buffer: &mut [u8]) -> usize {
+    let mut count = 0;
+    for slot in buffer.iter_mut() {
+        let Some(byte) = read_byte(com) else {
+            break;
+        };
+        *slot = byte;
+        count += 1;
+    }
+    count
+}
+
+/// Halts until a byte is received. Needs interrupts to be enabled.
+pub fn read_byte_blocking(com: ComPort) -> u8 {
+    loop {
+        interrupts::disable();
+        if let Some(byte) = read_byte(com) {
+            interrupts::enable();
+            return byte;
+        }
+        interrupts::enable_and_hlt();
+    }
+}
+
+pub fn write(com: ComPort, bytes: &[u8]) {
+    let mut uart = port(com);
+    for byte in bytes {
+        uart.send(*byte);
+    }
+}
+
+pub fn write_fmt(com: ComPort, args: fmt::Arguments) {
+    use core::fmt::Write;
+    port(com).write_fmt(args).expect("Writing to serial failed");
+}
+
+/// Like `write_fmt`, but doesn't wait for the port lock. If it is held, e.g.
+/// by the code this interrupted, the output is sent without it.
+pub fn write_fmt_nonblocking(com: ComPort, args: fmt::Arguments) {
+    use core::fmt::Write;
+    let _ = match try_port(com) {
+        Some(mut uart) => uart.write_fmt(args),
+        None => RawPort::new(com.base()).write_fmt(args),
+    };
+}
+
+/// Writes straight to the port, ignoring the lock. For when nothing else
+/// can be trusted, e.g. while panicking.
+pub fn write_fmt_raw(com: ComPort, args: fmt::Arguments) {
+    use core::fmt::Write;
+    let _ = RawPort::new(com.base()).write_fmt(args);
+}
+
+#[linkme::distributed_slice(crate::shell::COMMANDS)]
+static SERIAL: crate::shell::Command = crate::shell::Command {
+    name: "serial",
+    help: "show or change the serial ports: serial [<port> <baud> [format]]",
+    run: serial,
+};
+
+fn serial(args: &[&str]) {
+    match args {
+        [] => {
+            for com in ComPort::ALL {
+                // Not printed while holding the lock, the console may be on this port
+                let config = port(com).config();
+                match config {
+                    Some(config) if is_present(com) => println!("{}: {}", com.name(), config),
+                    _ => println!("{}: not present", com.name()),
+                }
+            }
+        }
+        [name, baud, format @ ..] if format.len() <= 1 => {
+            let Some(com) = ComPort::from_name(name) else {
+                println!("unknown port {}, expected com1 to com4", name);
+                return;
+            };
+            let Ok(baud) = baud.parse::<u32>() else {
+                println!("invalid baud rate {}", baud);
+                return;
+            };
+            let current = port(com).config().unwrap_or(LineConfig::DEFAULT);
+            let mut config = LineConfig { baud, ..current };
+            if let [format] = format {
+                match config.with_format(format) {
+                    Some(new) => config = new,
+                    None => {
+                        println!("invalid line format {}, expected e.g. 8N1", format);
+                        return;
+                    }
+                }
+            }
+            if !configure(com, config) {
+                println!("{}: not present", com.name());
+            }
+        }
+        _ => println!("usage: serial [<port> <baud> [format]]"),
+    }
+}
@@ -0,0 +1,278 @@
+use core::fmt;
+
+use x86_64::instructions::port::Port;
+
+// Register offsets from the base port
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+// With DLAB set, the first two registers hold the baud rate divisor
+const DIVISOR_LOW: u16 = 0;
+const DIVISOR_HIGH: u16 = 1;
+
+// Interrupt enable bits
+const RECEIVE_INTERRUPT: u8 = 1 << 0;
+
+// Line control bits
+const DIVISOR_LATCH: u8 = 1 << 7;
+
+// Enable and clear both FIFOs, interrupt once 14 bytes are queued
+const FIFO_ENABLE: u8 = 0xC7;
+
+// Modem control bits. OUT2 gates the interrupt line on PCs.
+const DTR: u8 = 1 << 0;
+const RTS: u8 = 1 << 1;
+const OUT1: u8 = 1 << 2;
+const OUT2: u8 = 1 << 3;
+const LOOPBACK: u8 = 1 << 4;
+
+// Line status bits
+const DATA_READY: u8 = 1 << 0;
+const TRANSMIT_EMPTY: u8 = 1 << 5;
+
+/// The UART clock divided by 16, the highest possible baud rate
+pub const MAX_BAUD: u32 = 115_200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    Mark,
+    Space,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// Baud rate and frame format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineConfig {
+    pub baud: u32,
+    /// 5 to 8
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl LineConfig {
+    pub const DEFAULT: LineConfig = LineConfig {
+        baud: MAX_BAUD,
+        data_bits: 8,
+        parity: Parity::None,
+        stop_bits: StopBits::One,
+    };
+
+    /// Parses the usual `<data bits><parity><stop bits>` notation, e.g. `8N1`,
+    /// keeping the baud rate
+    pub fn with_format(self, format: &str) -> Option<Self> {
+        let &[data_bits, parity, stop_bits] = format.as_bytes() else {
+            return None;
+        };
+        let data_bits = match data_bits {
+            b'5'..=b'8' => data_bits - b'0',
+            _ => return None,
+        };
+        let parity = match parity.to_ascii_uppercase() {
+            b'N' => Parity::None,
+            b'O' => Parity::Odd,
+            b'E' => Parity::Even,
+            b'M' => Parity::Mark,
+            b'S' => Parity::Space,
+            _ => return None,
+        };
+        let stop_bits = match stop_bits {
+            b'1' => StopBits::One,
+            b'2' => StopBits::Two,
+            _ => return None,
+        };
+        Some(Self {
+            data_bits,
+            parity,
+            stop_bits,
+            ..self
+        })
+    }
+
+    fn divisor(&self) -> u16 {
+        (MAX_BAUD / self.baud.clamp(1, MAX_BAUD)).min(u16::MAX as u32) as u16
+    }
+
+    fn line_control(&self) -> u8 {
+        let data_bits = self.data_bits.clamp(5, 8) - 5;
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 0,
+            StopBits::Two => 1 << 2,
+        };
+        let parity = match self.parity {
+            Parity::None => 0,
+            Parity::Odd => 0b001 << 3,
+            Parity::Even => 0b011 << 3,
+            Parity::Mark => 0b101 << 3,
+            Parity::Space => 0b111 << 3,
+        };
+        data_bits | stop_bits | parity
+    }
+}
+
+impl fmt::Display for LineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+            Parity::Mark => 'M',
+            Parity::Space => 'S',
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        write!(
+            f,
+            "{} baud {}{}{}",
+            self.baud, self.data_bits, parity, stop_bits
+        )
+    }
+}
+
+/// A 16550 compatible UART
+pub struct Uart {
+    base: u16,
+    config: Option<LineConfig>,
+}
+
+impl Uart {
+    pub const fn new(base: u16) -> Self {
+        Self { base, config: None }
+    }
+
+    fn read_register(&self, register: u16) -> u8 {
+        unsafe { Port::new(self.base + register).read() }
+    }
+
+    fn write_register(&mut self, register: u16, value: u8) {
+        unsafe { Port::new(self.base + register).write(value) }
+    }
+
+    /// The line settings, or None if the UART has not been initialized
+    pub fn config(&self) -> Option<LineConfig> {
+        self.config
+    }
+
+    /// Programs the line settings and enables the FIFOs. Returns false if
+    /// there is no working UART at this port.
+    pub fn init(&mut self, config: LineConfig) -> bool {
+        self.write_register(INTERRUPT_ENABLE, 0);
+
+        let divisor = config.divisor();
+        self.write_register(LINE_CONTROL, DIVISOR_LATCH);
+        self.write_register(DIVISOR_LOW, divisor as u8);
+        self.write_register(DIVISOR_HIGH, (divisor >> 8) as u8);
+        self.write_register(LINE_CONTROL, config.line_control());
+
+        self.write_register(FIFO_CONTROL, FIFO_ENABLE);
+
+        // Send a byte to ourselves to check that the chip exists
+        self.write_register(MODEM_CONTROL, RTS | OUT1 | OUT2 | LOOPBACK);
+        self.write_register(DATA, 0xAE);
+        if self.read_register(DATA) != 0xAE {
+            self.config = None;
+            return false;
+        }
+
+        self.write_register(MODEM_CONTROL, DTR | RTS | OUT1 | OUT2);
+        self.config = Some(config);
+        true
+    }
+
+    /// Interrupts on received data. The interrupt handler must then call
+    /// `receive` until it returns None.
+    pub fn set_receive_interrupt(&mut self, enabled: bool) {
+        let value = if enabled { RECEIVE_INTERRUPT } else { 0 };
+        self.write_register(INTERRUPT_ENABLE, value);
+    }
+
+    pub fn send(&mut self, byte: u8) {
+        if self.config.is_none() {
+            self.init(LineConfig::DEFAULT);
+        }
+        send(self.base, byte);
+    }
+
+    /// Returns the next received byte, if there is one
+    pub fn receive(&self) -> Option<u8> {
+        receive(self.base)
+    }
+}
+
+// Receiving only touches the receive registers, so it doesn't need the port lock
+// and can't deadlock with a sender that was interrupted
+pub(super) fn receive(base: u16) -> Option<u8> {
+    let mut line_status: Port<u8> = Port::new(base + LINE_STATUS);
+    if unsafe { line_status.read() } & DATA_READY == 0 {
+        return None;
+    }
+    Some(unsafe { Port::new(base + DATA).read() })
+}
+
+// Sending without the port lock, for when whoever holds it can't be waited for
+fn send(base: u16, byte: u8) {
+    let mut line_status: Port<u8> = Port::new(base + LINE_STATUS);
+    while unsafe { line_status.read() } & TRANSMIT_EMPTY == 0 {
+        core::hint::spin_loop();
+    }
+    unsafe { Port::new(base + DATA).write(byte) }
+}
+
+/// Writes to a UART without locking or initializing it. The output may be
+/// interleaved with that of a locked `Uart` for the same port.
+pub struct RawPort {
+    base: u16,
+}
+
+impl RawPort {
+    pub const fn new(base: u16) -> Self {
+        Self { base }
+    }
+}
+
+impl fmt::Write for RawPort {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            send(self.base, byte);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.send(byte);
+        }
+        Ok(())
+    }
+}
+
+crate::test_cases! {
+    fn uart_line_config() {
+        let config = LineConfig { baud: 9600, ..LineConfig::DEFAULT };
+        assert_eq!(config.divisor(), 12);
+        assert_eq!(config.line_control(), 0b11);
+
+        let config = config.with_format("7e2").unwrap();
+        assert_eq!((config.data_bits, config.parity, config.stop_bits), (7, Parity::Even, StopBits::Two));
+        assert_eq!(config.line_control(), 0b11110);
+        assert_eq!(config.baud, 9600);
+
+        assert_eq!(config.with_format("9N1"), None);
+        assert_eq!(config.with_format("8N"), None);
+    }
+}
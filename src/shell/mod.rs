@@ -0,0 +1,64 @@
+use alloc::vec::Vec;
+
+use crate::{
+    keyboard::line_reader::{FrameBufferEcho, LineReader},
+    println,
+    serial::console::{self, SerialEcho},
+};
+
+const PROMPT: &str = "> ";
+
+/// A shell command, registered by adding it to the `COMMANDS` slice
+pub struct Command {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub run: fn(&[&str]),
+}
+
+#[linkme::distributed_slice]
+pub static COMMANDS: [Command];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Splits `line` into whitespace separated arguments and runs the named command
+pub fn execute(line: &str) {
+    let args: Vec<&str> = line.split_whitespace().collect();
+    let Some((name, args)) = args.split_first() else {
+        return;
+    };
+
+    match find(name) {
+        Some(command) => (command.run)(args),
+        None => println!("{}: command not found", name),
+    }
+}
+
+/// Reads commands from the keyboard (and the serial console, if enabled) and
+/// runs them, forever
+pub fn run() -> ! {
+    let mut reader = LineReader::new();
+    loop {
+        let line = if console::is_enabled() {
+            let mut echo = (FrameBufferEcho::new(), SerialEcho);
+            reader.read_line_with(PROMPT, &mut echo, console::read_key)
+        } else {
+            reader.read_line(PROMPT)
+        };
+        execute(&line);
+    }
+}
+
+#[linkme::distributed_slice(COMMANDS)]
+static HELP: Command = Command {
+    name: "help",
+    help: "list the available commands",
+    run: help,
+};
+
+fn help(_args: &[&str]) {
+    for command in COMMANDS {
+        println!("{:<10} {}", command.name, command.help);
+    }
+}
@@ -0,0 +1,98 @@
+use core::{
+    ptr::{addr_of, addr_of_mut},
+    sync::atomic::{AtomicU16, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use x86_64::instructions::interrupts;
+
+use super::recovery::{longjmp, setjmp, JumpBuffer};
+use crate::{clock, interrupts::InterruptType, timer};
+
+// Vector of the exception `expect_exception` is waiting for
+const NOTHING: u16 = u16::MAX;
+static EXPECTED: AtomicU16 = AtomicU16::new(NOTHING);
+
+// Error code of the caught exception
+const NO_ERROR_CODE: u64 = u64::MAX;
+static ERROR_CODE: AtomicU64 = AtomicU64::new(NO_ERROR_CODE);
+
+// Where the exception handler jumps back to, see `catch`
+static mut CATCH_POINT: JumpBuffer = JumpBuffer::new();
+
+/// Runs `f`, which must raise the exception `expected`, and returns the error
+/// code, if the exception has one. The test fails if `f` returns or raises
+/// another exception. Whatever `f` was doing is abandoned, like after a panic.
+pub fn expect_exception(expected: InterruptType, f: impl FnOnce()) -> Option<u64> {
+    let interrupts_enabled = interrupts::are_enabled();
+    let caught = catch(expected.into(), f);
+    // The exception handler disabled them
+    if interrupts_enabled {
+        interrupts::enable();
+    }
+    assert!(
+        caught,
+        "expected a {:?} exception, but there was none",
+        expected
+    );
+
+    match ERROR_CODE.load(Ordering::Relaxed) {
+        NO_ERROR_CODE => None,
+        error_code => Some(error_code),
+    }
+}
+
+// Runs `f`, returning whether the exception was caught. Kept apart, like
+// `run_recoverable`, so that no locals are live across the second return.
+#[inline(never)]
+fn catch(vector: u8, f: impl FnOnce()) -> bool {
+    // SAFETY: Sound for the same reasons as in `run_recoverable`, the second
+    // return only returns `true`, so no local is read after it
+    if unsafe { setjmp(addr_of_mut!(CATCH_POINT)) } != 0 {
+        return true;
+    }
+    ERROR_CODE.store(NO_ERROR_CODE, Ordering::Relaxed);
+    EXPECTED.store(vector.into(), Ordering::Relaxed);
+    f();
+    EXPECTED.store(NOTHING, Ordering::Relaxed);
+    false
+}
+
+/// Called for fatal exceptions. Jumps back into `expect_exception` if it is
+/// waiting for this one, returns otherwise.
+pub(super) fn on_exception(vector: u8, error_code: Option<u64>) {
+    if EXPECTED.load(Ordering::Relaxed) != u16::from(vector) {
+        return;
+    }
+    EXPECTED.store(NOTHING, Ordering::Relaxed);
+    ERROR_CODE.store(error_code.unwrap_or(NO_ERROR_CODE), Ordering::Relaxed);
+    unsafe { longjmp(addr_of!(CATCH_POINT), 1) }
+}
+
+/// Forgets the expected exception, after a test failed while waiting for it
+pub(super) fn reset() {
+    EXPECTED.store(NOTHING, Ordering::Relaxed);
+}
+
+/// Waits for `ticks` timer interrupts, failing the test if they take longer
+/// than `timeout`. Only works in `#[interrupts]` tests. Without an invariant
+/// TSC the clock counts ticks itself, so a stuck timer hangs instead.
+pub fn expect_ticks(ticks: u64, timeout: Duration) {
+    assert!(
+        interrupts::are_enabled(),
+        "waiting for timer ticks needs an #[interrupts] test"
+    );
+    let start = timer::ticks();
+    let deadline = clock::now() + timeout;
+    while timer::ticks() - start < ticks {
+        // Spins rather than halts, a stuck timer would never wake us up
+        assert!(
+            clock::now() < deadline,
+            "only {} of {} timer ticks within {:?}",
+            timer::ticks() - start,
+            ticks,
+            timeout
+        );
+        core::hint::spin_loop();
+    }
+}
@@ -0,0 +1,357 @@
+// pub mod vga_buffer;
+
+pub mod expect;
+pub mod recovery;
+pub mod report;
+
+pub use expect::{expect_exception, expect_ticks};
+
+use alloc::{format, string::String};
+use core::{
+    fmt::{self, Write},
+    panic::PanicInfo,
+    ptr::{addr_of, addr_of_mut},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use recovery::{longjmp, setjmp, JumpBuffer};
+use report::{Format, Reporter, Status, Summary, TestResult};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::{
+    backtrace::Backtrace,
+    clock, cmdline,
+    framebuffer::crash::Crash,
+    heap, logger,
+    panic::{exit_qemu, QemuExitCode},
+    serial_println,
+};
+
+#[linkme::distributed_slice]
+pub static TESTS: [Test];
+
+/// A test registered by `test_cases!`
+pub struct Test {
+    pub name: &'static str,
+    pub module: &'static str,
+    pub run: fn(),
+    /// Passes only if it panics
+    pub should_panic: bool,
+    /// Skipped unless the filter asks for it
+    pub ignored: bool,
+    /// Runs with interrupts enabled, instead of disabled
+    pub interrupts: bool,
+}
+
+impl Test {
+    pub const fn new(name: &'static str, module: &'static str, run: fn()) -> Self {
+        Self {
+            name,
+            module,
+            run,
+            should_panic: false,
+            ignored: false,
+            interrupts: false,
+        }
+    }
+
+    // The attributes `test_cases!` accepts
+    pub const fn should_panic(self) -> Self {
+        Self {
+            should_panic: true,
+            ..self
+        }
+    }
+
+    pub const fn ignore(self) -> Self {
+        Self {
+            ignored: true,
+            ..self
+        }
+    }
+
+    pub const fn interrupts(self) -> Self {
+        Self {
+            interrupts: true,
+            ..self
+        }
+    }
+}
+
+// Index of the test that is running, the panic handler needs to know which
+// one panicked. An atomic rather than a lock, since the panic can happen anywhere.
+const NOT_RUNNING: usize = usize::MAX;
+static CURRENT: AtomicUsize = AtomicUsize::new(NOT_RUNNING);
+
+// Why the running test failed, written by the panic handler, which shouldn't
+// allocate
+static FAILURE: Mutex<Message> = Mutex::new(Message::new());
+
+// Where a failing test jumps back to, see `run_recoverable`
+static mut RECOVERY_POINT: JumpBuffer = JumpBuffer::new();
+
+// Values passed to `longjmp`, telling `run_recoverable` how the test ended
+const PANICKED: u64 = 1;
+const CRASHED: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Returned,
+    Panicked,
+    // A fatal exception
+    Crashed,
+}
+
+// A fixed size text buffer, longer messages are cut off
+struct Message {
+    bytes: [u8; 4096],
+    len: usize,
+}
+
+impl Message {
+    const fn new() -> Self {
+        Self {
+            bytes: [0; 4096],
+            len: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for Message {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut end = s.len().min(self.bytes.len() - self.len);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        Ok(())
+    }
+}
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static FILTER_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "test.filter",
+    kind: crate::cmdline::Kind::Text,
+    help: "only run the tests whose path contains one of these comma separated patterns",
+};
+
+#[linkme::distributed_slice(crate::cmdline::PARAMS)]
+static FORMAT_PARAM: crate::cmdline::Param = crate::cmdline::Param {
+    name: "test.format",
+    kind: crate::cmdline::Kind::Choice(&["pretty", "tap", "junit"]),
+    help: "how test results are printed to the serial port",
+};
+
+/// Runs the tests and exits QEMU with the result.
+///
+/// Options on the command line:
+/// - `test.filter=<patterns>` only runs the tests whose path contains one of
+///   the comma separated patterns. Naming an ignored test runs it anyway.
+/// - `test.format=pretty|tap|junit` picks the output format.
+pub fn test_runner() {
+    let format = cmdline::get("test.format")
+        .and_then(Format::from_name)
+        .unwrap_or(Format::Pretty);
+    let filter = cmdline::get("test.filter");
+    let selected = |test: &Test| match filter {
+        Some(filter) => {
+            let path = format!("{}::{}", test.module, test.name);
+            filter.split(',').any(|pattern| path.contains(pattern))
+        }
+        None => true,
+    };
+
+    let mut summary = Summary {
+        filtered_out: TESTS.iter().filter(|test| !selected(test)).count(),
+        ..Summary::default()
+    };
+    let mut reporter = Reporter::new(format);
+    reporter.start(TESTS.len() - summary.filtered_out);
+
+    for (index, test) in TESTS.iter().enumerate() {
+        if !selected(test) {
+            continue;
+        }
+        reporter.started(test);
+
+        let result = if test.ignored && filter.is_none() {
+            TestResult {
+                test,
+                status: Status::Ignored,
+                duration: Duration::ZERO,
+                message: None,
+            }
+        } else {
+            run(index, test)
+        };
+        match result.status {
+            Status::Passed => summary.passed += 1,
+            Status::Failed => summary.failed += 1,
+            Status::Ignored => summary.ignored += 1,
+        }
+        reporter.finished(result);
+    }
+
+    reporter.finish(&summary);
+    exit_qemu(if summary.failed == 0 {
+        QemuExitCode::Success
+    } else {
+        QemuExitCode::Failed
+    });
+}
+
+// Runs a test, recovering if it panics or crashes
+fn run(index: usize, test: &'static Test) -> TestResult {
+    let interrupts_enabled = interrupts::are_enabled();
+    let heap_used = heap::used();
+    let start = clock::now();
+
+    CURRENT.store(index, Ordering::Relaxed);
+    if test.interrupts {
+        interrupts::enable();
+    } else {
+        interrupts::disable();
+    }
+    let outcome = run_recoverable(test.run);
+    // Whatever the test did to them, or the exception that ended it
+    if interrupts_enabled {
+        interrupts::enable();
+    } else {
+        interrupts::disable();
+    }
+    CURRENT.store(NOT_RUNNING, Ordering::Relaxed);
+    let duration = start.elapsed();
+
+    let mut message = None;
+    if outcome != Outcome::Returned {
+        reset_after_failure();
+        let mut text = String::from(FAILURE.lock().as_str());
+        // Nothing the test allocated was dropped
+        let leaked = heap::used().saturating_sub(heap_used);
+        if leaked > 0 {
+            let _ = write!(text, "\n(leaked {} bytes of heap)", leaked);
+        }
+        message = Some(text);
+    }
+
+    let status = match (outcome, test.should_panic) {
+        (Outcome::Returned, false) | (Outcome::Panicked, true) => Status::Passed,
+        (Outcome::Returned, true) => {
+            message = Some(String::from("did not panic"));
+            Status::Failed
+        }
+        _ => Status::Failed,
+    };
+    TestResult {
+        test,
+        status,
+        duration,
+        message: message.filter(|_| status == Status::Failed),
+    }
+}
+
+// Runs `run`, the panic handler and the crash report jump back in here if it
+// fails. Kept apart so that no locals are live across the second return.
+#[inline(never)]
+fn run_recoverable(run: fn()) -> Outcome {
+    // SAFETY: Rust has no `returns_twice`, so the compiler treats `setjmp` as
+    // an ordinary call. After the second return the callee saved registers hold
+    // their values from the call and stack slots their latest ones, so a local
+    // changed between the two returns could be read stale. None is, that path
+    // only looks at the returned value. `longjmp` only comes from below this
+    // frame, which stays alive, and `#[inline(never)]` keeps the caller's locals
+    // out of it.
+    match unsafe { setjmp(addr_of_mut!(RECOVERY_POINT)) } {
+        0 => {}
+        PANICKED => return Outcome::Panicked,
+        _ => return Outcome::Crashed,
+    }
+    run();
+    Outcome::Returned
+}
+
+// Undoes what a test that didn't return may have left behind
+fn reset_after_failure() {
+    crate::panic::recovered();
+    expect::reset();
+    unsafe {
+        logger::force_unlock();
+        heap::force_unlock();
+    }
+}
+
+// Records why the test failed and jumps back to the runner, or exits QEMU if
+// no test is running
+fn fail(value: u64, report: fmt::Arguments) -> ! {
+    if CURRENT.load(Ordering::Relaxed) == NOT_RUNNING {
+        serial_println!("failed outside of a test\n{}", report);
+        exit_qemu(QemuExitCode::Failed);
+        crate::hlt_loop();
+    }
+    if let Some(mut failure) = FAILURE.try_lock() {
+        failure.clear();
+        let _ = failure.write_fmt(report);
+    }
+    unsafe { longjmp(addr_of!(RECOVERY_POINT), value) }
+}
+
+/// Called by the panic handler. The test fails, unless it is `#[should_panic]`,
+/// and the runner carries on with the next one.
+pub fn on_panic(info: &PanicInfo, backtrace: &Backtrace) -> ! {
+    fail(PANICKED, format_args!("{}\n{}", info, backtrace))
+}
+
+/// Called for fatal exceptions, which fail the test unless it expects them
+pub fn on_crash(crash: &Crash) -> ! {
+    if let Some((vector, error_code)) = crash.exception {
+        expect::on_exception(vector, error_code);
+    }
+    fail(CRASHED, format_args!("{}", crash))
+}
+
+#[macro_export]
+macro_rules! test_cases {
+    // Base case: When no more functions are left, do nothing.
+    () => {};
+
+    // Recursive case: Take one function and then recursively call the macro with the remaining functions.
+    // The attributes (`#[should_panic]`, `#[ignore]`, `#[interrupts]`) become calls to the builder
+    // methods of `Test`. The runner disables interrupts, unless the test has `#[interrupts]`.
+    (
+        $(#[$attribute:ident])*
+        fn $test_name:ident() $body:block
+        $($rest:tt)*
+    ) => {
+        crate::test_cases! { $($rest)* } // Recursively process the remaining functions
+
+        #[linkme::distributed_slice(crate::tests::TESTS)]
+        #[allow(non_upper_case_globals)]
+        static $test_name: crate::tests::Test = crate::tests::Test::new(
+            stringify!($test_name),
+            module_path!(),
+            {
+                fn run() $body
+                run
+            },
+        )
+        $(.$attribute())*;
+    };
+}
+
+crate::test_cases! {
+    #[should_panic]
+    fn should_panic_passes_on_panic() {
+        panic!("expected");
+    }
+}
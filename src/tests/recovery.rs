@@ -0,0 +1,91 @@
+use core::arch::asm;
+
+/// What `setjmp` saves: the callee saved registers, the stack pointer and the
+/// address to continue at. The caller saved registers are clobbered by the call
+/// anyway.
+#[repr(C)]
+pub struct JumpBuffer {
+    rbx: u64,
+    rbp: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+    rsp: u64,
+    rip: u64,
+}
+
+impl JumpBuffer {
+    pub const fn new() -> Self {
+        Self {
+            rbx: 0,
+            rbp: 0,
+            r12: 0,
+            r13: 0,
+            r14: 0,
+            r15: 0,
+            rsp: 0,
+            rip: 0,
+        }
+    }
+}
+
+impl Default for JumpBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Saves the registers to `buffer` and returns 0. A `longjmp` to the buffer
+/// returns from this call again, with the value passed to it.
+///
+/// # Safety
+/// `longjmp` may only be called while the function that called `setjmp` is
+/// still running. Its locals may be stale after the second return.
+#[naked]
+pub unsafe extern "C" fn setjmp(buffer: *mut JumpBuffer) -> u64 {
+    asm!(
+        "
+        mov [rdi], rbx
+        mov [rdi + 8], rbp
+        mov [rdi + 16], r12
+        mov [rdi + 24], r13
+        mov [rdi + 32], r14
+        mov [rdi + 40], r15
+
+        // the stack pointer after returning, and the return address
+        lea rdx, [rsp + 8]
+        mov [rdi + 48], rdx
+        mov rdx, [rsp]
+        mov [rdi + 56], rdx
+
+        xor eax, eax
+        ret
+        ",
+        options(noreturn)
+    )
+}
+
+/// Returns from the `setjmp` call that filled `buffer` again, with `value`,
+/// abandoning everything that was called since
+///
+/// # Safety
+/// See `setjmp`. Nothing on the abandoned part of the stack is dropped.
+#[naked]
+pub unsafe extern "C" fn longjmp(buffer: *const JumpBuffer, value: u64) -> ! {
+    asm!(
+        "
+        mov rbx, [rdi]
+        mov rbp, [rdi + 8]
+        mov r12, [rdi + 16]
+        mov r13, [rdi + 24]
+        mov r14, [rdi + 32]
+        mov r15, [rdi + 40]
+        mov rsp, [rdi + 48]
+
+        mov rax, rsi
+        jmp [rdi + 56]
+        ",
+        options(noreturn)
+    )
+}
@@ -0,0 +1,249 @@
+use alloc::{string::String, vec::Vec};
+use core::{fmt, time::Duration};
+
+use super::Test;
+use crate::{serial_print, serial_println};
+
+/// How the results are printed to the serial port, `test.format=` on the
+/// command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `name... [ok]`, for people
+    Pretty,
+    /// Test Anything Protocol, version 13
+    Tap,
+    /// JUnit XML, printed at the end, since the totals come first
+    Junit,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pretty" => Some(Format::Pretty),
+            "tap" => Some(Format::Tap),
+            "junit" => Some(Format::Junit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+pub struct TestResult {
+    pub test: &'static Test,
+    pub status: Status,
+    pub duration: Duration,
+    /// Why it failed
+    pub message: Option<String>,
+}
+
+/// Prints the results in the chosen format
+pub struct Reporter {
+    format: Format,
+    // Number of the next test in TAP
+    number: usize,
+    // Kept for JUnit
+    results: Vec<TestResult>,
+}
+
+impl Reporter {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            number: 1,
+            results: Vec::new(),
+        }
+    }
+
+    /// Before the first test, with the number of tests that will run
+    pub fn start(&mut self, count: usize) {
+        match self.format {
+            Format::Pretty => {
+                serial_println!("Running {} tests\n", count);
+            }
+            Format::Tap => {
+                serial_println!("TAP version 13\n1..{}", count);
+            }
+            Format::Junit => {}
+        }
+    }
+
+    /// Before a test runs. Only the pretty format prints its name right away,
+    /// so that a test that hangs can be seen.
+    pub fn started(&mut self, test: &Test) {
+        if self.format == Format::Pretty {
+            serial_print!("{}::{}...\t", test.module, test.name);
+        }
+    }
+
+    pub fn finished(&mut self, result: TestResult) {
+        match self.format {
+            Format::Pretty => match result.status {
+                Status::Passed => {
+                    serial_println!("[ok]\n");
+                }
+                Status::Ignored => {
+                    serial_println!("[ignored]\n");
+                }
+                Status::Failed => {
+                    serial_println!("[failed]\n{}\n", result.message.as_deref().unwrap_or(""));
+                }
+            },
+            Format::Tap => {
+                self.print_tap(&result);
+                self.number += 1;
+            }
+            Format::Junit => self.results.push(result),
+        }
+    }
+
+    fn print_tap(&self, result: &TestResult) {
+        let test = result.test;
+        let status = match result.status {
+            Status::Failed => "not ok",
+            _ => "ok",
+        };
+        let skip = match result.status {
+            Status::Ignored => " # SKIP ignored",
+            _ => "",
+        };
+        serial_println!(
+            "{} {} - {}::{}{}",
+            status,
+            self.number,
+            test.module,
+            test.name,
+            skip
+        );
+        if result.status == Status::Ignored {
+            return;
+        }
+
+        // A YAML block with the details
+        serial_println!("  ---");
+        serial_println!("  module: {}", test.module);
+        serial_println!(
+            "  duration_ms: {:.3}",
+            result.duration.as_secs_f64() * 1000.0
+        );
+        if let Some(message) = &result.message {
+            serial_println!("  message: |");
+            for line in message.lines() {
+                serial_println!("    {}", line);
+            }
+        }
+        serial_println!("  ...");
+    }
+
+    /// After the last test, with the counts of the tests that didn't run
+    pub fn finish(&mut self, summary: &Summary) {
+        match self.format {
+            Format::Pretty => {
+                serial_println!("test result: {}", summary);
+            }
+            Format::Tap => {
+                serial_println!("# {}", summary);
+            }
+            Format::Junit => self.print_junit(summary),
+        }
+    }
+
+    fn print_junit(&self, summary: &Summary) {
+        let time: Duration = self.results.iter().map(|result| result.duration).sum();
+        serial_println!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        serial_println!("<testsuites>");
+        serial_println!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.6}\">",
+            env!("CARGO_PKG_NAME"),
+            self.results.len(),
+            summary.failed,
+            summary.ignored,
+            time.as_secs_f64()
+        );
+        for result in &self.results {
+            let test = result.test;
+            serial_print!(
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.6}\"",
+                Xml(test.module),
+                Xml(test.name),
+                result.duration.as_secs_f64()
+            );
+            match (result.status, &result.message) {
+                (Status::Passed, _) => {
+                    serial_println!("/>");
+                }
+                (Status::Ignored, _) => {
+                    serial_println!("><skipped/></testcase>");
+                }
+                (Status::Failed, message) => {
+                    let message = message.as_deref().unwrap_or("");
+                    serial_println!(
+                        "><failure message=\"{}\">{}</failure></testcase>",
+                        Xml(message.lines().next().unwrap_or("")),
+                        Xml(message)
+                    );
+                }
+            }
+        }
+        serial_println!("  </testsuite>");
+        serial_println!("</testsuites>");
+    }
+}
+
+/// The counts of a test run
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub filtered_out: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}. {} passed; {} failed; {} ignored; {} filtered out",
+            if self.failed == 0 { "ok" } else { "FAILED" },
+            self.passed,
+            self.failed,
+            self.ignored,
+            self.filtered_out
+        )
+    }
+}
+
+// Escapes text for XML attributes and elements
+struct Xml<'a>(&'a str);
+
+impl fmt::Display for Xml<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '&' => f.write_str("&amp;")?,
+                '"' => f.write_str("&quot;")?,
+                '\n' => f.write_str("&#10;")?,
+                // Not allowed in XML 1.0, e.g. the escape sequences of colored logs
+                c if c.is_control() && c != '\t' => {}
+                c => fmt::Write::write_char(f, c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+crate::test_cases! {
+    fn xml_escaping() {
+        use alloc::format;
+
+        assert_eq!(format!("{}", Xml("a < b && \"c\"")), "a &lt; b &amp;&amp; &quot;c&quot;");
+        assert_eq!(format!("{}", Xml("one\ntwo\x1b[0m")), "one&#10;two[0m");
+    }
+}
@@ -0,0 +1,89 @@
+use core::time::Duration;
+
+use spin::Once;
+
+use crate::{
+    acpi::{self, GenericAddress},
+    memory::frame::FrameAllocator,
+    paging::{self, mapper::Mapper},
+};
+
+const GENERAL_CAPABILITIES: usize = 0x000;
+const GENERAL_CONFIGURATION: usize = 0x010;
+const MAIN_COUNTER: usize = 0x0F0;
+
+const ENABLE_CNF: u64 = 1 << 0;
+
+const FEMTOSECONDS_PER_SECOND: u64 = 1_000_000_000_000_000;
+
+/// The main counter of the High Precision Event Timer
+pub struct Hpet {
+    base: usize,
+    period_fs: u64,
+}
+
+static HPET: Once<Hpet> = Once::new();
+
+impl Hpet {
+    fn read(&self, register: usize) -> u64 {
+        unsafe { core::ptr::read_volatile((self.base + register) as *const u64) }
+    }
+
+    fn write(&self, register: usize, value: u64) {
+        unsafe { core::ptr::write_volatile((self.base + register) as *mut u64, value) }
+    }
+
+    pub fn counter(&self) -> u64 {
+        self.read(MAIN_COUNTER)
+    }
+
+    /// Length of one counter tick in femtoseconds
+    pub fn period_fs(&self) -> u64 {
+        self.period_fs
+    }
+
+    pub fn frequency(&self) -> u64 {
+        FEMTOSECONDS_PER_SECOND / self.period_fs
+    }
+
+    pub fn busy_wait(&self, duration: Duration) {
+        let ticks = (duration.as_nanos() * 1_000_000 / self.period_fs as u128) as u64;
+        let start = self.counter();
+        while self.counter().wrapping_sub(start) < ticks {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+pub fn get() -> Option<&'static Hpet> {
+    HPET.get()
+}
+
+/// Maps the HPET described by the ACPI tables and starts its main counter
+pub fn init(mapper: &mut Mapper, allocator: &mut impl FrameAllocator) -> bool {
+    let Some(table) = acpi::tables().and_then(|tables| tables.hpet.as_ref()) else {
+        return false;
+    };
+    let address = table.base_address;
+    let address_space = address.address_space;
+    if address_space != GenericAddress::SYSTEM_MEMORY {
+        return false;
+    }
+
+    let base = address.address as usize;
+    paging::map_mmio(base, mapper, allocator);
+
+    let capabilities =
+        unsafe { core::ptr::read_volatile((base + GENERAL_CAPABILITIES) as *const u64) };
+    let period_fs = capabilities >> 32;
+    if period_fs == 0 {
+        return false;
+    }
+
+    let hpet = HPET.call_once(|| Hpet { base, period_fs });
+
+    // Start the main counter, without legacy replacement routing
+    let configuration = hpet.read(GENERAL_CONFIGURATION);
+    hpet.write(GENERAL_CONFIGURATION, configuration | ENABLE_CNF);
+    true
+}
@@ -0,0 +1,141 @@
+pub mod hpet;
+pub mod pit;
+pub mod wheel;
+
+use alloc::boxed::Box;
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use spin::Mutex;
+use wheel::{TimerId, TimerWheel};
+use x86_64::instructions::interrupts;
+
+/// Requested frequency of the timer interrupt
+pub const TICK_FREQUENCY: u32 = 1000;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static FREQUENCY: AtomicU64 = AtomicU64::new(TICK_FREQUENCY as u64);
+static WHEEL: Mutex<TimerWheel> = Mutex::new(TimerWheel::new());
+
+pub fn init() {
+    let frequency = pit::set_frequency(TICK_FREQUENCY);
+    FREQUENCY.store(frequency as u64, Ordering::Relaxed);
+}
+
+// Called from the timer interrupt handler
+pub(crate) fn on_tick() {
+    let now = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    TimerWheel::advance(&WHEEL, now);
+}
+
+/// Number of timer interrupts since boot
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// The frequency (in Hz) the timer interrupt actually fires at
+pub fn frequency() -> u64 {
+    FREQUENCY.load(Ordering::Relaxed)
+}
+
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::from_nanos((ticks as u128 * 1_000_000_000 / frequency() as u128) as u64)
+}
+
+/// Rounds up, so waiting for the returned number of ticks takes at least `duration`
+pub fn duration_to_ticks(duration: Duration) -> u64 {
+    let ticks = duration.as_nanos() * frequency() as u128;
+    ticks.div_ceil(1_000_000_000) as u64
+}
+
+/// Time since the timer was started, with the resolution of one tick
+pub fn uptime() -> Duration {
+    ticks_to_duration(ticks())
+}
+
+/// Halts the CPU until `duration` has passed. With interrupts disabled no ticks
+/// arrive, so this falls back to polling the PIT.
+pub fn sleep(duration: Duration) {
+    if !interrupts::are_enabled() {
+        pit::busy_wait(duration);
+        return;
+    }
+
+    // One extra tick, since the current one has already partially elapsed
+    let target = ticks() + duration_to_ticks(duration) + 1;
+    while ticks() < target {
+        x86_64::instructions::hlt();
+    }
+}
+
+/// Runs `callback` once, after `delay`. The callback runs in interrupt context,
+/// so it must not block or take locks that are held with interrupts enabled.
+pub fn schedule_once(delay: Duration, callback: impl FnMut() + Send + 'static) -> TimerId {
+    let deadline = ticks() + duration_to_ticks(delay);
+    interrupts::without_interrupts(|| WHEEL.lock().schedule(deadline, None, Box::new(callback)))
+}
+
+/// Runs `callback` every `interval`, starting one interval from now
+pub fn schedule_periodic(interval: Duration, callback: impl FnMut() + Send + 'static) -> TimerId {
+    let period = duration_to_ticks(interval);
+    let deadline = ticks() + period;
+    interrupts::without_interrupts(|| {
+        WHEEL
+            .lock()
+            .schedule(deadline, Some(period), Box::new(callback))
+    })
+}
+
+pub fn cancel(id: TimerId) -> bool {
+    interrupts::without_interrupts(|| WHEEL.lock().cancel(id))
+}
+
+crate::test_cases! {
+    fn timer_wheel_expiry() {
+        use core::sync::atomic::AtomicUsize;
+
+        static ONCE: AtomicUsize = AtomicUsize::new(0);
+        static PERIODIC: AtomicUsize = AtomicUsize::new(0);
+
+        let timers = Mutex::new(TimerWheel::new());
+        timers.lock().schedule(5, None, Box::new(|| {
+            ONCE.fetch_add(1, Ordering::Relaxed);
+        }));
+        let id = timers.lock().schedule(3, Some(3), Box::new(|| {
+            PERIODIC.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        TimerWheel::advance(&timers, 4);
+        assert_eq!(ONCE.load(Ordering::Relaxed), 0);
+        assert_eq!(PERIODIC.load(Ordering::Relaxed), 1);
+
+        // Skipping ticks still fires everything that expired in between
+        TimerWheel::advance(&timers, 10);
+        assert_eq!(ONCE.load(Ordering::Relaxed), 1);
+        assert_eq!(PERIODIC.load(Ordering::Relaxed), 3);
+
+        assert!(timers.lock().cancel(id));
+        TimerWheel::advance(&timers, 600);
+        assert_eq!(PERIODIC.load(Ordering::Relaxed), 3);
+        assert_eq!(timers.lock().len(), 0);
+    }
+
+    fn timer_wheel_wraps_around() {
+        use core::sync::atomic::AtomicUsize;
+
+        static FIRED: AtomicUsize = AtomicUsize::new(0);
+
+        let timers = Mutex::new(TimerWheel::new());
+        // Lands in the same slot as tick 1, but one full rotation later
+        timers.lock().schedule(1 + wheel::WHEEL_SLOTS as u64, None, Box::new(|| {
+            FIRED.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        TimerWheel::advance(&timers, 1);
+        assert_eq!(FIRED.load(Ordering::Relaxed), 0);
+        TimerWheel::advance(&timers, 1 + wheel::WHEEL_SLOTS as u64);
+        assert_eq!(FIRED.load(Ordering::Relaxed), 1);
+    }
+}
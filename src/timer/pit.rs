@@ -0,0 +1,70 @@
+use core::{
+    sync::atomic::{AtomicU16, Ordering},
+    time::Duration,
+};
+
+use x86_64::instructions::port::Port;
+
+/// The frequency of the oscillator driving the 8253/8254 PIT
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+const CHANNEL0_DATA: u16 = 0x40;
+const COMMAND: u16 = 0x43;
+
+// Channel 0, access mode lobyte/hibyte, mode 2 (rate generator), binary
+const CHANNEL0_RATE_GENERATOR: u8 = 0b0011_0100;
+// Channel 0, latch count value command
+const CHANNEL0_LATCH: u8 = 0b0000_0000;
+
+static DIVISOR: AtomicU16 = AtomicU16::new(0);
+
+/// Programs channel 0 to fire IRQ0 `frequency` times per second, returns the
+/// frequency that was actually set
+pub fn set_frequency(frequency: u32) -> u32 {
+    let divisor = (PIT_FREQUENCY / frequency).clamp(1, u16::MAX as u32) as u16;
+    let mut command: Port<u8> = Port::new(COMMAND);
+    let mut data: Port<u8> = Port::new(CHANNEL0_DATA);
+    unsafe {
+        command.write(CHANNEL0_RATE_GENERATOR);
+        data.write(divisor as u8);
+        data.write((divisor >> 8) as u8);
+    }
+    DIVISOR.store(divisor, Ordering::Relaxed);
+    PIT_FREQUENCY / divisor as u32
+}
+
+/// The current value of the channel 0 down counter
+pub fn read_count() -> u16 {
+    let mut command: Port<u8> = Port::new(COMMAND);
+    let mut data: Port<u8> = Port::new(CHANNEL0_DATA);
+    unsafe {
+        command.write(CHANNEL0_LATCH);
+        let low = data.read() as u16;
+        let high = data.read() as u16;
+        (high << 8) | low
+    }
+}
+
+/// Waits for `duration` by polling the channel 0 counter, works with interrupts disabled
+pub fn busy_wait(duration: Duration) {
+    let divisor = match DIVISOR.load(Ordering::Relaxed) {
+        // A divisor of 0 means 65536 to the PIT
+        0 => 0x1_0000,
+        divisor => divisor as u64,
+    };
+    let target = duration.as_nanos() as u64 * PIT_FREQUENCY as u64 / 1_000_000_000;
+
+    let mut elapsed = 0;
+    let mut last = read_count() as u64;
+    while elapsed < target {
+        let count = read_count() as u64;
+        // The counter counts down and reloads with the divisor
+        elapsed += if count <= last {
+            last - count
+        } else {
+            last + divisor - count
+        };
+        last = count;
+        core::hint::spin_loop();
+    }
+}
@@ -0,0 +1,140 @@
+use alloc::{boxed::Box, vec::Vec};
+
+pub const WHEEL_SLOTS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerId(u64);
+
+struct Timer {
+    id: TimerId,
+    deadline: u64,
+    period: Option<u64>,
+    callback: Box<dyn FnMut() + Send>,
+}
+
+/// A hashed timing wheel, timers are bucketed by their deadline tick modulo the
+/// number of slots, so advancing by one tick only looks at a single slot
+pub struct TimerWheel {
+    slots: Vec<Vec<Timer>>,
+    current: u64,
+    next_id: u64,
+}
+
+impl TimerWheel {
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            current: 0,
+            next_id: 0,
+        }
+    }
+
+    fn insert(&mut self, timer: Timer) {
+        // The slots are allocated lazily, so the wheel can live in a static
+        if self.slots.is_empty() {
+            self.slots.resize_with(WHEEL_SLOTS, Vec::new);
+        }
+        let slot = (timer.deadline % WHEEL_SLOTS as u64) as usize;
+        self.slots[slot].push(timer);
+    }
+
+    /// Schedules `callback` to run once the wheel reaches tick `deadline`,
+    /// and then every `period` ticks if given
+    pub fn schedule(
+        &mut self,
+        deadline: u64,
+        period: Option<u64>,
+        callback: Box<dyn FnMut() + Send>,
+    ) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        // Timers in the past fire on the next tick
+        let deadline = deadline.max(self.current + 1);
+        let period = period.map(|period| period.max(1));
+        self.insert(Timer {
+            id,
+            deadline,
+            period,
+            callback,
+        });
+        id
+    }
+
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        for slot in self.slots.iter_mut() {
+            if let Some(index) = slot.iter().position(|timer| timer.id == id) {
+                slot.swap_remove(index);
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().map(|slot| slot.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|slot| slot.is_empty())
+    }
+
+    /// Moves the wheel forward to tick `now`, returning the timers that expired
+    fn expire(&mut self, now: u64) -> Vec<Timer> {
+        let mut expired = Vec::new();
+        if self.slots.is_empty() {
+            self.current = now;
+            return expired;
+        }
+
+        // Process every tick we missed, not just the last one
+        while self.current < now {
+            self.current += 1;
+            let slot = &mut self.slots[(self.current % WHEEL_SLOTS as u64) as usize];
+            let mut index = 0;
+            while index < slot.len() {
+                if slot[index].deadline <= self.current {
+                    expired.push(slot.swap_remove(index));
+                } else {
+                    index += 1;
+                }
+            }
+        }
+        expired
+    }
+
+    /// Reinserts a periodic timer after its callback ran. Returns it instead
+    /// if its next deadline has passed too, since ticks were skipped, so it
+    /// fires once for every period missed.
+    fn rearm(&mut self, mut timer: Timer) -> Option<Timer> {
+        timer.deadline += timer.period?;
+        if timer.deadline <= self.current {
+            return Some(timer);
+        }
+        self.insert(timer);
+        None
+    }
+
+    /// Advances the wheel and runs the expired callbacks. The callbacks run
+    /// while `wheel` is unlocked, so they may schedule or cancel timers.
+    pub fn advance(wheel: &spin::Mutex<TimerWheel>, now: u64) {
+        let expired = match wheel.try_lock() {
+            Some(mut wheel) => wheel.expire(now),
+            // Someone is scheduling a timer, the missed ticks are caught up next time
+            None => return,
+        };
+
+        for timer in expired {
+            let mut due = Some(timer);
+            while let Some(mut timer) = due {
+                (timer.callback)();
+                due = wheel.lock().rearm(timer);
+            }
+        }
+    }
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        Self::new()
+    }
+}